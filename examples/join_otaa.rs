@@ -3,8 +3,6 @@ mod common;
 use std::env;
 use std::io::{stdout, Write};
 
-use env_logger;
-
 use rn2xx3::{ConfirmationMode, DataRateEuCn, JoinMode};
 
 fn main() {
@@ -2,8 +2,6 @@ mod common;
 
 use std::env;
 
-use env_logger;
-
 fn main() {
     env_logger::init();
 
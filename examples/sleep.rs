@@ -3,7 +3,6 @@ mod common;
 use std::env;
 use std::time::Duration;
 
-use env_logger;
 use rn2xx3::errors::Error;
 
 fn main() {
@@ -0,0 +1,327 @@
+//! Driver for modules whose model and band are only known at runtime.
+//!
+//! The [`Frequency`](../trait.Frequency.html) type parameter of the
+//! [`Driver`](../struct.Driver.html) selects the band at compile time. If the
+//! same binary should work with any module (e.g. a configuration tool), use
+//! [`detect`](../fn.detect.html) to query the module and create a matching
+//! driver:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use rn2xx3::any::{AnyDriver, DataRate};
+//! use rn2xx3::{DataRateEuCn, DataRateUs};
+//!
+//! let mut rn = rn2xx3::detect(serialport).expect("Could not detect module");
+//! let data_rate = match rn {
+//!     AnyDriver::Rn2903_915(_) => DataRate::Us(DataRateUs::Sf7Bw125),
+//!     _ => DataRate::EuCn(DataRateEuCn::Sf7Bw125),
+//! };
+//! rn.set_data_rate(data_rate).expect("Could not set data rate");
+//! ```
+//!
+//! RN2483 modules keep the band they are configured for. To select the band
+//! instead (resetting the MAC of RN2483 modules), use
+//! [`detect_with_band`](../fn.detect_with_band.html):
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use rn2xx3::any::Band;
+//!
+//! let rn = rn2xx3::detect_with_band(serialport, Band::Mhz433).expect("Could not detect module");
+//! ```
+//!
+//! Commands that are not available on `AnyDriver` can be sent by matching on
+//! the variants.
+
+use crate::errors::{Error, JoinError, RnResult, TransitionError, TxError};
+use crate::serial::{Serial, TryRead};
+use crate::timeout::{Clock, NoClock, Timer};
+use crate::{
+    ConfirmationMode, DataRateEuCn, DataRateUs, Downlink, Driver, Freq433, Freq868, Freq915,
+    Frequency, JoinMode, Model,
+};
+
+/// A driver for any supported module and band.
+pub enum AnyDriver<S, C = NoClock> {
+    /// RN2483 module, configured for the 433 MHz band.
+    Rn2483_433(Driver<Freq433, S, C>),
+    /// RN2483 module, configured for the 868 MHz band.
+    Rn2483_868(Driver<Freq868, S, C>),
+    /// RN2903 module (915 MHz).
+    Rn2903_915(Driver<Freq915, S, C>),
+}
+
+/// A band of the RN2483, see
+/// [`detect_with_band`](../fn.detect_with_band.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Band {
+    /// The 433 MHz band.
+    Mhz433,
+    /// The 868 MHz band.
+    Mhz868,
+}
+
+/// A data rate of any band.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DataRate {
+    /// A data rate valid in Europe and China (RN2483).
+    EuCn(DataRateEuCn),
+    /// A data rate valid in the USA (RN2903).
+    Us(DataRateUs),
+}
+
+impl From<DataRateEuCn> for DataRate {
+    fn from(data_rate: DataRateEuCn) -> Self {
+        DataRate::EuCn(data_rate)
+    }
+}
+
+impl From<DataRateUs> for DataRate {
+    fn from(data_rate: DataRateUs) -> Self {
+        DataRate::Us(data_rate)
+    }
+}
+
+/// Call the same expression for every variant of the driver.
+macro_rules! dispatch {
+    ($driver:expr, $rn:ident => $body:expr) => {
+        match $driver {
+            AnyDriver::Rn2483_433($rn) => $body,
+            AnyDriver::Rn2483_868($rn) => $body,
+            AnyDriver::Rn2903_915($rn) => $body,
+        }
+    };
+}
+
+/// Detect the model and band of the module, see
+/// [`detect`](../fn.detect.html) and
+/// [`detect_with_band`](../fn.detect_with_band.html).
+///
+/// If `band` is set, the MAC of RN2483 modules is reset to that band instead
+/// of querying the configured band.
+pub(crate) fn detect<S, E>(
+    mut driver: Driver<Freq868, S>,
+    band: Option<Band>,
+) -> Result<AnyDriver<S>, TransitionError<S, Error<E>>>
+where
+    S: Serial<Error = E>,
+{
+    match (driver.model(), band) {
+        (Err(error), _) => Err(free(TransitionError {
+            state: driver,
+            error,
+        })),
+        (Ok(Model::RN2903), _) => Ok(AnyDriver::Rn2903_915(driver.into_frequency())),
+        (Ok(Model::RN2483), Some(Band::Mhz433)) => {
+            driver.into_band().map(AnyDriver::Rn2483_433).map_err(free)
+        }
+        (Ok(Model::RN2483), Some(Band::Mhz868)) => {
+            driver.into_band().map(AnyDriver::Rn2483_868).map_err(free)
+        }
+        (Ok(Model::RN2483), None) => match driver.get_band() {
+            Ok(Band::Mhz433) => Ok(AnyDriver::Rn2483_433(driver.into_frequency())),
+            Ok(Band::Mhz868) => Ok(AnyDriver::Rn2483_868(driver)),
+            Err(error) => Err(free(TransitionError {
+                state: driver,
+                error,
+            })),
+        },
+    }
+}
+
+/// Replace the driver of a failed detection with its serial port.
+fn free<F, S, E>(failed: TransitionError<Driver<F, S>, Error<E>>) -> TransitionError<S, Error<E>>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+{
+    TransitionError {
+        state: failed.state.free(),
+        error: failed.error,
+    }
+}
+
+impl<S, C, E> AnyDriver<S, C>
+where
+    S: Serial<Error = E>,
+    C: Timer<S>,
+{
+    /// Return the model of the module, without sending a command.
+    pub fn model(&self) -> Model {
+        match self {
+            AnyDriver::Rn2483_433(_) | AnyDriver::Rn2483_868(_) => Model::RN2483,
+            AnyDriver::Rn2903_915(_) => Model::RN2903,
+        }
+    }
+
+    /// Use the specified clock to enforce read timeouts, see
+    /// [`Driver::with_clock`](../struct.Driver.html#method.with_clock).
+    pub fn with_clock<C2: Clock>(self, clock: C2) -> AnyDriver<S, C2>
+    where
+        S: TryRead,
+    {
+        match self {
+            AnyDriver::Rn2483_433(rn) => AnyDriver::Rn2483_433(rn.with_clock(clock)),
+            AnyDriver::Rn2483_868(rn) => AnyDriver::Rn2483_868(rn.with_clock(clock)),
+            AnyDriver::Rn2903_915(rn) => AnyDriver::Rn2903_915(rn.with_clock(clock)),
+        }
+    }
+
+    /// Destroy this driver instance, return the wrapped serial device.
+    pub fn free(self) -> S {
+        dispatch!(self, rn => rn.free())
+    }
+
+    /// Set the data rate to be used for the following transmissions.
+    ///
+    /// If the data rate does not belong to the band of the module,
+    /// `Error::BadParameter` is returned.
+    pub fn set_data_rate(&mut self, data_rate: DataRate) -> RnResult<(), E> {
+        match (self, data_rate) {
+            (AnyDriver::Rn2483_433(rn), DataRate::EuCn(dr)) => rn.set_data_rate(dr),
+            (AnyDriver::Rn2483_868(rn), DataRate::EuCn(dr)) => rn.set_data_rate(dr),
+            (AnyDriver::Rn2903_915(rn), DataRate::Us(dr)) => rn.set_data_rate(dr),
+            _ => Err(Error::BadParameter),
+        }
+    }
+
+    /// Return the currently configured data rate.
+    pub fn get_data_rate(&mut self) -> RnResult<DataRate, E> {
+        match self {
+            AnyDriver::Rn2483_433(rn) => rn.get_data_rate().map(DataRate::EuCn),
+            AnyDriver::Rn2483_868(rn) => rn.get_data_rate().map(DataRate::EuCn),
+            AnyDriver::Rn2903_915(rn) => rn.get_data_rate().map(DataRate::Us),
+        }
+    }
+
+    /// Join the network, see
+    /// [`Driver::join`](../struct.Driver.html#method.join).
+    pub fn join(&mut self, mode: JoinMode) -> Result<(), JoinError<E>> {
+        dispatch!(self, rn => rn.join(mode))
+    }
+
+    /// Send a hex uplink on the specified port, see
+    /// [`Driver::transmit_hex`](../struct.Driver.html#method.transmit_hex).
+    pub fn transmit_hex(
+        &mut self,
+        mode: ConfirmationMode,
+        port: u8,
+        data: &str,
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        dispatch!(self, rn => rn.transmit_hex(mode, port, data))
+    }
+
+    /// Send an uplink on the specified port, see
+    /// [`Driver::transmit_slice`](../struct.Driver.html#method.transmit_slice).
+    pub fn transmit_slice(
+        &mut self,
+        mode: ConfirmationMode,
+        port: u8,
+        data: &[u8],
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        dispatch!(self, rn => rn.transmit_slice(mode, port, data))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "embedded-hal-02")]
+mod tests {
+    use super::*;
+
+    use embedded_hal_mock::serial::{Mock as SerialMock, Transaction};
+
+    use crate::testing::version;
+
+    #[test]
+    fn detect_rn2483_433() {
+        let mut expectations = version(b"RN2483 1.0.3 Mar 22 2017 06:00:42");
+        expectations.extend(vec![
+            Transaction::write_many(b"mac get band\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"433\r\n"),
+            Transaction::write_many(b"mac set dr 5\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = crate::detect(mock.clone()).unwrap();
+        assert!(matches!(rn, AnyDriver::Rn2483_433(_)));
+        assert_eq!(rn.model(), Model::RN2483);
+        assert_eq!(
+            rn.set_data_rate(DataRate::Us(DataRateUs::Sf7Bw125)),
+            Err(Error::BadParameter)
+        );
+        assert_eq!(rn.set_data_rate(DataRateEuCn::Sf7Bw125.into()), Ok(()));
+        mock.done();
+    }
+
+    #[test]
+    fn detect_rn2903() {
+        let mut expectations = version(b"RN2903 1.0.3 Mar 22 2017 06:00:42");
+        expectations.extend(vec![
+            Transaction::write_many(b"mac get dr\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"4\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = crate::detect(mock.clone()).unwrap();
+        assert_eq!(rn.model(), Model::RN2903);
+        assert_eq!(rn.get_data_rate(), Ok(DataRate::Us(DataRateUs::Sf8Bw500)));
+        mock.done();
+    }
+
+    /// If the module cannot be detected, the serial port is returned.
+    #[test]
+    fn detect_unknown() {
+        let expectations = version(b"RN2");
+        let mut mock = SerialMock::new(&expectations);
+        let failed = crate::detect(mock.clone()).err().unwrap();
+        assert_eq!(failed.error, Error::ParsingError);
+        let _serial: SerialMock<u8> = failed.state;
+        mock.done();
+    }
+
+    #[test]
+    fn detect_with_band_rn2483() {
+        let mut expectations = version(b"RN2483 1.0.3 Mar 22 2017 06:00:42");
+        expectations.extend(vec![
+            Transaction::write_many(b"mac reset 433\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let rn = crate::detect_with_band(mock.clone(), Band::Mhz433).unwrap();
+        assert!(matches!(rn, AnyDriver::Rn2483_433(_)));
+        mock.done();
+    }
+
+    /// The band is ignored for RN2903 modules.
+    #[test]
+    fn detect_with_band_rn2903() {
+        let expectations = version(b"RN2903 1.0.3 Mar 22 2017 06:00:42");
+        let mut mock = SerialMock::new(&expectations);
+        let rn = crate::detect_with_band(mock.clone(), Band::Mhz868).unwrap();
+        assert!(matches!(rn, AnyDriver::Rn2903_915(_)));
+        mock.done();
+    }
+
+    #[test]
+    fn detect_with_band_failed() {
+        let mut expectations = version(b"RN2483 1.0.3 Mar 22 2017 06:00:42");
+        expectations.extend(vec![
+            Transaction::write_many(b"mac reset 868\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"invalid_param\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let failed = crate::detect_with_band(mock.clone(), Band::Mhz868)
+            .err()
+            .unwrap();
+        assert_eq!(failed.error, Error::CommandFailed);
+        mock.done();
+    }
+}
@@ -0,0 +1,712 @@
+//! Async driver, built on top of the `embedded-io-async` traits.
+//!
+//! The [`AsyncDriver`](struct.AsyncDriver.html) mirrors the API of the
+//! blocking [`Driver`](../struct.Driver.html), but all methods that talk to
+//! the module are `async fn`s. This way, waiting for long running commands
+//! (e.g. the second response of `mac join` or `mac tx`) does not block the
+//! executor.
+//!
+//! Commands are encoded and responses are parsed exactly the same way as in
+//! the blocking driver. This includes asynchronous events (e.g. Class C
+//! downlinks) that arrive while waiting for a command response: They are
+//! queued and returned by [`wait_event`](struct.AsyncDriver.html#method.wait_event).
+//!
+//! This module is only available if the `async` feature is enabled, which
+//! requires Rust 1.75 or newer.
+
+use core::marker::PhantomData;
+use core::str::from_utf8;
+use core::time::Duration;
+
+use embedded_io_async::{Read, ReadReady, Write};
+use numtoa::NumToA;
+
+#[cfg(feature = "logging")]
+use crate::LoggableStrSlice;
+
+use crate::errors::{Error, JoinError, RnResult, TxError};
+use crate::protocol::{self, hex_setter_getter, EventQueue, LineBuffer};
+use crate::{
+    utils, ConfirmationMode, DataRateEuCn, DataRateUs, Downlink, Event, Freq433, Freq868, Freq915,
+    Frequency, JoinMode, Model,
+};
+
+/// The async driver instance.
+pub struct AsyncDriver<F: Frequency, S> {
+    /// Marker type with the module frequency.
+    frequency: PhantomData<F>,
+
+    /// Serial port.
+    serial: S,
+
+    /// Read buffer.
+    read_buf: LineBuffer,
+
+    /// This flag is set when entering sleep mode. As long as it is set,
+    /// sending any command will be prevented.
+    sleep: bool,
+
+    /// Asynchronous events that were received while waiting for command
+    /// responses.
+    pending_events: EventQueue,
+}
+
+/// Create a new async driver instance for the RN2483 (433 MHz), wrapping the
+/// specified serial port.
+pub fn rn2483_433<S, E>(serial: S) -> AsyncDriver<Freq433, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+{
+    AsyncDriver::new(serial)
+}
+
+/// Create a new async driver instance for the RN2483 (868 MHz), wrapping the
+/// specified serial port.
+pub fn rn2483_868<S, E>(serial: S) -> AsyncDriver<Freq868, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+{
+    AsyncDriver::new(serial)
+}
+
+/// Create a new async driver instance for the RN2903 (915 MHz), wrapping the
+/// specified serial port.
+pub fn rn2903_915<S, E>(serial: S) -> AsyncDriver<Freq915, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+{
+    AsyncDriver::new(serial)
+}
+
+/// Basic commands.
+impl<F, S, E> AsyncDriver<F, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+    F: Frequency,
+{
+    fn new(serial: S) -> Self {
+        Self {
+            frequency: PhantomData,
+            serial,
+            read_buf: LineBuffer::new(),
+            sleep: false,
+            pending_events: EventQueue::new(),
+        }
+    }
+
+    /// Ensure that the device is not currently in sleep mode.
+    ///
+    /// Returns `Error::SleepMode` if `self.sleep` is set.
+    fn ensure_not_in_sleep_mode(&self) -> RnResult<(), E> {
+        if self.sleep {
+            Err(Error::SleepMode)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write all bytes from the buffer to the serial port.
+    async fn write_all(&mut self, buffer: &[u8]) -> RnResult<(), E> {
+        self.ensure_not_in_sleep_mode()?;
+        self.serial
+            .write_all(buffer)
+            .await
+            .map_err(Error::SerialWrite)
+    }
+
+    /// Write CR+LF bytes and flush the serial port.
+    async fn write_crlf(&mut self) -> RnResult<(), E> {
+        self.write_all(&[protocol::CR, protocol::LF]).await?;
+        self.serial.flush().await.map_err(Error::SerialWrite)
+    }
+
+    /// Read a CR/LF terminated line from the serial port.
+    ///
+    /// The string is returned without the line termination.
+    pub async fn read_line(&mut self) -> RnResult<&[u8], E> {
+        let line_len = self.wait_for_line().await?;
+        let line = self.read_buf.take_line(line_len);
+        #[cfg(feature = "logging")]
+        log::debug!(
+            "Received response: {:?}",
+            from_utf8(line).unwrap_or("\"[invalid-utf8]\"")
+        );
+        Ok(line)
+    }
+
+    /// Read from the serial port until a complete line is buffered.
+    ///
+    /// Return the length of the line, which is not yet marked as consumed.
+    async fn wait_for_line(&mut self) -> RnResult<usize, E> {
+        loop {
+            if let Some(line_len) = self.read_buf.find_line() {
+                return Ok(line_len);
+            }
+            let spare = self.read_buf.spare()?;
+            let count = self.serial.read(spare).await.map_err(Error::SerialRead)?;
+            self.read_buf.commit(count);
+        }
+    }
+
+    /// Read the response to a command.
+    ///
+    /// Asynchronous events that arrive before the response are queued, see
+    /// [`Driver::send_raw_command`](../struct.Driver.html#method.send_raw_command).
+    async fn read_response(&mut self) -> RnResult<&[u8], E> {
+        loop {
+            self.read_line().await?;
+            if !self.pending_events.queue_event(self.read_buf.last_line()) {
+                return Ok(self.read_buf.last_line());
+            }
+        }
+    }
+
+    /// Send a raw command to the module and do not wait for the response.
+    ///
+    /// **Note:** If you use this for a command that returns a response (e.g.
+    /// `sleep`), you will have to manually read the response using the
+    /// `read_line()` method.
+    pub async fn send_raw_command_nowait(&mut self, command: &[&str]) -> RnResult<(), E> {
+        #[cfg(feature = "logging")]
+        log::debug!("Sending command: \"{}\"", LoggableStrSlice(command));
+        for part in command {
+            self.write_all(part.as_bytes()).await?;
+        }
+        self.write_crlf().await
+    }
+
+    /// Send a raw command to the module and return the response.
+    ///
+    /// Asynchronous events received while waiting for the response are kept
+    /// for [`wait_event`](#method.wait_event).
+    pub async fn send_raw_command(&mut self, command: &[&str]) -> RnResult<&[u8], E> {
+        self.send_raw_command_nowait(command).await?;
+        self.read_response().await
+    }
+
+    /// Wait for the next asynchronous event and return it.
+    ///
+    /// See [`Driver::wait_event`](../struct.Driver.html#method.wait_event).
+    pub async fn wait_event(&mut self) -> RnResult<Event<'_>, E> {
+        if self.pending_events.is_empty() {
+            self.wait_for_line().await?;
+        }
+        protocol::take_event(&mut self.pending_events, &mut self.read_buf)
+    }
+
+    /// Send a raw command and decode the resulting bytes to a `&str`.
+    pub async fn send_raw_command_str(&mut self, command: &[&str]) -> RnResult<&str, E> {
+        let bytes = self.send_raw_command(command).await?;
+        Ok(from_utf8(bytes)?)
+    }
+
+    /// Send a raw command that should be confirmed with 'OK'. If the response
+    /// is not 'OK', return `Error::CommandFailed`.
+    async fn send_raw_command_ok(&mut self, command: &[&str]) -> RnResult<(), E> {
+        let response = self.send_raw_command(command).await?;
+        protocol::parse_ok(response)
+    }
+}
+
+impl<F, S, E> AsyncDriver<F, S>
+where
+    S: Read<Error = E> + Write<Error = E> + ReadReady,
+    F: Frequency,
+{
+    /// Clear the module serial buffers and ensure a known good state.
+    ///
+    /// See [`Driver::ensure_known_state`](../struct.Driver.html#method.ensure_known_state)
+    /// for the implementation details.
+    pub async fn ensure_known_state(&mut self) -> RnResult<(), E> {
+        // First, clear the input buffer
+        self.read_buf.clear();
+        let mut discard = [0; 16];
+        while self.serial.read_ready().map_err(Error::SerialRead)? {
+            let _count = self
+                .serial
+                .read(&mut discard)
+                .await
+                .map_err(Error::SerialRead)?;
+            #[cfg(feature = "logging")]
+            log::debug!("Clearing input buffer: Discarded {} bytes", _count);
+        }
+        #[cfg(feature = "logging")]
+        log::debug!("Input buffer is clear");
+
+        // Max 3 attempts
+        for _ in 0..3 {
+            #[cfg(feature = "logging")]
+            log::debug!("Check whether module is in a known state, expecting \"invalid_param\"");
+
+            // To ensure that there's no valid command in the input buffer, write
+            // the letter 'z' followed by CRLF.
+            self.write_all(b"z").await?;
+            self.write_crlf().await?;
+
+            // Read the response, it should be "invalid_param".
+            match self.read_response().await? {
+                b"invalid_param" => return Ok(()),
+                _other => {
+                    #[cfg(feature = "logging")]
+                    log::debug!("Error: Module returned \"{:?}\"", _other);
+                }
+            }
+        }
+
+        // Should not happen™
+        Err(Error::InvalidState)
+    }
+}
+
+/// System commands.
+impl<F, S, E> AsyncDriver<F, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+    F: Frequency,
+{
+    /// Destroy this driver instance, return the wrapped serial device.
+    pub fn free(self) -> S {
+        self.serial
+    }
+
+    /// Reset and restart the RN module. Return the version string.
+    pub async fn reset(&mut self) -> RnResult<&str, E> {
+        self.send_raw_command_str(&["sys reset"]).await
+    }
+
+    /// Reset the module's configuration data and user EEPROM to factory
+    /// default values and restart the module.
+    ///
+    /// All configuration parameters will be restored to factory default
+    /// values. Return the version string.
+    pub async fn factory_reset(&mut self) -> RnResult<&str, E> {
+        self.send_raw_command_str(&["sys factoryRESET"]).await
+    }
+
+    /// Return the preprogrammed EUI node address as uppercase hex string.
+    pub async fn hweui(&mut self) -> RnResult<&str, E> {
+        self.send_raw_command_str(&["sys get hweui"]).await
+    }
+
+    /// Return the version string.
+    pub async fn version(&mut self) -> RnResult<&str, E> {
+        self.send_raw_command_str(&["sys get ver"]).await
+    }
+
+    /// Return the model of the module.
+    pub async fn model(&mut self) -> RnResult<Model, E> {
+        let version = self.version().await?;
+        protocol::parse_model(version)
+    }
+
+    /// Measure and return the Vdd voltage in millivolts.
+    pub async fn vdd(&mut self) -> RnResult<u16, E> {
+        let vdd = self.send_raw_command_str(&["sys get vdd"]).await?;
+        protocol::parse_number(vdd)
+    }
+
+    /// Set the NVM byte at `addr` to the specified value.
+    ///
+    /// The address must be between 0x300 and 0x3ff, otherwise
+    /// `Error::BadParameter` is returned.
+    pub async fn nvm_set(&mut self, addr: u16, byte: u8) -> RnResult<(), E> {
+        let mut hex_addr_buf = [0; 4];
+        let hex_addr = protocol::encode_nvm_addr(addr, &mut hex_addr_buf)?;
+
+        let mut hex_byte_buf = [0; 2];
+        let hex_byte = protocol::encode_nvm_byte(byte, &mut hex_byte_buf);
+
+        let args = ["sys set nvm ", hex_addr, " ", hex_byte];
+        self.send_raw_command_ok(&args).await
+    }
+
+    /// Get the NVM byte at `addr`.
+    ///
+    /// The address must be between 0x300 and 0x3ff, otherwise
+    /// `Error::BadParameter` is returned.
+    pub async fn nvm_get(&mut self, addr: u16) -> RnResult<u8, E> {
+        let mut hex_addr_buf = [0; 4];
+        let hex_addr = protocol::encode_nvm_addr(addr, &mut hex_addr_buf)?;
+
+        let response = self.send_raw_command(&["sys get nvm ", hex_addr]).await?;
+        protocol::parse_nvm_byte(response)
+    }
+
+    /// Put the system to sleep (with millisecond precision).
+    ///
+    /// **Note:** This command will *not* wait for the module to wake up. You
+    /// need to call [`wait_for_wakeup()`][wait_for_wakeup] to wait for the
+    /// module before sending any other command.
+    ///
+    /// [wait_for_wakeup]: #method.wait_for_wakeup
+    pub async fn sleep(&mut self, duration: Duration) -> RnResult<(), E> {
+        let millis = protocol::sleep_millis(duration)?;
+
+        let mut buf = [0u8; 10];
+        self.send_raw_command_nowait(&["sys sleep ", millis.numtoa_str(10, &mut buf)])
+            .await?;
+        self.sleep = true;
+        Ok(())
+    }
+
+    /// After [sleep mode][sleep] has been enabled, wait for wakeup and clear
+    /// the `sleep` flag.
+    ///
+    /// See [`Driver::wait_for_wakeup`](../struct.Driver.html#method.wait_for_wakeup)
+    /// for a description of the `force` flag.
+    ///
+    /// [sleep]: #method.sleep
+    pub async fn wait_for_wakeup(&mut self, force: bool) -> RnResult<(), E> {
+        // If no sleep is in progress, return immediately
+        if !force && !self.sleep {
+            return Ok(());
+        }
+
+        // Wait for "ok" response.
+        // If any response is returned, the `sleep` flag will be cleared.
+        let response = self.read_line().await?;
+        let result = protocol::parse_wakeup(response);
+        self.sleep = false;
+        result
+    }
+}
+
+/// MAC commands.
+impl<F, S, E> AsyncDriver<F, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+    F: Frequency,
+{
+    /// Save MAC configuration parameters.
+    ///
+    /// See [`Driver::save_config`](../struct.Driver.html#method.save_config)
+    /// for the list of saved parameters.
+    pub async fn save_config(&mut self) -> RnResult<(), E> {
+        self.send_raw_command_ok(&["mac save"]).await
+    }
+
+    hex_setter_getter!(
+        async "devaddr",
+        4,
+        "the unique network device address",
+        set_dev_addr_hex,
+        set_dev_addr_slice,
+        get_dev_addr_hex,
+        get_dev_addr_slice,
+    );
+
+    hex_setter_getter!(
+        async "deveui",
+        8,
+        "the globally unique device identifier",
+        set_dev_eui_hex,
+        set_dev_eui_slice,
+        get_dev_eui_hex,
+        get_dev_eui_slice,
+    );
+
+    hex_setter_getter!(
+        async "appeui",
+        8,
+        "the globally unique application identifier",
+        set_app_eui_hex,
+        set_app_eui_slice,
+        get_app_eui_hex,
+        get_app_eui_slice,
+    );
+
+    hex_setter_getter!(
+        async "nwkskey",
+        16,
+        "the network session key",
+        set_network_session_key_hex,
+        set_network_session_key_slice,
+    );
+
+    hex_setter_getter!(
+        async "appskey",
+        16,
+        "the application session key",
+        set_app_session_key_hex,
+        set_app_session_key_slice,
+    );
+
+    hex_setter_getter!(
+        async "appkey",
+        16,
+        "the application key",
+        set_app_key_hex,
+        set_app_key_slice,
+    );
+
+    /// Set whether the ADR (adaptive data rate) mechanism is enabled.
+    pub async fn set_adr(&mut self, enabled: bool) -> RnResult<(), E> {
+        self.send_raw_command_ok(&["mac set adr ", protocol::on_off(enabled)])
+            .await
+    }
+
+    /// Return whether the ADR (adaptive data rate) mechanism is enabled.
+    pub async fn get_adr(&mut self) -> RnResult<bool, E> {
+        let adr = self.send_raw_command_str(&["mac get adr"]).await?;
+        protocol::parse_on_off(adr)
+    }
+
+    /// Set the up frame counter.
+    pub async fn set_upctr(&mut self, upctr: u32) -> RnResult<(), E> {
+        let mut buf = [0u8; 10];
+        self.send_raw_command_ok(&["mac set upctr ", upctr.numtoa_str(10, &mut buf)])
+            .await
+    }
+
+    /// Get the up frame counter.
+    pub async fn get_upctr(&mut self) -> RnResult<u32, E> {
+        let ctr = self.send_raw_command_str(&["mac get upctr"]).await?;
+        protocol::parse_number(ctr)
+    }
+
+    /// Set the down frame counter.
+    pub async fn set_dnctr(&mut self, dnctr: u32) -> RnResult<(), E> {
+        let mut buf = [0u8; 10];
+        self.send_raw_command_ok(&["mac set dnctr ", dnctr.numtoa_str(10, &mut buf)])
+            .await
+    }
+
+    /// Get the down frame counter.
+    pub async fn get_dnctr(&mut self) -> RnResult<u32, E> {
+        let ctr = self.send_raw_command_str(&["mac get dnctr"]).await?;
+        protocol::parse_number(ctr)
+    }
+
+    /// Join the network.
+    pub async fn join(&mut self, mode: JoinMode) -> Result<(), JoinError<E>> {
+        let mode_str = protocol::join_mode_str(mode);
+
+        // First response is whether the join procedure was initialized properly.
+        let response = self.send_raw_command_str(&["mac join ", mode_str]).await?;
+        protocol::parse_join_response(response)?;
+
+        // Second response indicates whether the join procedure succeeded.
+        let result = self.read_line().await?;
+        protocol::parse_join_result(result)
+    }
+
+    /// Send a hex uplink on the specified port.
+    ///
+    /// If a downlink is received, it is returned.
+    pub async fn transmit_hex(
+        &mut self,
+        mode: ConfirmationMode,
+        port: u8,
+        data: &str,
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        // Validate and parse arguments
+        let mode_str = protocol::validate_tx(mode, port, data)?;
+        let mut buf = [0; 3];
+        let port_str = utils::u8_to_str(port, &mut buf)?;
+
+        // First response is whether the uplink transmission could be initialized.
+        let response = self
+            .send_raw_command(&["mac tx ", mode_str, " ", port_str, " ", data])
+            .await?;
+        protocol::parse_tx_response(response)?;
+
+        // The second response could contain an error or a downlink.
+        let result = self.read_line().await?;
+        protocol::parse_tx_result(result, mode)
+    }
+
+    /// Send an uplink on the specified port.
+    ///
+    /// If a downlink is received, it is returned.
+    pub async fn transmit_slice(
+        &mut self,
+        mode: ConfirmationMode,
+        port: u8,
+        data: &[u8],
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        let mut buf = [0; 256];
+        self.transmit_hex(mode, port, protocol::encode_tx_data(data, &mut buf)?)
+            .await
+    }
+}
+
+/// MAC commands for 433 MHz modules.
+impl<S, E> AsyncDriver<Freq433, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+{
+    /// Set the data rate to be used for the following transmissions.
+    pub async fn set_data_rate(&mut self, data_rate: DataRateEuCn) -> RnResult<(), E> {
+        self.send_raw_command_ok(&["mac set dr ", data_rate.into()])
+            .await
+    }
+
+    /// Return the currently configured data rate.
+    pub async fn get_data_rate(&mut self) -> RnResult<DataRateEuCn, E> {
+        let dr = self.send_raw_command_str(&["mac get dr"]).await?;
+        protocol::parse_variant(dr)
+    }
+}
+
+/// MAC commands for 868 MHz modules.
+impl<S, E> AsyncDriver<Freq868, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+{
+    /// Set the data rate to be used for the following transmissions.
+    pub async fn set_data_rate(&mut self, data_rate: DataRateEuCn) -> RnResult<(), E> {
+        self.send_raw_command_ok(&["mac set dr ", data_rate.into()])
+            .await
+    }
+
+    /// Return the currently configured data rate.
+    pub async fn get_data_rate(&mut self) -> RnResult<DataRateEuCn, E> {
+        let dr = self.send_raw_command_str(&["mac get dr"]).await?;
+        protocol::parse_variant(dr)
+    }
+}
+
+/// MAC commands for 915 MHz modules.
+impl<S, E> AsyncDriver<Freq915, S>
+where
+    S: Read<Error = E> + Write<Error = E>,
+{
+    /// Set the data rate to be used for the following transmissions.
+    pub async fn set_data_rate(&mut self, data_rate: DataRateUs) -> RnResult<(), E> {
+        self.send_raw_command_ok(&["mac set dr ", data_rate.into()])
+            .await
+    }
+
+    /// Return the currently configured data rate.
+    pub async fn get_data_rate(&mut self) -> RnResult<DataRateUs, E> {
+        let dr = self.send_raw_command_str(&["mac get dr"]).await?;
+        protocol::parse_variant(dr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    use embassy_futures::block_on;
+
+    use crate::testing::ScriptedSerial;
+
+    #[test]
+    fn version() {
+        let serial = ScriptedSerial::new(&[b"RN2483 1.0.3 Mar 22 ", b"2017 06:00:42\r\n"]);
+        let mut rn = rn2483_868(serial);
+        assert_eq!(
+            block_on(rn.version()).unwrap(),
+            "RN2483 1.0.3 Mar 22 2017 06:00:42"
+        );
+        rn.free().done(b"sys get ver\r\n");
+    }
+
+    #[test]
+    fn nvm_get() {
+        let serial = ScriptedSerial::new(&[b"ff\r\n"]);
+        let mut rn = rn2483_868(serial);
+        assert_eq!(block_on(rn.nvm_get(0x300)).unwrap(), 0xff);
+        assert_eq!(block_on(rn.nvm_get(0x400)), Err(Error::BadParameter));
+        rn.free().done(b"sys get nvm 300\r\n");
+    }
+
+    #[test]
+    fn set_dev_eui_slice() {
+        let serial = ScriptedSerial::new(&[b"ok\r\n"]);
+        let mut rn = rn2483_868(serial);
+        assert!(
+            block_on(rn.set_dev_eui_slice(&[0x00, 0x04, 0xa3, 0x0b, 0x00, 0x1a, 0x55, 0xed]))
+                .is_ok()
+        );
+        rn.free().done(b"mac set deveui 0004a30b001a55ed\r\n");
+    }
+
+    #[test]
+    fn get_data_rate_us() {
+        let serial = ScriptedSerial::new(&[b"4\r\n"]);
+        let mut rn = rn2903_915(serial);
+        assert_eq!(block_on(rn.get_data_rate()).unwrap(), DataRateUs::Sf8Bw500);
+        rn.free().done(b"mac get dr\r\n");
+    }
+
+    #[test]
+    fn join_otaa_single_read() {
+        // Both responses arrive in a single read
+        let serial = ScriptedSerial::new(&[b"ok\r\naccepted\r\n"]);
+        let mut rn = rn2483_868(serial);
+        assert_eq!(block_on(rn.join(JoinMode::Otaa)), Ok(()));
+        rn.free().done(b"mac join otaa\r\n");
+    }
+
+    #[test]
+    fn join_otaa_denied() {
+        let serial = ScriptedSerial::new(&[b"ok\r\n", b"denied\r\n"]);
+        let mut rn = rn2483_868(serial);
+        assert_eq!(
+            block_on(rn.join(JoinMode::Otaa)),
+            Err(JoinError::JoinUnsuccessful)
+        );
+        rn.free().done(b"mac join otaa\r\n");
+    }
+
+    #[test]
+    fn transmit_hex_downlink() {
+        let serial = ScriptedSerial::new(&[b"ok\r\nmac_r", b"x 101 000102feff\r\n"]);
+        let mut rn = rn2483_868(serial);
+        assert_eq!(
+            block_on(rn.transmit_hex(ConfirmationMode::Unconfirmed, 42, "23ff")),
+            Ok(Some(Downlink {
+                port: 101,
+                hexdata: "000102feff",
+                acknowledged: false,
+                multicast: None,
+            }))
+        );
+        rn.free().done(b"mac tx uncnf 42 23ff\r\n");
+    }
+
+    #[test]
+    fn sleep_mode_no_write() {
+        let serial = ScriptedSerial::new(&[b"ok\r\n"]);
+        let mut rn = rn2483_868(serial);
+        block_on(rn.sleep(Duration::from_secs(1))).unwrap();
+        assert_eq!(block_on(rn.version()), Err(Error::SleepMode));
+        assert_eq!(block_on(rn.wait_for_wakeup(false)), Ok(()));
+        rn.free().done(b"sys sleep 1000\r\n");
+    }
+
+    #[test]
+    fn events_around_response() {
+        let serial = ScriptedSerial::new(&[
+            b"mac_rx 1 aa\r\nmac_rx 2 bb\r\n",
+            b"RN2483 1.0.3 Mar 22 2017 06:00:42\r\n",
+            b"mac_err\r\n",
+        ]);
+        let mut rn = rn2483_868(serial);
+        assert_eq!(
+            block_on(rn.version()).unwrap(),
+            "RN2483 1.0.3 Mar 22 2017 06:00:42"
+        );
+        for port in 1..=2 {
+            match block_on(rn.wait_event()).unwrap() {
+                Event::Downlink(downlink) => assert_eq!(downlink.port(), port),
+                other => panic!("Unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(block_on(rn.wait_event()), Ok(Event::MacError));
+        rn.free().done(b"sys get ver\r\n");
+    }
+
+    #[test]
+    fn ensure_known_state() {
+        let mut serial = ScriptedSerial::new(&[b"sys ", b"res", b"invalid_param\r\n"]);
+        serial.ready = 2;
+        let mut rn = rn2483_868(serial);
+        block_on(rn.ensure_known_state()).unwrap();
+        rn.free().done(b"z\r\n");
+    }
+}
@@ -0,0 +1,791 @@
+//! Firmware updates over the UART bootloader.
+//!
+//! After the application firmware has been erased with
+//! [`erase_fw`](../struct.Driver.html#method.erase_fw), the module runs the
+//! Microchip UART bootloader. The [`Bootloader`](struct.Bootloader.html)
+//! writes a new firmware image (in Intel HEX format) to the flash memory of
+//! the module and verifies it:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use rn2xx3::bootloader::HexImage;
+//!
+//! let text = std::fs::read_to_string("firmware.hex").expect("Could not read image");
+//! let image = HexImage::parse(&text).expect("Invalid image");
+//!
+//! let rn = rn2xx3::rn2483_868(serialport);
+//! let mut bootloader = rn.erase_fw().map_err(|e| e.error).expect("Could not erase firmware");
+//!
+//! // Program memory of the module (64 KiB)
+//! let flash = 0x0000..0x1_0000;
+//! bootloader
+//!     .program(&image, flash, |progress| println!("{:?}", progress))
+//!     .expect("Could not program firmware");
+//!
+//! // Start the new firmware
+//! bootloader.reset().expect("Could not reset module");
+//! let rn = rn2xx3::rn2483_868(bootloader.free());
+//! ```
+//!
+//! **Note:** If programming fails, the module stays in the bootloader (even
+//! after a power cycle), so the update can be retried by creating a new
+//! bootloader instance with [`Bootloader::new`](struct.Bootloader.html#method.new).
+//!
+//! ## Protocol
+//!
+//! Every command frame starts with the byte `0x55`, which the bootloader uses
+//! to detect the baud rate, followed by a 9 byte header (command, data length,
+//! unlock key and address) and the data. The bootloader echoes the header,
+//! followed by the response data.
+
+use core::convert::{Infallible, TryFrom};
+use core::ops::Range;
+
+use crate::errors::{BootloaderError, Error, HexError};
+use crate::serial::Serial;
+
+/// The byte sent before every command, used by the bootloader to detect the
+/// baud rate.
+const SYNC: u8 = 0x55;
+
+/// The unlock key required by commands that modify the flash memory.
+const UNLOCK: [u8; 2] = [0x55, 0xAA];
+
+/// The status byte returned by a successful command.
+const SUCCESS: u8 = 0x01;
+
+/// The length of a command header.
+const HEADER_LEN: usize = 9;
+
+/// The bootloader commands.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Command {
+    ReadVersion = 0x00,
+    ReadFlash = 0x01,
+    WriteFlash = 0x02,
+    EraseFlash = 0x03,
+    CalcChecksum = 0x08,
+    ResetDevice = 0x09,
+}
+
+/// Information returned by the bootloader, see
+/// [`read_version`](struct.Bootloader.html#method.read_version).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BootloaderInfo {
+    /// Major version of the bootloader.
+    pub major: u8,
+    /// Minor version of the bootloader.
+    pub minor: u8,
+    /// The maximum size of a command frame in bytes.
+    pub max_packet_size: u16,
+    /// The device ID of the microcontroller.
+    pub device_id: u16,
+    /// The size of a flash row (the unit of erasure) in bytes.
+    pub erase_row_size: u8,
+    /// The number of bytes that can be written at once.
+    pub write_latches: u8,
+}
+
+/// The phase of [`program`](struct.Bootloader.html#method.program).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Phase {
+    /// Erasing and writing the flash rows.
+    Write,
+    /// Comparing the checksums of the flash rows with the image.
+    Verify,
+}
+
+/// Progress information passed to the progress callback.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Progress {
+    /// The current phase.
+    pub phase: Phase,
+    /// The number of flash rows processed in this phase.
+    pub row: usize,
+    /// The total number of flash rows in the image.
+    pub rows: usize,
+}
+
+/// A validated firmware image in Intel HEX format.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct HexImage<'a> {
+    text: &'a str,
+}
+
+/// A data record of a [`HexImage`](struct.HexImage.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct DataRecord<'a> {
+    /// The absolute start address of the data.
+    pub address: u32,
+    /// The data, as hex string.
+    hex: &'a str,
+}
+
+impl<'a> DataRecord<'a> {
+    /// Return an iterator over the data bytes.
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + 'a {
+        let hex = self.hex;
+        (0..hex.len())
+            .step_by(2)
+            .map(move |i| parse_hex_byte(&hex[i..i + 2]).unwrap_or(0xFF))
+    }
+
+    /// Return the number of data bytes.
+    fn byte_len(&self) -> usize {
+        self.hex.len() / 2
+    }
+}
+
+impl<'a> HexImage<'a> {
+    /// Parse and validate an Intel HEX image.
+    ///
+    /// The data records must be sorted by address and may not overlap. The
+    /// extended segment and extended linear address records are supported,
+    /// start address records are ignored.
+    pub fn parse(text: &'a str) -> Result<Self, HexError> {
+        let mut next_address = 0u32;
+        let mut first = true;
+        for record in Records::new(text) {
+            let (line, record) = record?;
+            if !first && record.address < next_address {
+                return Err(HexError::Unordered { line });
+            }
+            first = false;
+            next_address = record.address.wrapping_add(record.byte_len() as u32);
+        }
+        Ok(Self { text })
+    }
+
+    /// Return an iterator over the data records.
+    pub fn data_records(&self) -> impl Iterator<Item = DataRecord<'a>> {
+        Records::new(self.text).filter_map(|record| record.ok().map(|(_, record)| record))
+    }
+}
+
+/// An iterator over the data records of an Intel HEX file, together with
+/// their line number.
+struct Records<'a> {
+    lines: core::iter::Enumerate<core::str::Lines<'a>>,
+    base: u32,
+    done: bool,
+}
+
+impl<'a> Records<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().enumerate(),
+            base: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<(usize, DataRecord<'a>), HexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        for (index, line) in &mut self.lines {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, offset, data) = match parse_record(line, line_number) {
+                Ok(record) => record,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            match (kind, data.len()) {
+                (0x00, _) => {
+                    let record = DataRecord {
+                        address: self.base.wrapping_add(u32::from(offset)),
+                        hex: data,
+                    };
+                    return Some(Ok((line_number, record)));
+                }
+                (0x01, 0) => {
+                    self.done = true;
+                    return None;
+                }
+                (0x02, 4) | (0x04, 4) => {
+                    let value = u32::from_str_radix(data, 16).unwrap_or(0);
+                    self.base = if kind == 0x02 {
+                        value << 4
+                    } else {
+                        value << 16
+                    };
+                }
+                (0x03, 8) | (0x05, 8) => {}
+                (0x01, _) | (0x02, _) | (0x03, _) | (0x04, _) | (0x05, _) => {
+                    self.done = true;
+                    return Some(Err(HexError::InvalidRecord { line: line_number }));
+                }
+                _ => {
+                    self.done = true;
+                    return Some(Err(HexError::UnsupportedRecordType { line: line_number }));
+                }
+            }
+        }
+        self.done = true;
+        Some(Err(HexError::MissingEndOfFile))
+    }
+}
+
+/// Parse a two digit hex string.
+fn parse_hex_byte(hex: &str) -> Option<u8> {
+    if hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        u8::from_str_radix(hex, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Parse a single Intel HEX record. Return the record type, the address
+/// offset and the data as hex string.
+fn parse_record(line: &str, line_number: usize) -> Result<(u8, u16, &str), HexError> {
+    let invalid = HexError::InvalidRecord { line: line_number };
+    let hex = line.strip_prefix(':').ok_or(invalid)?;
+    if hex.len() < 10 || hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid);
+    }
+    let len = parse_hex_byte(&hex[0..2]).ok_or(invalid)?;
+    if hex.len() != 10 + 2 * usize::from(len) {
+        return Err(invalid);
+    }
+    let sum = (0..hex.len()).step_by(2).fold(0u8, |sum, i| {
+        sum.wrapping_add(parse_hex_byte(&hex[i..i + 2]).unwrap_or(0))
+    });
+    if sum != 0 {
+        return Err(HexError::InvalidChecksum { line: line_number });
+    }
+    let offset = u16::from_str_radix(&hex[2..6], 16).map_err(|_| invalid)?;
+    let kind = parse_hex_byte(&hex[6..8]).ok_or(invalid)?;
+    Ok((kind, offset, &hex[8..hex.len() - 2]))
+}
+
+/// Calculate the checksum of a flash row, like the bootloader does: The sum
+/// of all 16 bit little endian words.
+fn row_checksum(data: &[u8]) -> u16 {
+    data.chunks(2).fold(0u16, |sum, word| {
+        let low = u16::from(word[0]);
+        let high = u16::from(word.get(1).copied().unwrap_or(0));
+        sum.wrapping_add(low).wrapping_add(high << 8)
+    })
+}
+
+/// Call `f` with the address and contents of every flash row in `flash`
+/// that contains data from the image, in ascending order. Bytes that are not
+/// part of the image are set to `0xFF` (the value of erased flash memory).
+fn for_each_row<X>(
+    image: &HexImage<'_>,
+    flash: &Range<u32>,
+    row_size: usize,
+    mut f: impl FnMut(u32, &[u8]) -> Result<(), X>,
+) -> Result<(), X> {
+    // The row size is reported as `u8`, so it always fits
+    let mut buf = [0xFF; 256];
+    let mut current: Option<u32> = None;
+    for record in image.data_records() {
+        for (i, byte) in record.bytes().enumerate() {
+            let address = record.address.wrapping_add(i as u32);
+            if !flash.contains(&address) {
+                continue;
+            }
+            let row = address - address % row_size as u32;
+            if current != Some(row) {
+                if let Some(previous) = current {
+                    f(previous, &buf[..row_size])?;
+                }
+                buf = [0xFF; 256];
+                current = Some(row);
+            }
+            buf[(address - row) as usize] = byte;
+        }
+    }
+    if let Some(previous) = current {
+        f(previous, &buf[..row_size])?;
+    }
+    Ok(())
+}
+
+/// The UART bootloader of the RN module.
+///
+/// Note that the bootloader does not use the read timeouts of the driver.
+/// Reads block until the serial port returns data (or an error).
+pub struct Bootloader<S> {
+    /// Serial port.
+    serial: S,
+
+    /// The bootloader information, once it has been queried.
+    info: Option<BootloaderInfo>,
+}
+
+/// A `Result<T, BootloaderError>`.
+type BlResult<T, E> = Result<T, BootloaderError<E>>;
+
+impl<S, E> Bootloader<S>
+where
+    S: Serial<Error = E>,
+{
+    /// Create a new bootloader instance.
+    ///
+    /// The module must already run the bootloader, either because the
+    /// firmware was erased with
+    /// [`erase_fw`](../struct.Driver.html#method.erase_fw) or because a
+    /// previous update failed.
+    pub fn new(serial: S) -> Self {
+        Self { serial, info: None }
+    }
+
+    /// Destroy this bootloader instance, return the wrapped serial device.
+    pub fn free(self) -> S {
+        self.serial
+    }
+
+    /// Write all bytes from the buffer to the serial port.
+    fn write_all(&mut self, buffer: &[u8]) -> BlResult<(), E> {
+        self.serial
+            .write_all(buffer)
+            .map_err(|e| Error::SerialWrite(e).into())
+    }
+
+    /// Fill the buffer with bytes from the serial port.
+    fn read_exact(&mut self, mut buffer: &mut [u8]) -> BlResult<(), E> {
+        while !buffer.is_empty() {
+            match self.serial.read(buffer).map_err(Error::SerialRead)? {
+                0 => return Err(BootloaderError::UnexpectedResponse),
+                count => buffer = &mut buffer[count..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a command frame and check that the bootloader echoes the header.
+    fn send_command(
+        &mut self,
+        command: Command,
+        length: u16,
+        address: u32,
+        data: &[u8],
+    ) -> BlResult<(), E> {
+        let unlock = match command {
+            Command::WriteFlash | Command::EraseFlash => UNLOCK,
+            _ => [0; 2],
+        };
+        let mut header = [0; HEADER_LEN];
+        header[0] = command as u8;
+        header[1..3].copy_from_slice(&length.to_le_bytes());
+        header[3..5].copy_from_slice(&unlock);
+        header[5..9].copy_from_slice(&address.to_le_bytes());
+
+        #[cfg(feature = "logging")]
+        log::debug!("Sending bootloader command {:?} at {:#x}", command, address);
+        self.write_all(&[SYNC])?;
+        self.write_all(&header)?;
+        self.write_all(data)?;
+        self.serial.flush().map_err(Error::SerialWrite)?;
+
+        let mut echo = [0; HEADER_LEN];
+        self.read_exact(&mut echo)?;
+        if echo != header {
+            return Err(BootloaderError::UnexpectedResponse);
+        }
+        Ok(())
+    }
+
+    /// Read the status byte of a command.
+    fn read_status(&mut self) -> BlResult<(), E> {
+        let mut status = [0];
+        self.read_exact(&mut status)?;
+        if status[0] == SUCCESS {
+            Ok(())
+        } else {
+            Err(BootloaderError::CommandFailed)
+        }
+    }
+
+    /// Read the bootloader version and the flash geometry.
+    pub fn read_version(&mut self) -> BlResult<BootloaderInfo, E> {
+        self.send_command(Command::ReadVersion, 0, 0, &[])?;
+        let mut data = [0; 16];
+        self.read_exact(&mut data)?;
+        let info = BootloaderInfo {
+            major: data[1],
+            minor: data[0],
+            max_packet_size: u16::from_le_bytes([data[2], data[3]]),
+            device_id: u16::from_le_bytes([data[6], data[7]]),
+            erase_row_size: data[10],
+            write_latches: data[11],
+        };
+        self.info = Some(info);
+        Ok(info)
+    }
+
+    /// Return the row size and the write size, as long as the bootloader
+    /// reports a flash geometry that is supported.
+    fn geometry(&mut self) -> BlResult<(usize, usize), E> {
+        let info = match self.info {
+            Some(info) => info,
+            None => self.read_version()?,
+        };
+        let row_size = usize::from(info.erase_row_size);
+        let write_size = usize::from(info.write_latches);
+        if row_size == 0 || row_size % 2 != 0 || write_size == 0 || row_size % write_size != 0 {
+            return Err(BootloaderError::UnsupportedDevice);
+        }
+        Ok((row_size, write_size))
+    }
+
+    /// Erase `count` flash rows, starting at the specified address.
+    pub fn erase_rows(&mut self, address: u32, count: u16) -> BlResult<(), E> {
+        self.send_command(Command::EraseFlash, count, address, &[])?;
+        self.read_status()
+    }
+
+    /// Write data to the (erased) flash memory at the specified address.
+    ///
+    /// The data may not be longer than the number of write latches, see
+    /// [`BootloaderInfo`](struct.BootloaderInfo.html).
+    pub fn write_flash(&mut self, address: u32, data: &[u8]) -> BlResult<(), E> {
+        let length = u16::try_from(data.len()).map_err(|_| Error::BadParameter)?;
+        self.send_command(Command::WriteFlash, length, address, data)?;
+        self.read_status()
+    }
+
+    /// Read the flash memory at the specified address into the buffer.
+    pub fn read_flash(&mut self, address: u32, buffer: &mut [u8]) -> BlResult<(), E> {
+        let length = u16::try_from(buffer.len()).map_err(|_| Error::BadParameter)?;
+        self.send_command(Command::ReadFlash, length, address, &[])?;
+        self.read_exact(buffer)
+    }
+
+    /// Calculate the checksum of `length` bytes of flash memory, starting at
+    /// the specified address.
+    ///
+    /// The checksum is the sum of all 16 bit little endian words.
+    pub fn checksum(&mut self, address: u32, length: u16) -> BlResult<u16, E> {
+        self.send_command(Command::CalcChecksum, length, address, &[])?;
+        let mut checksum = [0; 2];
+        self.read_exact(&mut checksum)?;
+        Ok(u16::from_le_bytes(checksum))
+    }
+
+    /// Reset the module and start the application firmware.
+    ///
+    /// Afterwards, create a new driver from the serial port returned by
+    /// [`free`](#method.free).
+    pub fn reset(&mut self) -> BlResult<(), E> {
+        self.send_command(Command::ResetDevice, 0, 0, &[])?;
+        self.read_status()
+    }
+
+    /// Write the firmware image to the flash memory and verify it.
+    ///
+    /// Every flash row containing data from the image is erased and written.
+    /// Afterwards, the checksum of every row is compared with the image, see
+    /// [`verify`](#method.verify). Data outside of the `flash` address range
+    /// (e.g. configuration words or EEPROM data) is skipped.
+    ///
+    /// The `progress` callback is called after every row.
+    pub fn program<P>(
+        &mut self,
+        image: &HexImage<'_>,
+        flash: Range<u32>,
+        mut progress: P,
+    ) -> BlResult<(), E>
+    where
+        P: FnMut(Progress),
+    {
+        let (row_size, write_size) = self.geometry()?;
+        let rows = count_rows(image, &flash, row_size);
+        let mut row = 0;
+        for_each_row::<BootloaderError<E>>(image, &flash, row_size, |address, data| {
+            self.erase_rows(address, 1)?;
+            for (i, chunk) in data.chunks(write_size).enumerate() {
+                self.write_flash(address + (i * write_size) as u32, chunk)?;
+            }
+            row += 1;
+            progress(Progress {
+                phase: Phase::Write,
+                row,
+                rows,
+            });
+            Ok(())
+        })?;
+        self.verify(image, flash, progress)
+    }
+
+    /// Compare the checksum of every flash row containing data from the
+    /// image with the image.
+    ///
+    /// If a checksum does not match, `BootloaderError::VerifyFailed` with the
+    /// address of the row is returned. The `progress` callback is called
+    /// after every row.
+    pub fn verify<P>(
+        &mut self,
+        image: &HexImage<'_>,
+        flash: Range<u32>,
+        mut progress: P,
+    ) -> BlResult<(), E>
+    where
+        P: FnMut(Progress),
+    {
+        let (row_size, _) = self.geometry()?;
+        let rows = count_rows(image, &flash, row_size);
+        let mut row = 0;
+        for_each_row(image, &flash, row_size, |address, data| {
+            if self.checksum(address, data.len() as u16)? != row_checksum(data) {
+                return Err(BootloaderError::VerifyFailed(address));
+            }
+            row += 1;
+            progress(Progress {
+                phase: Phase::Verify,
+                row,
+                rows,
+            });
+            Ok(())
+        })
+    }
+}
+
+/// Return the number of flash rows in `flash` that contain data from the
+/// image.
+fn count_rows(image: &HexImage<'_>, flash: &Range<u32>, row_size: usize) -> usize {
+    let mut rows = 0;
+    let _ = for_each_row::<Infallible>(image, flash, row_size, |_, _| {
+        rows += 1;
+        Ok(())
+    });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    use crate::io::FromIo;
+    use crate::testing::ScriptedSerial;
+    use std::vec::Vec;
+
+    /// A scripted exchange: The command frame sent to the bootloader and the
+    /// response.
+    struct Exchange {
+        written: Vec<u8>,
+        read: Vec<u8>,
+    }
+
+    fn exchange(command: u8, length: u16, address: u32, data: &[u8], response: &[u8]) -> Exchange {
+        let unlock = if command == 0x02 || command == 0x03 {
+            UNLOCK
+        } else {
+            [0; 2]
+        };
+        let mut header = vec![command];
+        header.extend_from_slice(&length.to_le_bytes());
+        header.extend_from_slice(&unlock);
+        header.extend_from_slice(&address.to_le_bytes());
+        let mut written = vec![SYNC];
+        written.extend_from_slice(&header);
+        written.extend_from_slice(data);
+        let mut read = header;
+        read.extend_from_slice(response);
+        Exchange { written, read }
+    }
+
+    fn script(exchanges: &[Exchange]) -> (ScriptedSerial, Vec<u8>) {
+        let reads: Vec<u8> = exchanges.iter().flat_map(|e| e.read.clone()).collect();
+        let writes = exchanges.iter().flat_map(|e| e.written.clone()).collect();
+        (ScriptedSerial::new(&[&reads]), writes)
+    }
+
+    /// A version response with 8 byte rows and 4 write latches.
+    fn version() -> Exchange {
+        exchange(
+            0x00,
+            0,
+            0,
+            &[],
+            &[3, 1, 0x50, 0, 0, 0, 0x40, 0x54, 0, 0, 8, 4, 0, 0, 0, 0],
+        )
+    }
+
+    const IMAGE: &str = ":020000040000FA\n\
+                         :0400000001020304F2\n\
+                         :02000A00AABB8F\n\
+                         :020000040030CA\n\
+                         :0100000012ED\n\
+                         :00000001FF\n";
+
+    #[test]
+    fn parse_image() {
+        let image = HexImage::parse(IMAGE).unwrap();
+        let records: Vec<(u32, Vec<u8>)> = image
+            .data_records()
+            .map(|record| (record.address, record.bytes().collect()))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (0x0000_0000, vec![1, 2, 3, 4]),
+                (0x0000_000A, vec![0xAA, 0xBB]),
+                (0x0030_0000, vec![0x12]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_image_segment_address() {
+        let image = HexImage::parse(":020000021000EC\r\n:0100040042B9\r\n:00000001FF\r\n").unwrap();
+        let record = image.data_records().next().unwrap();
+        assert_eq!(record.address, 0x10004);
+        assert_eq!(record.bytes().collect::<Vec<_>>(), vec![0x42]);
+    }
+
+    #[test]
+    fn parse_image_errors() {
+        assert_eq!(
+            HexImage::parse("0400000001020304F2\n:00000001FF"),
+            Err(HexError::InvalidRecord { line: 1 })
+        );
+        assert_eq!(
+            HexImage::parse(":0400000001020304F3\n:00000001FF"),
+            Err(HexError::InvalidChecksum { line: 1 })
+        );
+        assert_eq!(
+            HexImage::parse(":0500000001020304F2\n:00000001FF"),
+            Err(HexError::InvalidRecord { line: 1 })
+        );
+        assert_eq!(
+            HexImage::parse(":0400000001020304F2\n:00000006FA"),
+            Err(HexError::UnsupportedRecordType { line: 2 })
+        );
+        assert_eq!(
+            HexImage::parse(":0400000001020304F2\n\n:0100020042BB\n:00000001FF"),
+            Err(HexError::Unordered { line: 3 })
+        );
+        assert_eq!(
+            HexImage::parse(":0400000001020304F2\n"),
+            Err(HexError::MissingEndOfFile)
+        );
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(row_checksum(&[1, 2, 3, 4, 0xFF, 0xFF, 0xFF, 0xFF]), 0x0602);
+        assert_eq!(row_checksum(&[0xFF; 8]), 0xFFFC);
+    }
+
+    #[test]
+    fn read_version() {
+        let (serial, writes) = script(&[version()]);
+        let mut bootloader = Bootloader::new(FromIo::new(serial));
+        assert_eq!(
+            bootloader.read_version(),
+            Ok(BootloaderInfo {
+                major: 1,
+                minor: 3,
+                max_packet_size: 0x50,
+                device_id: 0x5440,
+                erase_row_size: 8,
+                write_latches: 4,
+            })
+        );
+        bootloader.free().into_inner().done(&writes);
+    }
+
+    #[test]
+    fn program() {
+        let (serial, writes) = script(&[
+            version(),
+            // Row 0x0000
+            exchange(0x03, 1, 0x0000, &[], &[SUCCESS]),
+            exchange(0x02, 4, 0x0000, &[1, 2, 3, 4], &[SUCCESS]),
+            exchange(0x02, 4, 0x0004, &[0xFF; 4], &[SUCCESS]),
+            // Row 0x0008
+            exchange(0x03, 1, 0x0008, &[], &[SUCCESS]),
+            exchange(0x02, 4, 0x0008, &[0xFF, 0xFF, 0xAA, 0xBB], &[SUCCESS]),
+            exchange(0x02, 4, 0x000C, &[0xFF; 4], &[SUCCESS]),
+            // Verify
+            exchange(0x08, 8, 0x0000, &[], &0x0602u16.to_le_bytes()),
+            exchange(0x08, 8, 0x0008, &[], &0xBBA7u16.to_le_bytes()),
+        ]);
+        let image = HexImage::parse(IMAGE).unwrap();
+        let mut bootloader = Bootloader::new(FromIo::new(serial));
+        let mut progress = Vec::new();
+        assert_eq!(
+            bootloader.program(&image, 0x0000..0x1_0000, |p| progress.push(p)),
+            Ok(())
+        );
+        let phases: Vec<(Phase, usize, usize)> =
+            progress.iter().map(|p| (p.phase, p.row, p.rows)).collect();
+        assert_eq!(
+            phases,
+            vec![
+                (Phase::Write, 1, 2),
+                (Phase::Write, 2, 2),
+                (Phase::Verify, 1, 2),
+                (Phase::Verify, 2, 2),
+            ]
+        );
+        bootloader.free().into_inner().done(&writes);
+    }
+
+    #[test]
+    fn verify_failed() {
+        let (serial, writes) = script(&[
+            version(),
+            exchange(0x08, 8, 0x0000, &[], &0x0602u16.to_le_bytes()),
+            exchange(0x08, 8, 0x0008, &[], &0xFFFCu16.to_le_bytes()),
+        ]);
+        let image = HexImage::parse(IMAGE).unwrap();
+        let mut bootloader = Bootloader::new(FromIo::new(serial));
+        assert_eq!(
+            bootloader.verify(&image, 0x0000..0x1_0000, |_| {}),
+            Err(BootloaderError::VerifyFailed(0x0008))
+        );
+        bootloader.free().into_inner().done(&writes);
+    }
+
+    #[test]
+    fn command_failed() {
+        let (serial, writes) = script(&[exchange(0x03, 2, 0x0100, &[], &[0x00])]);
+        let mut bootloader = Bootloader::new(FromIo::new(serial));
+        assert_eq!(
+            bootloader.erase_rows(0x0100, 2),
+            Err(BootloaderError::CommandFailed)
+        );
+        bootloader.free().into_inner().done(&writes);
+    }
+
+    #[test]
+    fn unexpected_response() {
+        let mut serial = ScriptedSerial::new(&[&[0x01, 0, 0, 0, 0, 0, 0, 0, 0]]);
+        let mut bootloader = Bootloader::new(FromIo::new(&mut serial));
+        assert_eq!(
+            bootloader.read_version(),
+            Err(BootloaderError::UnexpectedResponse)
+        );
+        assert!(serial.reads.is_empty());
+
+        // Missing response
+        let mut bootloader = Bootloader::new(FromIo::new(ScriptedSerial::new(&[&[]])));
+        assert_eq!(bootloader.reset(), Err(BootloaderError::UnexpectedResponse));
+    }
+
+    #[test]
+    fn erase_fw() {
+        let serial = ScriptedSerial::new(&[]);
+        let rn = crate::io::rn2483_868(serial);
+        let bootloader = rn.erase_fw().map_err(|e| e.error).unwrap();
+        bootloader.free().into_inner().done(b"sys eraseFW\r\n");
+    }
+}
@@ -0,0 +1,767 @@
+//! Channel configuration.
+//!
+//! The RN2483 supports 16 channels (0–15). Channels 0–2 are the default
+//! channels of the band, only their duty cycle, data rate range and status
+//! can be changed. Channels 3–15 can be configured freely, either one by one
+//! or all at once using a [`ChannelPlan`](struct.ChannelPlan.html):
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use rn2xx3::channel::{Channel, ChannelPlan};
+//! use rn2xx3::DataRateEuCn;
+//!
+//! let mut rn = rn2xx3::rn2483_868(serialport);
+//!
+//! let mut plan = ChannelPlan::new();
+//! plan.set(
+//!     3,
+//!     Channel {
+//!         frequency: 867_100_000,
+//!         duty_cycle: 999,
+//!         min_data_rate: DataRateEuCn::Sf12Bw125,
+//!         max_data_rate: DataRateEuCn::Sf7Bw125,
+//!         enabled: true,
+//!     },
+//! )
+//! .expect("Invalid channel");
+//! rn.apply_channel_plan(&plan).expect("Could not apply channel plan");
+//!
+//! // Persist the channel configuration
+//! rn.save_config().expect("Could not save config");
+//! ```
+//!
+//! The RN2903 supports 72 channels with fixed frequencies: 64 channels with
+//! 125 kHz bandwidth (0–63) and 8 channels with 500 kHz bandwidth (64–71).
+//! Most networks only use one [sub-band](enum.SubBand.html) of 8 + 1
+//! channels, which can be selected with
+//! [`set_sub_band`](../struct.Driver.html#method.set_sub_band).
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use numtoa::NumToA;
+
+use crate::errors::{ChannelPlanError, Error, RnResult};
+use crate::protocol;
+use crate::serial::Serial;
+use crate::timeout::Timer;
+use crate::{utils, DataRateEuCn, DataRateUs, Driver, Freq915, Frequency};
+
+/// Number of channels supported by the RN2483.
+const CHANNEL_COUNT: u8 = 16;
+
+/// Number of channels supported by the RN2903.
+const US_CHANNEL_COUNT: u8 = 72;
+
+/// Number of 125 kHz channels supported by the RN2903. They are followed by
+/// the 500 kHz channels.
+const US_125KHZ_CHANNEL_COUNT: u8 = 64;
+
+/// Number of default channels. Their frequency cannot be changed.
+const DEFAULT_CHANNEL_COUNT: u8 = 3;
+
+/// The configuration of a channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Channel {
+    /// Frequency in Hz.
+    pub frequency: u32,
+    /// Duty cycle value. The actual duty cycle is `100 / (duty_cycle + 1)`
+    /// percent, e.g. 99 for 1%.
+    pub duty_cycle: u16,
+    /// Lowest data rate that may be used on this channel.
+    pub min_data_rate: DataRateEuCn,
+    /// Highest data rate that may be used on this channel.
+    pub max_data_rate: DataRateEuCn,
+    /// Whether the channel is enabled.
+    pub enabled: bool,
+}
+
+/// The configuration of channels 3–15, to be applied at once.
+///
+/// All channels are validated when they are added to the plan, so that the
+/// plan can be applied without being rejected halfway.
+pub struct ChannelPlan<F: Frequency> {
+    /// Marker type with the module frequency.
+    frequency: PhantomData<F>,
+
+    /// Channel configuration, indexed by channel ID.
+    channels: [Option<Channel>; CHANNEL_COUNT as usize],
+}
+
+impl<F: Frequency<DataRate = DataRateEuCn>> ChannelPlan<F> {
+    /// Create an empty channel plan.
+    pub fn new() -> Self {
+        Self {
+            frequency: PhantomData,
+            channels: [None; CHANNEL_COUNT as usize],
+        }
+    }
+
+    /// Add the configuration for the channel with the specified ID,
+    /// replacing any previous configuration.
+    pub fn set(&mut self, id: u8, channel: Channel) -> Result<(), ChannelPlanError> {
+        if id < DEFAULT_CHANNEL_COUNT {
+            return Err(ChannelPlanError::ReadOnlyChannel);
+        }
+        if id >= CHANNEL_COUNT {
+            return Err(ChannelPlanError::InvalidChannel);
+        }
+        if !F::RADIO_FREQUENCIES.contains(&channel.frequency) {
+            return Err(ChannelPlanError::InvalidFrequency);
+        }
+        if channel.min_data_rate > channel.max_data_rate {
+            return Err(ChannelPlanError::InvalidDataRateRange);
+        }
+        self.channels[usize::from(id)] = Some(channel);
+        Ok(())
+    }
+
+    /// Return the configuration of the channel with the specified ID, if any.
+    pub fn get(&self, id: u8) -> Option<&Channel> {
+        self.channels.get(usize::from(id))?.as_ref()
+    }
+
+    /// Remove the configuration of the channel with the specified ID.
+    pub fn remove(&mut self, id: u8) -> Option<Channel> {
+        self.channels.get_mut(usize::from(id))?.take()
+    }
+
+    /// Iterate over the configured channels and their IDs.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Channel)> {
+        (0..CHANNEL_COUNT)
+            .zip(self.channels.iter())
+            .filter_map(|(id, channel)| channel.as_ref().map(|channel| (id, channel)))
+    }
+}
+
+impl<F: Frequency<DataRate = DataRateEuCn>> Default for ChannelPlan<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The traits are implemented manually, so that they do not require the
+// frequency marker type to implement them.
+
+impl<F: Frequency> fmt::Debug for ChannelPlan<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelPlan")
+            .field("channels", &self.channels)
+            .finish()
+    }
+}
+
+impl<F: Frequency> PartialEq for ChannelPlan<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.channels == other.channels
+    }
+}
+
+impl<F: Frequency> Eq for ChannelPlan<F> {}
+
+impl<F: Frequency> Clone for ChannelPlan<F> {
+    fn clone(&self) -> Self {
+        Self {
+            frequency: PhantomData,
+            channels: self.channels,
+        }
+    }
+}
+
+/// A sub-band of the US 902–928 MHz band, consisting of eight 125 kHz
+/// channels and one 500 kHz channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SubBand {
+    /// Sub-band 1: Channels 0–7 and 64
+    Sb1,
+    /// Sub-band 2: Channels 8–15 and 65
+    Sb2,
+    /// Sub-band 3: Channels 16–23 and 66
+    Sb3,
+    /// Sub-band 4: Channels 24–31 and 67
+    Sb4,
+    /// Sub-band 5: Channels 32–39 and 68
+    Sb5,
+    /// Sub-band 6: Channels 40–47 and 69
+    Sb6,
+    /// Sub-band 7: Channels 48–55 and 70
+    Sb7,
+    /// Sub-band 8: Channels 56–63 and 71
+    Sb8,
+}
+
+impl SubBand {
+    /// Return the zero based index of the sub-band.
+    fn index(self) -> u8 {
+        match self {
+            SubBand::Sb1 => 0,
+            SubBand::Sb2 => 1,
+            SubBand::Sb3 => 2,
+            SubBand::Sb4 => 3,
+            SubBand::Sb5 => 4,
+            SubBand::Sb6 => 5,
+            SubBand::Sb7 => 6,
+            SubBand::Sb8 => 7,
+        }
+    }
+
+    /// Return the ID of the 500 kHz channel of this sub-band.
+    pub fn wide_channel(self) -> u8 {
+        US_125KHZ_CHANNEL_COUNT + self.index()
+    }
+
+    /// Return whether the channel with the specified ID belongs to this
+    /// sub-band.
+    pub fn contains(self, id: u8) -> bool {
+        id / 8 == self.index() || id == self.wide_channel()
+    }
+}
+
+/// Validate the channel ID and convert it to a decimal string.
+fn channel_id_str<S>(id: u8, count: u8, buf: &mut [u8; 3]) -> RnResult<&str, S> {
+    if id >= count {
+        return Err(Error::BadParameter);
+    }
+    utils::u8_to_str(id, buf)
+}
+
+/// Channel commands for RN2483 modules.
+impl<F, S, C, E> Driver<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency<DataRate = DataRateEuCn>,
+    C: Timer<S>,
+{
+    /// Set the frequency of a channel in Hz.
+    ///
+    /// Only the frequency of channels 3–15 can be changed, and it must be
+    /// within the band of the module. Otherwise, `Error::BadParameter` is
+    /// returned.
+    pub fn set_channel_frequency(&mut self, id: u8, frequency: u32) -> RnResult<(), E> {
+        if id < DEFAULT_CHANNEL_COUNT || !F::RADIO_FREQUENCIES.contains(&frequency) {
+            return Err(Error::BadParameter);
+        }
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        let mut buf = [0; 10];
+        let frequency_str = frequency.numtoa_str(10, &mut buf);
+        self.send_raw_command_ok(&["mac set ch freq ", id_str, " ", frequency_str])
+    }
+
+    /// Return the frequency of a channel in Hz.
+    pub fn get_channel_frequency(&mut self, id: u8) -> RnResult<u32, E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        let frequency = self.send_raw_command_str(&["mac get ch freq ", id_str])?;
+        protocol::parse_number(frequency)
+    }
+
+    /// Set the duty cycle value of a channel. The actual duty cycle is
+    /// `100 / (duty_cycle + 1)` percent.
+    pub fn set_channel_duty_cycle(&mut self, id: u8, duty_cycle: u16) -> RnResult<(), E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        let mut buf = [0; 5];
+        let duty_cycle_str = duty_cycle.numtoa_str(10, &mut buf);
+        self.send_raw_command_ok(&["mac set ch dcycle ", id_str, " ", duty_cycle_str])
+    }
+
+    /// Return the duty cycle value of a channel.
+    pub fn get_channel_duty_cycle(&mut self, id: u8) -> RnResult<u16, E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        let duty_cycle = self.send_raw_command_str(&["mac get ch dcycle ", id_str])?;
+        protocol::parse_number(duty_cycle)
+    }
+
+    /// Set the range of data rates that may be used on a channel.
+    ///
+    /// If `min` is higher than `max`, `Error::BadParameter` is returned.
+    pub fn set_channel_data_rate_range(
+        &mut self,
+        id: u8,
+        min: DataRateEuCn,
+        max: DataRateEuCn,
+    ) -> RnResult<(), E> {
+        if min > max {
+            return Err(Error::BadParameter);
+        }
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        self.send_raw_command_ok(&[
+            "mac set ch drrange ",
+            id_str,
+            " ",
+            min.into(),
+            " ",
+            max.into(),
+        ])
+    }
+
+    /// Return the range of data rates that may be used on a channel.
+    pub fn get_channel_data_rate_range(
+        &mut self,
+        id: u8,
+    ) -> RnResult<(DataRateEuCn, DataRateEuCn), E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        let range = self.send_raw_command_str(&["mac get ch drrange ", id_str])?;
+        protocol::parse_data_rate_range(range)
+    }
+
+    /// Enable or disable a channel.
+    pub fn set_channel_enabled(&mut self, id: u8, enabled: bool) -> RnResult<(), E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        self.send_raw_command_ok(&["mac set ch status ", id_str, " ", protocol::on_off(enabled)])
+    }
+
+    /// Return whether a channel is enabled.
+    pub fn get_channel_enabled(&mut self, id: u8) -> RnResult<bool, E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, CHANNEL_COUNT, &mut id_buf)?;
+        let status = self.send_raw_command_str(&["mac get ch status ", id_str])?;
+        protocol::parse_on_off(status)
+    }
+
+    /// Return the complete configuration of a channel.
+    pub fn get_channel(&mut self, id: u8) -> RnResult<Channel, E> {
+        let (min_data_rate, max_data_rate) = self.get_channel_data_rate_range(id)?;
+        Ok(Channel {
+            frequency: self.get_channel_frequency(id)?,
+            duty_cycle: self.get_channel_duty_cycle(id)?,
+            min_data_rate,
+            max_data_rate,
+            enabled: self.get_channel_enabled(id)?,
+        })
+    }
+
+    /// Apply a channel plan atomically.
+    ///
+    /// The current configuration of all channels in the plan is read first.
+    /// If the module rejects a command while the plan is applied, the
+    /// channels that have already been changed are restored to that
+    /// configuration and the original error is returned. Errors during the
+    /// rollback are ignored.
+    ///
+    /// Channels that are not part of the plan are left unchanged. To persist
+    /// the configuration, call [`save_config`](#method.save_config)
+    /// afterwards.
+    pub fn apply_channel_plan(&mut self, plan: &ChannelPlan<F>) -> RnResult<(), E> {
+        let mut previous = [None; CHANNEL_COUNT as usize];
+        for (id, _) in plan.iter() {
+            previous[usize::from(id)] = Some(self.get_channel(id)?);
+        }
+
+        let mut applied = 0;
+        let mut result = Ok(());
+        for (id, channel) in plan.iter() {
+            // The channel may already be partially changed on failure
+            applied += 1;
+            result = self.configure_channel(id, channel);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        if result.is_err() {
+            #[cfg(feature = "logging")]
+            log::debug!("Restoring the previous channel configuration");
+            for (id, _) in plan.iter().take(applied) {
+                if let Some(ref channel) = previous[usize::from(id)] {
+                    let _ = self.restore_channel(id, channel);
+                }
+            }
+        }
+        result
+    }
+
+    /// Write the complete configuration of a channel.
+    fn configure_channel(&mut self, id: u8, channel: &Channel) -> RnResult<(), E> {
+        // A channel must only be enabled after it has been configured
+        self.set_channel_frequency(id, channel.frequency)?;
+        self.set_channel_duty_cycle(id, channel.duty_cycle)?;
+        self.set_channel_data_rate_range(id, channel.min_data_rate, channel.max_data_rate)?;
+        self.set_channel_enabled(id, channel.enabled)
+    }
+
+    /// Restore the configuration of a channel that was read from the module.
+    ///
+    /// Unconfigured channels report a frequency of 0, which cannot be
+    /// written back. Only their other settings are restored.
+    fn restore_channel(&mut self, id: u8, channel: &Channel) -> RnResult<(), E> {
+        if !channel.enabled {
+            self.set_channel_enabled(id, false)?;
+        }
+        if F::RADIO_FREQUENCIES.contains(&channel.frequency) {
+            self.set_channel_frequency(id, channel.frequency)?;
+        }
+        self.set_channel_duty_cycle(id, channel.duty_cycle)?;
+        self.set_channel_data_rate_range(id, channel.min_data_rate, channel.max_data_rate)?;
+        if channel.enabled {
+            self.set_channel_enabled(id, true)?;
+        }
+        Ok(())
+    }
+}
+
+/// Channel commands for RN2903 modules.
+impl<S, C, E> Driver<Freq915, S, C>
+where
+    S: Serial<Error = E>,
+    C: Timer<S>,
+{
+    /// Return the frequency of a channel in Hz.
+    pub fn get_channel_frequency(&mut self, id: u8) -> RnResult<u32, E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, US_CHANNEL_COUNT, &mut id_buf)?;
+        let frequency = self.send_raw_command_str(&["mac get ch freq ", id_str])?;
+        protocol::parse_number(frequency)
+    }
+
+    /// Set the range of data rates that may be used on a channel.
+    ///
+    /// If `min` is higher than `max`, `Error::BadParameter` is returned.
+    pub fn set_channel_data_rate_range(
+        &mut self,
+        id: u8,
+        min: DataRateUs,
+        max: DataRateUs,
+    ) -> RnResult<(), E> {
+        if min > max {
+            return Err(Error::BadParameter);
+        }
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, US_CHANNEL_COUNT, &mut id_buf)?;
+        self.send_raw_command_ok(&[
+            "mac set ch drrange ",
+            id_str,
+            " ",
+            min.into(),
+            " ",
+            max.into(),
+        ])
+    }
+
+    /// Return the range of data rates that may be used on a channel.
+    pub fn get_channel_data_rate_range(&mut self, id: u8) -> RnResult<(DataRateUs, DataRateUs), E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, US_CHANNEL_COUNT, &mut id_buf)?;
+        let range = self.send_raw_command_str(&["mac get ch drrange ", id_str])?;
+        protocol::parse_data_rate_range(range)
+    }
+
+    /// Enable or disable a channel.
+    pub fn set_channel_enabled(&mut self, id: u8, enabled: bool) -> RnResult<(), E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, US_CHANNEL_COUNT, &mut id_buf)?;
+        self.send_raw_command_ok(&["mac set ch status ", id_str, " ", protocol::on_off(enabled)])
+    }
+
+    /// Return whether a channel is enabled.
+    pub fn get_channel_enabled(&mut self, id: u8) -> RnResult<bool, E> {
+        let mut id_buf = [0; 3];
+        let id_str = channel_id_str(id, US_CHANNEL_COUNT, &mut id_buf)?;
+        let status = self.send_raw_command_str(&["mac get ch status ", id_str])?;
+        protocol::parse_on_off(status)
+    }
+
+    /// Restrict the module to the channels of a single sub-band.
+    ///
+    /// The channels of the sub-band are enabled first (the 500 kHz channel
+    /// with the data rate fixed to `Sf8Bw500`), then all other channels are
+    /// disabled. This requires 73 commands. To persist the configuration,
+    /// call [`save_config`](#method.save_config) afterwards.
+    pub fn set_sub_band(&mut self, sub_band: SubBand) -> RnResult<(), E> {
+        let first = sub_band.index() * 8;
+        for id in first..first + 8 {
+            self.set_channel_enabled(id, true)?;
+        }
+        let wide_channel = sub_band.wide_channel();
+        self.set_channel_data_rate_range(wide_channel, DataRateUs::Sf8Bw500, DataRateUs::Sf8Bw500)?;
+        self.set_channel_enabled(wide_channel, true)?;
+        for id in (0..US_CHANNEL_COUNT).filter(|id| !sub_band.contains(*id)) {
+            self.set_channel_enabled(id, false)?;
+        }
+        Ok(())
+    }
+
+    /// Return which of the 72 channels are enabled, indexed by channel ID.
+    pub fn enabled_channels(&mut self) -> RnResult<[bool; US_CHANNEL_COUNT as usize], E> {
+        let mut enabled = [false; US_CHANNEL_COUNT as usize];
+        for id in 0..US_CHANNEL_COUNT {
+            enabled[usize::from(id)] = self.get_channel_enabled(id)?;
+        }
+        Ok(enabled)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "embedded-hal-02")]
+mod tests {
+    use super::*;
+
+    use embedded_hal_mock::serial::Mock as SerialMock;
+
+    use crate::testing::{command, commands};
+    use crate::{rn2483_868, rn2903_915};
+    use crate::{Freq433, Freq868};
+
+    fn channel() -> Channel {
+        Channel {
+            frequency: 867_100_000,
+            duty_cycle: 999,
+            min_data_rate: DataRateEuCn::Sf12Bw125,
+            max_data_rate: DataRateEuCn::Sf7Bw125,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn plan_validation() {
+        let mut plan = ChannelPlan::<Freq868>::new();
+        assert_eq!(
+            plan.set(2, channel()),
+            Err(ChannelPlanError::ReadOnlyChannel)
+        );
+        assert_eq!(
+            plan.set(16, channel()),
+            Err(ChannelPlanError::InvalidChannel)
+        );
+        let mut invalid = channel();
+        invalid.frequency = 433_175_000;
+        assert_eq!(
+            plan.set(3, invalid),
+            Err(ChannelPlanError::InvalidFrequency)
+        );
+        let mut invalid = channel();
+        invalid.min_data_rate = DataRateEuCn::Sf7Bw250;
+        assert_eq!(
+            plan.set(3, invalid),
+            Err(ChannelPlanError::InvalidDataRateRange)
+        );
+        assert_eq!(plan.iter().count(), 0);
+
+        assert_eq!(plan.set(15, channel()), Ok(()));
+        assert_eq!(plan.get(15), Some(&channel()));
+        let copy = plan.clone();
+        assert_eq!(copy, plan);
+        assert!(format!("{:?}", copy).starts_with("ChannelPlan { channels: [None, "));
+        assert_eq!(plan.remove(15), Some(channel()));
+        assert_eq!(plan.get(15), None);
+    }
+
+    #[test]
+    fn plan_433() {
+        let mut plan = ChannelPlan::<Freq433>::new();
+        assert_eq!(
+            plan.set(3, channel()),
+            Err(ChannelPlanError::InvalidFrequency)
+        );
+        let mut channel = channel();
+        channel.frequency = 433_575_000;
+        assert_eq!(plan.set(3, channel), Ok(()));
+    }
+
+    /// The frequency of the default channels cannot be changed.
+    #[test]
+    fn set_channel_frequency_read_only() {
+        let expectations = [];
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(
+            rn.set_channel_frequency(0, 868_100_000),
+            Err(Error::BadParameter)
+        );
+        assert_eq!(
+            rn.set_channel_frequency(16, 868_100_000),
+            Err(Error::BadParameter)
+        );
+        assert_eq!(
+            rn.set_channel_frequency(3, 915_000_000),
+            Err(Error::BadParameter)
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn get_channel() {
+        let expectations = commands(&[
+            (b"mac get ch drrange 1\r\n", b"0 5\r\n"),
+            (b"mac get ch freq 1\r\n", b"868300000\r\n"),
+            (b"mac get ch dcycle 1\r\n", b"302\r\n"),
+            (b"mac get ch status 1\r\n", b"on\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(
+            rn.get_channel(1),
+            Ok(Channel {
+                frequency: 868_300_000,
+                duty_cycle: 302,
+                min_data_rate: DataRateEuCn::Sf12Bw125,
+                max_data_rate: DataRateEuCn::Sf7Bw125,
+                enabled: true,
+            })
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn apply_channel_plan() {
+        let expectations = commands(&[
+            (b"mac get ch drrange 3\r\n", b"0 5\r\n"),
+            (b"mac get ch freq 3\r\n", b"0\r\n"),
+            (b"mac get ch dcycle 3\r\n", b"0\r\n"),
+            (b"mac get ch status 3\r\n", b"off\r\n"),
+            (b"mac get ch drrange 12\r\n", b"0 5\r\n"),
+            (b"mac get ch freq 12\r\n", b"0\r\n"),
+            (b"mac get ch dcycle 12\r\n", b"0\r\n"),
+            (b"mac get ch status 12\r\n", b"off\r\n"),
+            (b"mac set ch freq 3 867100000\r\n", b"ok\r\n"),
+            (b"mac set ch dcycle 3 999\r\n", b"ok\r\n"),
+            (b"mac set ch drrange 3 0 5\r\n", b"ok\r\n"),
+            (b"mac set ch status 3 on\r\n", b"ok\r\n"),
+            (b"mac set ch freq 12 867300000\r\n", b"ok\r\n"),
+            (b"mac set ch dcycle 12 999\r\n", b"ok\r\n"),
+            (b"mac set ch drrange 12 0 5\r\n", b"ok\r\n"),
+            (b"mac set ch status 12 off\r\n", b"ok\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut plan = ChannelPlan::new();
+        plan.set(3, channel()).unwrap();
+        plan.set(
+            12,
+            Channel {
+                frequency: 867_300_000,
+                enabled: false,
+                ..channel()
+            },
+        )
+        .unwrap();
+        assert_eq!(rn.apply_channel_plan(&plan), Ok(()));
+        mock.done();
+    }
+
+    /// If a command is rejected, the channels that have already been
+    /// changed are restored.
+    #[test]
+    fn apply_channel_plan_rollback() {
+        let expectations = commands(&[
+            (b"mac get ch drrange 3\r\n", b"0 5\r\n"),
+            (b"mac get ch freq 3\r\n", b"867500000\r\n"),
+            (b"mac get ch dcycle 3\r\n", b"302\r\n"),
+            (b"mac get ch status 3\r\n", b"on\r\n"),
+            (b"mac get ch drrange 4\r\n", b"0 5\r\n"),
+            (b"mac get ch freq 4\r\n", b"0\r\n"),
+            (b"mac get ch dcycle 4\r\n", b"0\r\n"),
+            (b"mac get ch status 4\r\n", b"off\r\n"),
+            (b"mac get ch drrange 5\r\n", b"0 5\r\n"),
+            (b"mac get ch freq 5\r\n", b"0\r\n"),
+            (b"mac get ch dcycle 5\r\n", b"0\r\n"),
+            (b"mac get ch status 5\r\n", b"off\r\n"),
+            // Channel 3 is configured, channel 4 is rejected
+            (b"mac set ch freq 3 867100000\r\n", b"ok\r\n"),
+            (b"mac set ch dcycle 3 999\r\n", b"ok\r\n"),
+            (b"mac set ch drrange 3 0 5\r\n", b"ok\r\n"),
+            (b"mac set ch status 3 on\r\n", b"ok\r\n"),
+            (b"mac set ch freq 4 867100000\r\n", b"ok\r\n"),
+            (b"mac set ch dcycle 4 999\r\n", b"invalid_param\r\n"),
+            // Rollback of channel 3 and 4, channel 5 is left untouched
+            (b"mac set ch freq 3 867500000\r\n", b"ok\r\n"),
+            (b"mac set ch dcycle 3 302\r\n", b"ok\r\n"),
+            (b"mac set ch drrange 3 0 5\r\n", b"ok\r\n"),
+            (b"mac set ch status 3 on\r\n", b"ok\r\n"),
+            (b"mac set ch status 4 off\r\n", b"ok\r\n"),
+            (b"mac set ch dcycle 4 0\r\n", b"ok\r\n"),
+            (b"mac set ch drrange 4 0 5\r\n", b"ok\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut plan = ChannelPlan::new();
+        for id in 3..6 {
+            plan.set(id, channel()).unwrap();
+        }
+        assert_eq!(rn.apply_channel_plan(&plan), Err(Error::CommandFailed));
+        mock.done();
+    }
+
+    #[test]
+    fn sub_band_channels() {
+        assert!(SubBand::Sb2.contains(8));
+        assert!(SubBand::Sb2.contains(15));
+        assert!(SubBand::Sb2.contains(65));
+        assert!(!SubBand::Sb2.contains(7));
+        assert!(!SubBand::Sb2.contains(16));
+        assert!(!SubBand::Sb2.contains(64));
+        assert_eq!(SubBand::Sb8.wide_channel(), 71);
+    }
+
+    #[test]
+    fn set_sub_band() {
+        let mut commands = vec![];
+        for id in 8..16 {
+            commands.push(format!("mac set ch status {} on\r\n", id));
+        }
+        commands.push("mac set ch drrange 65 4 4\r\n".to_string());
+        commands.push("mac set ch status 65 on\r\n".to_string());
+        for id in (0..8).chain(16..65).chain(66..72) {
+            commands.push(format!("mac set ch status {} off\r\n", id));
+        }
+        assert_eq!(commands.len(), 73);
+        let expectations: Vec<_> = commands
+            .iter()
+            .flat_map(|cmd| command(cmd.as_bytes(), b"ok\r\n"))
+            .collect();
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2903_915(mock.clone());
+        assert_eq!(rn.set_sub_band(SubBand::Sb2), Ok(()));
+        mock.done();
+    }
+
+    #[test]
+    fn set_sub_band_rejected() {
+        let expectations = command(b"mac set ch status 0 on\r\n", b"invalid_param\r\n");
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2903_915(mock.clone());
+        assert_eq!(rn.set_sub_band(SubBand::Sb1), Err(Error::CommandFailed));
+        mock.done();
+    }
+
+    #[test]
+    fn enabled_channels() {
+        let expectations: Vec<_> = (0..72)
+            .flat_map(|id| {
+                let response: &[u8] = if SubBand::Sb1.contains(id) {
+                    b"on\r\n"
+                } else {
+                    b"off\r\n"
+                };
+                command(format!("mac get ch status {}\r\n", id).as_bytes(), response)
+            })
+            .collect();
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2903_915(mock.clone());
+        let enabled = rn.enabled_channels().unwrap();
+        for (id, enabled) in enabled.iter().enumerate() {
+            assert_eq!(*enabled, id < 8 || id == 64, "Channel {}", id);
+        }
+        mock.done();
+    }
+
+    #[test]
+    fn channel_id_out_of_range() {
+        let expectations = [];
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2903_915(mock.clone());
+        assert_eq!(rn.set_channel_enabled(72, true), Err(Error::BadParameter));
+        assert_eq!(
+            rn.set_channel_data_rate_range(64, DataRateUs::Sf8Bw500, DataRateUs::Sf10Bw125),
+            Err(Error::BadParameter)
+        );
+        mock.done();
+    }
+}
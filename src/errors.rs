@@ -1,5 +1,6 @@
 //! Error types used in this driver.
 
+use core::fmt;
 use core::str::Utf8Error;
 
 /// A collection of errors that can occur.
@@ -24,6 +25,14 @@ pub enum Error<S> {
     SleepMode,
     /// The module is in an invalid state.
     InvalidState,
+    /// No response was received within the configured timeout.
+    Timeout,
+    /// The command is not supported by the firmware of the module, see
+    /// [`capabilities`](../struct.Driver.html#method.capabilities).
+    Unsupported,
+    /// Asynchronous events were dropped, because more events arrived while
+    /// waiting for command responses than could be queued.
+    EventOverflow,
 }
 
 impl<S> From<Utf8Error> for Error<S> {
@@ -110,5 +119,136 @@ impl<S> From<Utf8Error> for TxError<S> {
     }
 }
 
+/// Errors that can occur when transmitting or receiving with the radio.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RadioError<S> {
+    /// Invalid data or receive window size.
+    BadParameter,
+    /// The transceiver is busy.
+    Busy,
+    /// The MAC could not be paused.
+    MacNotPaused,
+    /// Transmission was not successful (the radio watchdog timer expired).
+    TxUnsuccessful,
+    /// No packet was received within the receive window (or before the radio
+    /// watchdog timer expired).
+    RxTimeout,
+    /// The received packet does not fit into the provided buffer.
+    BufferTooSmall,
+    /// The received packet is not valid hex.
+    InvalidHex,
+    /// Unknown response.
+    UnknownResponse,
+    /// Another error occurred.
+    Other(Error<S>),
+}
+
+impl<S> From<Error<S>> for RadioError<S> {
+    fn from(other: Error<S>) -> Self {
+        RadioError::Other(other)
+    }
+}
+
+impl<S> From<Utf8Error> for RadioError<S> {
+    fn from(_: Utf8Error) -> Self {
+        RadioError::Other(Error::EncodingError)
+    }
+}
+
+/// Errors that can occur when decoding a downlink payload.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The target buffer is too small for the decoded payload.
+    BufferTooSmall,
+    /// The payload is not a valid hex string.
+    InvalidHex,
+}
+
+/// Errors that can occur when building a channel plan.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChannelPlanError {
+    /// The channel is one of the default channels (0–2), which cannot be
+    /// reconfigured.
+    ReadOnlyChannel,
+    /// The channel ID is out of range.
+    InvalidChannel,
+    /// The frequency is outside of the band.
+    InvalidFrequency,
+    /// The minimum data rate is higher than the maximum data rate.
+    InvalidDataRateRange,
+}
+
+/// Errors that can occur when parsing an Intel HEX firmware image.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum HexError {
+    /// A line is not a valid Intel HEX record.
+    InvalidRecord {
+        /// The line number (starting at 1).
+        line: usize,
+    },
+    /// The checksum of a record does not match its contents.
+    InvalidChecksum {
+        /// The line number (starting at 1).
+        line: usize,
+    },
+    /// The record type is not supported.
+    UnsupportedRecordType {
+        /// The line number (starting at 1).
+        line: usize,
+    },
+    /// The data records are not sorted by address, or they overlap.
+    Unordered {
+        /// The line number (starting at 1).
+        line: usize,
+    },
+    /// The image does not end with an end-of-file record.
+    MissingEndOfFile,
+}
+
+/// Errors that can occur while talking to the bootloader.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BootloaderError<S> {
+    /// The bootloader did not echo the command that was sent.
+    UnexpectedResponse,
+    /// The bootloader reported that the command failed.
+    CommandFailed,
+    /// The flash geometry reported by the bootloader is not supported.
+    UnsupportedDevice,
+    /// The checksum of the flash row at this address does not match the
+    /// firmware image.
+    VerifyFailed(u32),
+    /// Another error occurred.
+    Other(Error<S>),
+}
+
+impl<S> From<Error<S>> for BootloaderError<S> {
+    fn from(other: Error<S>) -> Self {
+        BootloaderError::Other(other)
+    }
+}
+
+/// A failed conversion, containing the value before the conversion and the
+/// error.
+///
+/// This is used by the state transitions of the
+/// [typestate](../typestate/index.html) layer and by
+/// [`detect`](../fn.detect.html), so that the driver or the serial port is
+/// not lost.
+pub struct TransitionError<T, E> {
+    /// The value before the conversion (e.g. the previous state), which can
+    /// be used to retry.
+    pub state: T,
+    /// The error that occurred.
+    pub error: E,
+}
+
+impl<T, E: fmt::Debug> fmt::Debug for TransitionError<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransitionError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
 /// A `Result<T, Error>`.
 pub type RnResult<T, S> = Result<T, Error<S>>;
@@ -0,0 +1,624 @@
+//! General purpose I/O pins of the module.
+//!
+//! The RN2483 and RN2903 expose the pins GPIO0–GPIO14, UART_CTS, UART_RTS,
+//! TEST0 and TEST1, which can be used as digital outputs, digital inputs and
+//! (on some pins) analog inputs. This allows using the module as the only
+//! microcontroller in simple sensor nodes.
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use rn2xx3::gpio::{Pin, PinMode};
+//!
+//! let mut rn = rn2xx3::rn2483_868(serialport);
+//! rn.set_pin(Pin::Gpio0, true).expect("Could not set pin");
+//!
+//! rn.set_pin_mode(Pin::Gpio1, PinMode::Analog).expect("Could not set pin mode");
+//! let value = rn.read_analog(Pin::Gpio1).expect("Could not read pin");
+//! ```
+//!
+//! Setting the pin mode and reading inputs requires firmware 1.0.5 or newer.
+//!
+//! If the `embedded-hal-02` feature is enabled, a
+//! [`DigitalOutput`](struct.DigitalOutput.html) implements the `OutputPin`
+//! trait from `embedded-hal` 0.2.
+//!
+//! To use several pins at once, e.g. with existing sensor drivers, share the
+//! driver through a `RefCell`. [`RnOutputPin`](struct.RnOutputPin.html),
+//! [`RnInputPin`](struct.RnInputPin.html) and [`RnAdc`](struct.RnAdc.html)
+//! implement the `OutputPin`, `InputPin` and `adc::OneShot` traits from
+//! `embedded-hal` 0.2:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use core::cell::RefCell;
+//! use embedded_hal::adc::OneShot;
+//! use embedded_hal::digital::v2::OutputPin;
+//! use rn2xx3::gpio::{analog, Pin, RnAdc, RnOutputPin};
+//!
+//! let rn = RefCell::new(rn2xx3::rn2483_868(serialport));
+//! let mut led = RnOutputPin::new(&rn, Pin::Gpio0).expect("Could not set pin mode");
+//! let mut adc = RnAdc::new(&rn);
+//!
+//! led.set_high().expect("Could not set pin");
+//! let value: u16 = nb::block!(adc.read(&mut analog::Gpio1)).expect("Could not read pin");
+//! ```
+
+#[cfg(feature = "embedded-hal-02")]
+use core::cell::{RefCell, RefMut};
+use core::convert::TryFrom;
+
+use crate::errors::{Error, RnResult};
+use crate::protocol;
+use crate::serial::Serial;
+use crate::timeout::{NoClock, Timer};
+use crate::{Driver, Frequency};
+
+/// A general purpose I/O pin of the module.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Pin {
+    Gpio0,
+    Gpio1,
+    Gpio2,
+    Gpio3,
+    Gpio4,
+    Gpio5,
+    Gpio6,
+    Gpio7,
+    Gpio8,
+    Gpio9,
+    Gpio10,
+    Gpio11,
+    Gpio12,
+    Gpio13,
+    Gpio14,
+    UartCts,
+    UartRts,
+    Test0,
+    Test1,
+}
+
+impl Pin {
+    /// Return whether the pin can be used as analog input.
+    pub fn supports_analog(self) -> bool {
+        matches!(
+            self,
+            Pin::Gpio0
+                | Pin::Gpio1
+                | Pin::Gpio2
+                | Pin::Gpio3
+                | Pin::Gpio5
+                | Pin::Gpio6
+                | Pin::Gpio7
+                | Pin::Gpio8
+                | Pin::Gpio9
+                | Pin::Gpio10
+                | Pin::Gpio11
+                | Pin::Gpio12
+                | Pin::Gpio13
+        )
+    }
+}
+
+impl From<Pin> for &str {
+    fn from(pin: Pin) -> Self {
+        match pin {
+            Pin::Gpio0 => "GPIO0",
+            Pin::Gpio1 => "GPIO1",
+            Pin::Gpio2 => "GPIO2",
+            Pin::Gpio3 => "GPIO3",
+            Pin::Gpio4 => "GPIO4",
+            Pin::Gpio5 => "GPIO5",
+            Pin::Gpio6 => "GPIO6",
+            Pin::Gpio7 => "GPIO7",
+            Pin::Gpio8 => "GPIO8",
+            Pin::Gpio9 => "GPIO9",
+            Pin::Gpio10 => "GPIO10",
+            Pin::Gpio11 => "GPIO11",
+            Pin::Gpio12 => "GPIO12",
+            Pin::Gpio13 => "GPIO13",
+            Pin::Gpio14 => "GPIO14",
+            Pin::UartCts => "UART_CTS",
+            Pin::UartRts => "UART_RTS",
+            Pin::Test0 => "TEST0",
+            Pin::Test1 => "TEST1",
+        }
+    }
+}
+
+impl TryFrom<&str> for Pin {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "GPIO0" => Ok(Pin::Gpio0),
+            "GPIO1" => Ok(Pin::Gpio1),
+            "GPIO2" => Ok(Pin::Gpio2),
+            "GPIO3" => Ok(Pin::Gpio3),
+            "GPIO4" => Ok(Pin::Gpio4),
+            "GPIO5" => Ok(Pin::Gpio5),
+            "GPIO6" => Ok(Pin::Gpio6),
+            "GPIO7" => Ok(Pin::Gpio7),
+            "GPIO8" => Ok(Pin::Gpio8),
+            "GPIO9" => Ok(Pin::Gpio9),
+            "GPIO10" => Ok(Pin::Gpio10),
+            "GPIO11" => Ok(Pin::Gpio11),
+            "GPIO12" => Ok(Pin::Gpio12),
+            "GPIO13" => Ok(Pin::Gpio13),
+            "GPIO14" => Ok(Pin::Gpio14),
+            "UART_CTS" => Ok(Pin::UartCts),
+            "UART_RTS" => Ok(Pin::UartRts),
+            "TEST0" => Ok(Pin::Test0),
+            "TEST1" => Ok(Pin::Test1),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The mode of a general purpose I/O pin.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PinMode {
+    /// Digital output (default).
+    DigitalOutput,
+    /// Digital input.
+    DigitalInput,
+    /// Analog input, see [`Pin::supports_analog`](enum.Pin.html#method.supports_analog).
+    Analog,
+}
+
+impl From<PinMode> for &str {
+    fn from(mode: PinMode) -> Self {
+        match mode {
+            PinMode::DigitalOutput => "digout",
+            PinMode::DigitalInput => "digin",
+            PinMode::Analog => "ana",
+        }
+    }
+}
+
+impl TryFrom<&str> for PinMode {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "digout" => Ok(PinMode::DigitalOutput),
+            "digin" => Ok(PinMode::DigitalInput),
+            "ana" => Ok(PinMode::Analog),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A pin used as digital output, borrowing the driver. See
+/// [`digital_output`](../struct.Driver.html#method.digital_output).
+pub struct DigitalOutput<'a, F: Frequency, S, C = NoClock> {
+    driver: &'a mut Driver<F, S, C>,
+    pin: Pin,
+}
+
+/// GPIO commands.
+impl<F, S, C, E> Driver<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Set the mode of the pin (firmware 1.0.5 and later).
+    ///
+    /// If the pin does not support the analog mode, `Error::BadParameter` is
+    /// returned.
+    pub fn set_pin_mode(&mut self, pin: Pin, mode: PinMode) -> RnResult<(), E> {
+        if mode == PinMode::Analog && !pin.supports_analog() {
+            return Err(Error::BadParameter);
+        }
+        self.ensure_supported(|c| c.gpio_input)?;
+        self.send_raw_command_ok(&["sys set pinmode ", pin.into(), " ", mode.into()])
+    }
+
+    /// Set the state of a pin that is configured as digital output.
+    pub fn set_pin(&mut self, pin: Pin, high: bool) -> RnResult<(), E> {
+        let state = if high { "1" } else { "0" };
+        self.send_raw_command_ok(&["sys set pindig ", pin.into(), " ", state])
+    }
+
+    /// Return the state of a pin that is configured as digital input
+    /// (firmware 1.0.5 and later).
+    pub fn get_pin_digital(&mut self, pin: Pin) -> RnResult<bool, E> {
+        self.ensure_supported(|c| c.gpio_input)?;
+        let state = self.send_raw_command_str(&["sys get pindig ", pin.into()])?;
+        match state {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => Err(Error::ParsingError),
+        }
+    }
+
+    /// Read the value (0–1023) of a pin that is configured as analog input
+    /// (firmware 1.0.5 and later).
+    ///
+    /// If the pin does not support analog input, `Error::BadParameter` is
+    /// returned.
+    pub fn read_analog(&mut self, pin: Pin) -> RnResult<u16, E> {
+        if !pin.supports_analog() {
+            return Err(Error::BadParameter);
+        }
+        self.ensure_supported(|c| c.gpio_input)?;
+        let value = self.send_raw_command_str(&["sys get pinana ", pin.into()])?;
+        protocol::parse_number(value)
+    }
+
+    /// Return a handle that uses the pin as digital output.
+    ///
+    /// On firmware 1.0.5 and later, the pin is switched to digital output
+    /// mode, since it may have been used as input before. The firmware
+    /// version is queried once for this, see
+    /// [`capabilities`](../struct.Driver.html#method.capabilities).
+    pub fn digital_output(&mut self, pin: Pin) -> RnResult<DigitalOutput<'_, F, S, C>, E> {
+        self.set_output_mode(pin)?;
+        Ok(DigitalOutput { driver: self, pin })
+    }
+
+    /// Switch the pin to digital output mode, if the firmware supports pin
+    /// modes. Older firmware only supports digital outputs.
+    fn set_output_mode(&mut self, pin: Pin) -> RnResult<(), E> {
+        if self.capabilities()?.gpio_input {
+            self.set_pin_mode(pin, PinMode::DigitalOutput)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F, S, C, E> DigitalOutput<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Return the pin.
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
+    /// Set the pin state.
+    pub fn set(&mut self, high: bool) -> RnResult<(), E> {
+        self.driver.set_pin(self.pin, high)
+    }
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<F, S, C, E> embedded_hal::digital::v2::OutputPin for DigitalOutput<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    type Error = Error<E>;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true)
+    }
+}
+
+/// A pin used as digital output, sharing the driver with other pins through
+/// a `RefCell`.
+#[cfg(feature = "embedded-hal-02")]
+pub struct RnOutputPin<'a, F: Frequency, S, C = NoClock> {
+    driver: &'a RefCell<Driver<F, S, C>>,
+    pin: Pin,
+}
+
+/// A pin used as digital input, sharing the driver with other pins through
+/// a `RefCell`.
+#[cfg(feature = "embedded-hal-02")]
+pub struct RnInputPin<'a, F: Frequency, S, C = NoClock> {
+    driver: &'a RefCell<Driver<F, S, C>>,
+    pin: Pin,
+}
+
+/// The analog to digital converter of the module, sharing the driver with
+/// other pins through a `RefCell`.
+///
+/// The channels are the types in the [`analog`](analog/index.html) module.
+/// Every pin is switched to analog mode before it is read for the first
+/// time.
+#[cfg(feature = "embedded-hal-02")]
+pub struct RnAdc<'a, F: Frequency, S, C = NoClock> {
+    driver: &'a RefCell<Driver<F, S, C>>,
+    /// Bit mask of the pins that have been switched to analog mode.
+    analog_pins: u32,
+}
+
+/// Borrow the shared driver. A driver that is already borrowed results in
+/// `Error::InvalidState`.
+#[cfg(feature = "embedded-hal-02")]
+fn borrow<F: Frequency, S, C, E>(
+    driver: &RefCell<Driver<F, S, C>>,
+) -> RnResult<RefMut<'_, Driver<F, S, C>>, E> {
+    driver.try_borrow_mut().map_err(|_| Error::InvalidState)
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<'a, F, S, C, E> RnOutputPin<'a, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Use the pin of the shared driver as digital output.
+    ///
+    /// On firmware 1.0.5 and later, the pin is switched to digital output
+    /// mode, see
+    /// [`digital_output`](../struct.Driver.html#method.digital_output).
+    pub fn new(driver: &'a RefCell<Driver<F, S, C>>, pin: Pin) -> RnResult<Self, E> {
+        borrow(driver)?.set_output_mode(pin)?;
+        Ok(Self { driver, pin })
+    }
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<F, S, C, E> embedded_hal::digital::v2::OutputPin for RnOutputPin<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    type Error = Error<E>;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        borrow(self.driver)?.set_pin(self.pin, false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        borrow(self.driver)?.set_pin(self.pin, true)
+    }
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<'a, F, S, C, E> RnInputPin<'a, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Switch the pin of the shared driver to digital input mode (firmware
+    /// 1.0.5 and later).
+    pub fn new(driver: &'a RefCell<Driver<F, S, C>>, pin: Pin) -> RnResult<Self, E> {
+        borrow(driver)?.set_pin_mode(pin, PinMode::DigitalInput)?;
+        Ok(Self { driver, pin })
+    }
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<F, S, C, E> embedded_hal::digital::v2::InputPin for RnInputPin<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    type Error = Error<E>;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        borrow(self.driver)?.get_pin_digital(self.pin)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<'a, F, S, C, E> RnAdc<'a, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Use the analog inputs of the shared driver (firmware 1.0.5 and later).
+    pub fn new(driver: &'a RefCell<Driver<F, S, C>>) -> Self {
+        Self {
+            driver,
+            analog_pins: 0,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-02")]
+impl<'a, F, S, C, E, P> embedded_hal::adc::OneShot<RnAdc<'a, F, S, C>, u16, P>
+    for RnAdc<'a, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+    P: embedded_hal::adc::Channel<RnAdc<'a, F, S, C>, ID = Pin>,
+{
+    type Error = Error<E>;
+
+    fn read(&mut self, _pin: &mut P) -> nb::Result<u16, Self::Error> {
+        let pin = P::channel();
+        let mut driver = borrow(self.driver)?;
+        let mask = 1 << pin as u32;
+        if self.analog_pins & mask == 0 {
+            driver.set_pin_mode(pin, PinMode::Analog)?;
+            self.analog_pins |= mask;
+        }
+        Ok(driver.read_analog(pin)?)
+    }
+}
+
+/// The pins that can be used as channels of the [`RnAdc`](../struct.RnAdc.html).
+#[cfg(feature = "embedded-hal-02")]
+pub mod analog {
+    use embedded_hal::adc::Channel;
+
+    use super::{Pin, RnAdc};
+    use crate::Frequency;
+
+    /// Analog input GPIO0.
+    pub struct Gpio0;
+    /// Analog input GPIO1.
+    pub struct Gpio1;
+    /// Analog input GPIO2.
+    pub struct Gpio2;
+    /// Analog input GPIO3.
+    pub struct Gpio3;
+    /// Analog input GPIO5.
+    pub struct Gpio5;
+    /// Analog input GPIO6.
+    pub struct Gpio6;
+    /// Analog input GPIO7.
+    pub struct Gpio7;
+    /// Analog input GPIO8.
+    pub struct Gpio8;
+    /// Analog input GPIO9.
+    pub struct Gpio9;
+    /// Analog input GPIO10.
+    pub struct Gpio10;
+    /// Analog input GPIO11.
+    pub struct Gpio11;
+    /// Analog input GPIO12.
+    pub struct Gpio12;
+    /// Analog input GPIO13.
+    pub struct Gpio13;
+
+    macro_rules! channel {
+        ($type:ident, $pin:ident) => {
+            impl<'a, F: Frequency, S, C> Channel<RnAdc<'a, F, S, C>> for $type {
+                type ID = Pin;
+
+                fn channel() -> Pin {
+                    Pin::$pin
+                }
+            }
+        };
+    }
+
+    channel!(Gpio0, Gpio0);
+    channel!(Gpio1, Gpio1);
+    channel!(Gpio2, Gpio2);
+    channel!(Gpio3, Gpio3);
+    channel!(Gpio5, Gpio5);
+    channel!(Gpio6, Gpio6);
+    channel!(Gpio7, Gpio7);
+    channel!(Gpio8, Gpio8);
+    channel!(Gpio9, Gpio9);
+    channel!(Gpio10, Gpio10);
+    channel!(Gpio11, Gpio11);
+    channel!(Gpio12, Gpio12);
+    channel!(Gpio13, Gpio13);
+}
+
+#[cfg(test)]
+#[cfg(feature = "embedded-hal-02")]
+mod tests {
+    use super::*;
+
+    use embedded_hal::digital::v2::OutputPin;
+    use embedded_hal_mock::serial::Mock as SerialMock;
+
+    use crate::rn2483_868;
+    use crate::testing::{commands, version};
+
+    #[test]
+    fn shared_pins() {
+        use embedded_hal::adc::OneShot;
+        use embedded_hal::digital::v2::InputPin;
+
+        let mut expectations = version(b"RN2483 1.0.5 Oct 31 2018 15:06:52");
+        expectations.extend(commands(&[
+            (b"sys set pinmode GPIO0 digout\r\n", b"ok\r\n"),
+            (b"sys set pinmode GPIO3 digin\r\n", b"ok\r\n"),
+            (b"sys set pindig GPIO0 1\r\n", b"ok\r\n"),
+            (b"sys get pindig GPIO3\r\n", b"0\r\n"),
+            (b"sys set pinmode GPIO1 ana\r\n", b"ok\r\n"),
+            (b"sys get pinana GPIO1\r\n", b"512\r\n"),
+            (b"sys get pinana GPIO1\r\n", b"513\r\n"),
+        ]));
+        let mut mock = SerialMock::new(&expectations);
+        let rn = RefCell::new(rn2483_868(mock.clone()));
+        let mut output = RnOutputPin::new(&rn, Pin::Gpio0).unwrap();
+        let input = RnInputPin::new(&rn, Pin::Gpio3).unwrap();
+        let mut adc = RnAdc::new(&rn);
+        assert!(output.set_high().is_ok());
+        assert_eq!(input.is_low(), Ok(true));
+        assert_eq!(adc.read(&mut analog::Gpio1), Ok(512));
+        assert_eq!(adc.read(&mut analog::Gpio1), Ok(513));
+        mock.done();
+    }
+
+    /// A driver that is borrowed elsewhere results in an error. Firmware
+    /// older than 1.0.5 does not support pin modes, so the mode is not set.
+    #[test]
+    fn shared_borrowed() {
+        let expectations = version(b"RN2483 1.0.3 Mar 22 2017 06:00:42");
+        let mut mock = SerialMock::new(&expectations);
+        let rn = RefCell::new(rn2483_868(mock.clone()));
+        let mut output = RnOutputPin::new(&rn, Pin::Gpio0).unwrap();
+        let _borrowed = rn.borrow_mut();
+        assert_eq!(output.set_low(), Err(Error::InvalidState));
+        drop(_borrowed);
+        mock.done();
+    }
+
+    #[test]
+    fn pin_names() {
+        for name in &["GPIO0", "GPIO14", "UART_CTS", "UART_RTS", "TEST0", "TEST1"] {
+            let pin = Pin::try_from(*name).unwrap();
+            assert_eq!(<&str>::from(pin), *name);
+        }
+        assert_eq!(Pin::try_from("GPIO15"), Err(()));
+    }
+
+    #[test]
+    fn analog() {
+        let expectations = commands(&[
+            (b"sys set pinmode GPIO13 ana\r\n", b"ok\r\n"),
+            (b"sys get pinana GPIO13\r\n", b"1023\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert!(rn.set_pin_mode(Pin::Gpio13, PinMode::Analog).is_ok());
+        assert_eq!(rn.read_analog(Pin::Gpio13), Ok(1023));
+        mock.done();
+    }
+
+    /// Pins without ADC are rejected without sending a command.
+    #[test]
+    fn analog_unsupported_pin() {
+        let mut mock = SerialMock::new(&[]);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(
+            rn.set_pin_mode(Pin::Gpio4, PinMode::Analog),
+            Err(Error::BadParameter)
+        );
+        assert_eq!(rn.read_analog(Pin::UartCts), Err(Error::BadParameter));
+        mock.done();
+    }
+
+    #[test]
+    fn digital() {
+        let mut expectations = commands(&[
+            (b"sys set pinmode GPIO2 digin\r\n", b"ok\r\n"),
+            (b"sys get pindig GPIO2\r\n", b"1\r\n"),
+        ]);
+        // The pin is switched back to digital output
+        expectations.extend(version(b"RN2483 1.0.5 Oct 31 2018 15:06:52"));
+        expectations.extend(commands(&[
+            (b"sys set pinmode GPIO2 digout\r\n", b"ok\r\n"),
+            (b"sys set pindig GPIO2 1\r\n", b"ok\r\n"),
+            (b"sys set pindig GPIO2 0\r\n", b"ok\r\n"),
+        ]));
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert!(rn.set_pin_mode(Pin::Gpio2, PinMode::DigitalInput).is_ok());
+        assert_eq!(rn.get_pin_digital(Pin::Gpio2), Ok(true));
+        let mut output = rn.digital_output(Pin::Gpio2).unwrap();
+        assert!(output.set_high().is_ok());
+        assert!(output.set_low().is_ok());
+        mock.done();
+    }
+}
@@ -0,0 +1,129 @@
+//! Support for serial ports implementing the `embedded-io` traits.
+//!
+//! Wrap the serial port in a [`FromIo`](struct.FromIo.html) adapter, or use
+//! the constructors from this module which do that for you. Responses are
+//! read in blocks instead of byte by byte:
+//!
+//! ```no_run
+//! # struct Uart;
+//! # impl embedded_io::ErrorType for Uart { type Error = embedded_io::ErrorKind; }
+//! # impl embedded_io::Read for Uart {
+//! #     fn read(&mut self, _: &mut [u8]) -> Result<usize, Self::Error> { unimplemented!() }
+//! # }
+//! # impl embedded_io::Write for Uart {
+//! #     fn write(&mut self, _: &[u8]) -> Result<usize, Self::Error> { unimplemented!() }
+//! #     fn flush(&mut self) -> Result<(), Self::Error> { unimplemented!() }
+//! # }
+//! # let uart = Uart;
+//! let rn = rn2xx3::io::rn2483_868(uart);
+//! ```
+//!
+//! Nonblocking reads (see [`TryRead`](../serial/trait.TryRead.html)) are
+//! supported if the serial port implements `embedded_io::ReadReady`.
+
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::any::{AnyDriver, Band};
+use crate::errors::{Error, TransitionError};
+use crate::serial::{Serial, TryRead};
+use crate::uart::{BaudRateControl, WakeUp};
+use crate::{Driver, Freq433, Freq868, Freq915};
+
+/// Adapter that implements the [`Serial`](../serial/trait.Serial.html) trait
+/// for a serial port implementing the `embedded-io` traits.
+pub struct FromIo<S>(S);
+
+impl<S> FromIo<S> {
+    /// Wrap the specified serial port.
+    pub fn new(inner: S) -> Self {
+        Self(inner)
+    }
+
+    /// Return the wrapped serial port.
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S: Read + Write> Serial for FromIo<S> {
+    type Error = S::Error;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.0.read(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
+
+impl<S: Read + Write + ReadReady> TryRead for FromIo<S> {
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.0.read_ready()? {
+            self.0.read(buf)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+impl<S: WakeUp> WakeUp for FromIo<S> {
+    type Error = S::Error;
+
+    fn send_break(&mut self) -> Result<(), Self::Error> {
+        self.0.send_break()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+        self.0.set_baud_rate(baud_rate)
+    }
+}
+
+impl<S: BaudRateControl> BaudRateControl for FromIo<S> {
+    fn baud_rate(&self) -> u32 {
+        self.0.baud_rate()
+    }
+}
+
+/// Create a new driver instance for the RN2483 (433 MHz), wrapping the
+/// specified `embedded-io` serial port.
+pub fn rn2483_433<S: Read + Write>(serial: S) -> Driver<Freq433, FromIo<S>> {
+    crate::rn2483_433(FromIo::new(serial))
+}
+
+/// Create a new driver instance for the RN2483 (868 MHz), wrapping the
+/// specified `embedded-io` serial port.
+pub fn rn2483_868<S: Read + Write>(serial: S) -> Driver<Freq868, FromIo<S>> {
+    crate::rn2483_868(FromIo::new(serial))
+}
+
+/// Create a new driver instance for the RN2903 (915 MHz), wrapping the
+/// specified `embedded-io` serial port.
+pub fn rn2903_915<S: Read + Write>(serial: S) -> Driver<Freq915, FromIo<S>> {
+    crate::rn2903_915(FromIo::new(serial))
+}
+
+/// Detect the model and band of the module connected to the specified
+/// `embedded-io` serial port, and create a matching driver instance. See
+/// [`rn2xx3::detect`](../fn.detect.html).
+#[allow(clippy::type_complexity)]
+pub fn detect<S: Read + Write>(
+    serial: S,
+) -> Result<AnyDriver<FromIo<S>>, TransitionError<FromIo<S>, Error<S::Error>>> {
+    crate::detect(FromIo::new(serial))
+}
+
+/// Detect the model of the module connected to the specified `embedded-io`
+/// serial port, and create a matching driver instance for the specified
+/// band. See [`rn2xx3::detect_with_band`](../fn.detect_with_band.html).
+#[allow(clippy::type_complexity)]
+pub fn detect_with_band<S: Read + Write>(
+    serial: S,
+    band: Band,
+) -> Result<AnyDriver<FromIo<S>>, TransitionError<FromIo<S>, Error<S::Error>>> {
+    crate::detect_with_band(FromIo::new(serial), band)
+}
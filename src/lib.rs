@@ -7,7 +7,10 @@
 //!
 //! First, configure a serial port using a crate that implements the serial
 //! traits from `embedded_hal`, for example
-//! [serial](https://crates.io/crates/serial).
+//! [serial](https://crates.io/crates/serial). Serial ports implementing the
+//! traits from [embedded-io](https://crates.io/crates/embedded-io) can be
+//! used through the [`io`](io/index.html) module, see the
+//! [`serial`](serial/index.html) module for details.
 //!
 //! ```no_run
 //! use std::time::Duration;
@@ -163,46 +166,124 @@
 //! [2020-03-03T20:41:42Z DEBUG rn2xx3] Received response: "RN2483 1.0.3 Mar 22 2017 06:00:42"
 //! ...
 //! ```
+//!
+//! ## Async
+//!
+//! If the optional `async` feature is enabled, an async variant of the driver
+//! is available in the [`asynch`](asynch/index.html) module. It wraps a
+//! serial port implementing the `embedded-io-async` traits and can be used
+//! with async executors like Embassy or RTIC.
+//!
+//! The crate builds with Rust 1.60 or newer, but the `async` feature requires
+//! Rust 1.75 (async functions in traits).
+//!
+//! ## Typestate
+//!
+//! The [`typestate`](typestate/index.html) module wraps the driver in handles
+//! that track whether the module is joined, sleeping or has its MAC paused,
+//! so that commands which are invalid in the current state do not compile.
 
 #![cfg_attr(not(test), no_std)]
 
+pub mod any;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod bootloader;
+pub mod channel;
 pub mod errors;
+pub mod gpio;
+pub mod io;
+mod protocol;
+pub mod radio;
+pub mod serial;
+#[cfg(test)]
+mod testing;
+pub mod timeout;
+pub mod typestate;
+pub mod uart;
 mod utils;
 
 use core::convert::TryFrom;
 use core::marker::PhantomData;
-use core::str::{from_utf8, FromStr};
+use core::ops::RangeInclusive;
+use core::str::from_utf8;
 use core::time::Duration;
 
-use doc_comment::doc_comment;
-use embedded_hal::serial;
-use nb::block;
 use numtoa::NumToA;
 
 #[cfg(feature = "logging")]
 use core::fmt;
-#[cfg(feature = "logging")]
-use log;
-
-use crate::errors::{Error, JoinError, RnResult, TxError};
 
-const CR: u8 = 0x0d;
-const LF: u8 = 0x0a;
+use crate::any::{AnyDriver, Band};
+use crate::bootloader::Bootloader;
+use crate::errors::{DecodeError, Error, JoinError, RnResult, TransitionError, TxError};
+use crate::protocol::{hex_setter_getter, EventQueue, HeldLine, LineBuffer, CR, LF};
+use crate::serial::{Serial, TryRead};
+use crate::timeout::{Clock, NoClock, Timeouts, Timer};
+use crate::uart::{BaudRateControl, WakeUp};
 
 /// Marker trait implemented for all models / frequencies.
-pub trait Frequency {}
+pub trait Frequency {
+    /// The data rates valid in this band.
+    type DataRate: Copy + Into<&'static str> + for<'a> TryFrom<&'a str>;
+    /// The data rates valid for downlinks in the second receive window.
+    type DownlinkDataRate: Copy + Into<&'static str> + for<'a> TryFrom<&'a str>;
+    /// The transmit power levels supported in this band.
+    type TxPower: Copy + Into<&'static str> + for<'a> TryFrom<&'a str>;
+    /// Valid radio frequencies in Hz.
+    const RADIO_FREQUENCIES: RangeInclusive<u32>;
+    /// Valid radio output power values in dBm.
+    const RADIO_POWER: RangeInclusive<i8>;
+    /// The band argument of band specific MAC commands (e.g. `mac get rx2`).
+    /// The RN2903 only supports a single band, so this is `None`.
+    const MAC_BAND: Option<&'static str>;
+}
+/// Marker trait implemented for the bands of the RN2483, see
+/// [`into_band`](struct.Driver.html#method.into_band).
+pub trait Rn2483Frequency: Frequency {
+    /// The argument of `mac reset` for this band.
+    const BAND: &'static str;
+}
+
 /// Frequency type parameter for the RN2483 (433 MHz).
 pub struct Freq433;
 /// Frequency type parameter for the RN2483 (868 MHz).
 pub struct Freq868;
 /// Frequency type parameter for the RN2903 (915 MHz).
 pub struct Freq915;
-impl Frequency for Freq433 {}
-impl Frequency for Freq868 {}
-impl Frequency for Freq915 {}
+impl Frequency for Freq433 {
+    type DataRate = DataRateEuCn;
+    type DownlinkDataRate = DataRateEuCn;
+    type TxPower = TxPower433;
+    const RADIO_FREQUENCIES: RangeInclusive<u32> = 433_050_000..=434_790_000;
+    const RADIO_POWER: RangeInclusive<i8> = -3..=15;
+    const MAC_BAND: Option<&'static str> = Some(Self::BAND);
+}
+impl Rn2483Frequency for Freq433 {
+    const BAND: &'static str = "433";
+}
+impl Frequency for Freq868 {
+    type DataRate = DataRateEuCn;
+    type DownlinkDataRate = DataRateEuCn;
+    type TxPower = TxPower868;
+    const RADIO_FREQUENCIES: RangeInclusive<u32> = 863_000_000..=870_000_000;
+    const RADIO_POWER: RangeInclusive<i8> = -3..=15;
+    const MAC_BAND: Option<&'static str> = Some(Self::BAND);
+}
+impl Rn2483Frequency for Freq868 {
+    const BAND: &'static str = "868";
+}
+impl Frequency for Freq915 {
+    type DataRate = DataRateUs;
+    type DownlinkDataRate = DataRateUsDownlink;
+    type TxPower = TxPower915;
+    const RADIO_FREQUENCIES: RangeInclusive<u32> = 902_000_000..=928_000_000;
+    const RADIO_POWER: RangeInclusive<i8> = 2..=20;
+    const MAC_BAND: Option<&'static str> = None;
+}
 
 #[cfg(feature = "logging")]
-struct LoggableStrSlice<'o, 'i>(&'o [&'i str]);
+pub(crate) struct LoggableStrSlice<'o, 'i>(&'o [&'i str]);
 
 #[cfg(feature = "logging")]
 impl fmt::Display for LoggableStrSlice<'_, '_> {
@@ -215,7 +296,10 @@ impl fmt::Display for LoggableStrSlice<'_, '_> {
 }
 
 /// The main driver instance.
-pub struct Driver<F: Frequency, S> {
+///
+/// The optional clock type parameter is used to enforce read timeouts, see
+/// the [`timeout`](timeout/index.html) module.
+pub struct Driver<F: Frequency, S, C = NoClock> {
     /// Marker type with the module frequency.
     frequency: PhantomData<F>,
 
@@ -223,20 +307,86 @@ pub struct Driver<F: Frequency, S> {
     serial: S,
 
     /// Read buffer.
-    read_buf: [u8; 64],
+    read_buf: LineBuffer,
 
     /// This flag is set when entering sleep mode. As long as it is set,
     /// sending any command will be prevented.
     sleep: bool,
+
+    /// Clock used to enforce read timeouts.
+    clock: C,
+
+    /// Read timeouts. If this is `None`, the driver waits forever.
+    timeouts: Option<Timeouts>,
+
+    /// Asynchronous events that were received while waiting for command
+    /// responses.
+    pending_events: EventQueue,
+
+    /// The multicast down frame counter, while multicast is enabled. It is
+    /// used to tell multicast downlinks from unicast ones.
+    multicast_dnctr: Option<u32>,
+
+    /// A downlink received after an uplink, held while the multicast down
+    /// frame counter is queried.
+    held_downlink: HeldLine,
+
+    /// The capabilities of the firmware, once they have been queried.
+    capabilities: Option<Capabilities>,
 }
 
 /// List of all supported RN module models.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Model {
     RN2483,
     RN2903,
 }
 
+/// The parsed firmware version string, see
+/// [`firmware_info`](struct.Driver.html#method.firmware_info).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct FirmwareInfo<'a> {
+    /// The module model.
+    pub model: Model,
+    /// Major firmware version.
+    pub major: u8,
+    /// Minor firmware version.
+    pub minor: u8,
+    /// Patch firmware version.
+    pub patch: u8,
+    /// Build date (e.g. `Mar 22 2017 06:00:42`).
+    pub build_date: &'a str,
+}
+
+impl FirmwareInfo<'_> {
+    /// Return the commands that are supported by this firmware version.
+    pub fn capabilities(&self) -> Capabilities {
+        let version = (self.major, self.minor, self.patch);
+        Capabilities {
+            class_c: version >= (1, 0, 5),
+            multicast: version >= (1, 0, 5),
+            radio_rssi: version >= (1, 0, 5),
+            gpio_input: version >= (1, 0, 5),
+        }
+    }
+}
+
+/// The optional commands that are supported by the firmware of the module,
+/// see [`capabilities`](struct.Driver.html#method.capabilities).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Capabilities {
+    /// LoRaWAN Class C (`mac set class c`, firmware 1.0.5 and later).
+    pub class_c: bool,
+    /// Multicast sessions (`mac set mcast`, firmware 1.0.5 and later).
+    pub multicast: bool,
+    /// RSSI of the last received packet (`radio get rssi`, firmware 1.0.5
+    /// and later).
+    pub radio_rssi: bool,
+    /// Pin modes as well as digital and analog inputs (`sys set pinmode`,
+    /// firmware 1.0.5 and later).
+    pub gpio_input: bool,
+}
+
 /// The join procedure.
 #[derive(Debug, PartialEq, Eq)]
 pub enum JoinMode {
@@ -247,7 +397,7 @@ pub enum JoinMode {
 }
 
 /// Whether to send an uplink as confirmed or unconfirmed message.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum ConfirmationMode {
     /// Expect a confirmation from the gateway.
     Confirmed,
@@ -255,6 +405,17 @@ pub enum ConfirmationMode {
     Unconfirmed,
 }
 
+/// The LoRaWAN device class.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Class {
+    /// Class A: Downlinks can only be received in the two receive windows
+    /// following an uplink.
+    A,
+    /// Class C: The receiver is continuously open, downlinks can be received
+    /// at any time. Requires firmware 1.0.5 or newer.
+    C,
+}
+
 /// The data rates valid in Europe and China.
 ///
 /// Frequencies:
@@ -262,7 +423,7 @@ pub enum ConfirmationMode {
 /// - EU 863–870 MHz (LoRaWAN Specification (2015), Page 35, Table 14)
 /// - CN 779–787 MHz (LoRaWAN Specification (2015), Page 44, Table 25)
 /// - EU 433 MHz (LoRaWAN Specification (2015), Page 48, Table 31)
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub enum DataRateEuCn {
     /// Data Rate 0: SF 12 BW 125 (250 bit/s)
     Sf12Bw125,
@@ -315,7 +476,7 @@ impl TryFrom<&str> for DataRateEuCn {
 /// Frequencies:
 ///
 /// - US 902–928 MHz (LoRaWAN Specification (2015), Page 40, Table 18)
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub enum DataRateUs {
     /// Data Rate 0: SF 10 BW 125 (980 bit/s)
     Sf10Bw125,
@@ -355,66 +516,595 @@ impl TryFrom<&str> for DataRateUs {
     }
 }
 
+/// The downlink data rates valid in the USA.
+///
+/// Frequencies:
+///
+/// - US 902–928 MHz (LoRaWAN Specification (2015), Page 40, Table 18)
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum DataRateUsDownlink {
+    /// Data Rate 8: SF 12 BW 500 (980 bit/s)
+    Sf12Bw500,
+    /// Data Rate 9: SF 11 BW 500 (1760 bit/s)
+    Sf11Bw500,
+    /// Data Rate 10: SF 10 BW 500 (3900 bit/s)
+    Sf10Bw500,
+    /// Data Rate 11: SF 9 BW 500 (7000 bit/s)
+    Sf9Bw500,
+    /// Data Rate 12: SF 8 BW 500 (12500 bit/s)
+    Sf8Bw500,
+    /// Data Rate 13: SF 7 BW 500 (21900 bit/s)
+    Sf7Bw500,
+}
+
+impl From<DataRateUsDownlink> for &str {
+    fn from(dr: DataRateUsDownlink) -> Self {
+        match dr {
+            DataRateUsDownlink::Sf12Bw500 => "8",
+            DataRateUsDownlink::Sf11Bw500 => "9",
+            DataRateUsDownlink::Sf10Bw500 => "10",
+            DataRateUsDownlink::Sf9Bw500 => "11",
+            DataRateUsDownlink::Sf8Bw500 => "12",
+            DataRateUsDownlink::Sf7Bw500 => "13",
+        }
+    }
+}
+
+impl TryFrom<&str> for DataRateUsDownlink {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "8" => Ok(DataRateUsDownlink::Sf12Bw500),
+            "9" => Ok(DataRateUsDownlink::Sf11Bw500),
+            "10" => Ok(DataRateUsDownlink::Sf10Bw500),
+            "11" => Ok(DataRateUsDownlink::Sf9Bw500),
+            "12" => Ok(DataRateUsDownlink::Sf8Bw500),
+            "13" => Ok(DataRateUsDownlink::Sf7Bw500),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The transmit power levels of the RN2483 at 433 MHz.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TxPower433 {
+    /// Power index 0: 10 dBm
+    Dbm10,
+    /// Power index 1: 7 dBm
+    Dbm7,
+    /// Power index 2: 4 dBm
+    Dbm4,
+    /// Power index 3: 1 dBm
+    Dbm1,
+    /// Power index 4: -2 dBm
+    DbmMinus2,
+    /// Power index 5: -5 dBm
+    DbmMinus5,
+}
+
+impl TxPower433 {
+    /// Return the output power in dBm.
+    pub fn dbm(self) -> i8 {
+        match self {
+            TxPower433::Dbm10 => 10,
+            TxPower433::Dbm7 => 7,
+            TxPower433::Dbm4 => 4,
+            TxPower433::Dbm1 => 1,
+            TxPower433::DbmMinus2 => -2,
+            TxPower433::DbmMinus5 => -5,
+        }
+    }
+}
+
+impl From<TxPower433> for &str {
+    fn from(power: TxPower433) -> Self {
+        match power {
+            TxPower433::Dbm10 => "0",
+            TxPower433::Dbm7 => "1",
+            TxPower433::Dbm4 => "2",
+            TxPower433::Dbm1 => "3",
+            TxPower433::DbmMinus2 => "4",
+            TxPower433::DbmMinus5 => "5",
+        }
+    }
+}
+
+impl TryFrom<&str> for TxPower433 {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "0" => Ok(TxPower433::Dbm10),
+            "1" => Ok(TxPower433::Dbm7),
+            "2" => Ok(TxPower433::Dbm4),
+            "3" => Ok(TxPower433::Dbm1),
+            "4" => Ok(TxPower433::DbmMinus2),
+            "5" => Ok(TxPower433::DbmMinus5),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The transmit power levels of the RN2483 at 868 MHz.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TxPower868 {
+    /// Power index 1: 14 dBm
+    Dbm14,
+    /// Power index 2: 11 dBm
+    Dbm11,
+    /// Power index 3: 8 dBm
+    Dbm8,
+    /// Power index 4: 5 dBm
+    Dbm5,
+    /// Power index 5: 2 dBm
+    Dbm2,
+}
+
+impl TxPower868 {
+    /// Return the output power in dBm.
+    pub fn dbm(self) -> i8 {
+        match self {
+            TxPower868::Dbm14 => 14,
+            TxPower868::Dbm11 => 11,
+            TxPower868::Dbm8 => 8,
+            TxPower868::Dbm5 => 5,
+            TxPower868::Dbm2 => 2,
+        }
+    }
+}
+
+impl From<TxPower868> for &str {
+    fn from(power: TxPower868) -> Self {
+        match power {
+            TxPower868::Dbm14 => "1",
+            TxPower868::Dbm11 => "2",
+            TxPower868::Dbm8 => "3",
+            TxPower868::Dbm5 => "4",
+            TxPower868::Dbm2 => "5",
+        }
+    }
+}
+
+impl TryFrom<&str> for TxPower868 {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "1" => Ok(TxPower868::Dbm14),
+            "2" => Ok(TxPower868::Dbm11),
+            "3" => Ok(TxPower868::Dbm8),
+            "4" => Ok(TxPower868::Dbm5),
+            "5" => Ok(TxPower868::Dbm2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The transmit power levels of the RN2903 at 915 MHz.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TxPower915 {
+    /// Power index 5: 20 dBm
+    Dbm20,
+    /// Power index 7: 16 dBm
+    Dbm16,
+    /// Power index 8: 14 dBm
+    Dbm14,
+    /// Power index 9: 12 dBm
+    Dbm12,
+    /// Power index 10: 10 dBm
+    Dbm10,
+}
+
+impl TxPower915 {
+    /// Return the output power in dBm.
+    pub fn dbm(self) -> i8 {
+        match self {
+            TxPower915::Dbm20 => 20,
+            TxPower915::Dbm16 => 16,
+            TxPower915::Dbm14 => 14,
+            TxPower915::Dbm12 => 12,
+            TxPower915::Dbm10 => 10,
+        }
+    }
+}
+
+impl From<TxPower915> for &str {
+    fn from(power: TxPower915) -> Self {
+        match power {
+            TxPower915::Dbm20 => "5",
+            TxPower915::Dbm16 => "7",
+            TxPower915::Dbm14 => "8",
+            TxPower915::Dbm12 => "9",
+            TxPower915::Dbm10 => "10",
+        }
+    }
+}
+
+impl TryFrom<&str> for TxPower915 {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "5" => Ok(TxPower915::Dbm20),
+            "7" => Ok(TxPower915::Dbm16),
+            "8" => Ok(TxPower915::Dbm14),
+            "9" => Ok(TxPower915::Dbm12),
+            "10" => Ok(TxPower915::Dbm10),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A downlink received after an uplink transmission.
 #[derive(Debug, PartialEq)]
 pub struct Downlink<'a> {
     port: u8,
     hexdata: &'a str,
+    acknowledged: bool,
+    multicast: Option<bool>,
+}
+
+impl<'a> Downlink<'a> {
+    /// Return the port on which the downlink was received.
+    pub fn port(&self) -> u8 {
+        self.port
+    }
+
+    /// Return the payload as hex string.
+    pub fn hex(&self) -> &'a str {
+        self.hexdata
+    }
+
+    /// Return the length of the decoded payload in bytes.
+    pub fn len(&self) -> usize {
+        self.hexdata.len() / 2
+    }
+
+    /// Return whether the payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.hexdata.is_empty()
+    }
+
+    /// Decode the payload into the specified buffer and return the decoded
+    /// bytes.
+    ///
+    /// If the buffer is smaller than [`len()`](#method.len) bytes,
+    /// `DecodeError::BufferTooSmall` is returned.
+    pub fn decode_into<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], DecodeError> {
+        let len = self.len();
+        if buf.len() < len {
+            return Err(DecodeError::BufferTooSmall);
+        }
+        base16::decode_slice(self.hexdata, &mut buf[..len]).map_err(|_| DecodeError::InvalidHex)?;
+        Ok(&buf[..len])
+    }
+
+    /// Return whether the downlink acknowledged the uplink.
+    ///
+    /// This is the case for every downlink that was received in response to
+    /// a [confirmed](enum.ConfirmationMode.html#variant.Confirmed) uplink.
+    pub fn acknowledged(&self) -> bool {
+        self.acknowledged
+    }
+
+    /// Return whether the downlink was sent to the multicast address, or
+    /// `None` if this is unknown.
+    ///
+    /// This is only detected while multicast is enabled through
+    /// [`set_multicast`](struct.Driver.html#method.set_multicast), both for
+    /// downlinks returned by
+    /// [`transmit_hex`](struct.Driver.html#method.transmit_hex) and for
+    /// downlink [events](enum.Event.html). If several downlinks arrive before
+    /// the multicast down frame counter can be queried and only some of them
+    /// changed it, they cannot be told apart and `None` is returned for all
+    /// of them.
+    pub fn multicast(&self) -> Option<bool> {
+        self.multicast
+    }
+}
+
+/// An asynchronous event, sent by the module without a preceding command.
+///
+/// See [`poll_event`](struct.Driver.html#method.poll_event) and
+/// [`wait_event`](struct.Driver.html#method.wait_event).
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+    /// A downlink was received outside of a transmit command (e.g. in Class
+    /// C mode).
+    Downlink(Downlink<'a>),
+    /// The module reported a MAC error (`mac_err`).
+    MacError,
+    /// Continuous reception was stopped (`rxstop`).
+    RxStopped,
+}
+
+/// Quality of the link to the network, see
+/// [`link_quality`](struct.Driver.html#method.link_quality).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct LinkQuality {
+    /// Signal-to-noise ratio of the last received packet in dB.
+    pub snr: i8,
+    /// RSSI of the last received packet in dBm. This is `None` if the
+    /// firmware does not support reading the RSSI (before 1.0.5).
+    pub rssi: Option<i16>,
+    /// Demodulation margin in dB, as reported in the last Link Check Answer.
+    pub margin: u8,
+    /// Number of gateways that received the last Link Check Request.
+    pub gateways: u8,
+}
+
+/// The state of the LoRaWAN MAC, see [`MacStatus`](struct.MacStatus.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MacState {
+    /// No transmission or reception is in progress.
+    Idle,
+    /// An uplink is being transmitted.
+    TransmissionOccurring,
+    /// Waiting for the first receive window to open.
+    BeforeRx1,
+    /// The first receive window is open.
+    Rx1Open,
+    /// Waiting between the first and the second receive window.
+    BetweenRx1Rx2,
+    /// The second receive window is open.
+    Rx2Open,
+    /// Waiting before retransmitting a confirmed uplink.
+    RetransmissionDelay,
+    /// Waiting after an ABP join (`APB_delay`).
+    AbpDelay,
+}
+
+/// The decoded MAC status word, see
+/// [`mac_status`](struct.Driver.html#method.mac_status).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct MacStatus {
+    /// Whether the device has joined a network.
+    pub joined: bool,
+    /// The state of the MAC.
+    pub state: MacState,
+    /// Whether automatic replies are enabled.
+    pub auto_reply: bool,
+    /// Whether adaptive data rate is enabled.
+    pub adr: bool,
+    /// Whether the network requested the device to stop transmitting.
+    pub silent: bool,
+    /// Whether the MAC is paused.
+    pub mac_paused: bool,
+    /// Whether a downlink was received.
+    pub rx_done: bool,
+    /// Whether link checks are enabled.
+    pub link_check: bool,
+    /// Whether the network updated the channels.
+    pub channels_updated: bool,
+    /// Whether the network updated the output power.
+    pub output_power_updated: bool,
+    /// Whether the network updated the number of repetitions.
+    pub nb_rep_updated: bool,
+    /// Whether the network updated the duty cycle prescaler.
+    pub prescaler_updated: bool,
+    /// Whether the network updated the parameters of the second receive
+    /// window.
+    pub rx2_updated: bool,
+    /// Whether the network updated the receive window delays.
+    pub rx_timing_updated: bool,
+    /// Whether the network requested a rejoin.
+    pub rejoin_needed: bool,
+    /// Whether multicast is enabled.
+    pub multicast: bool,
+}
+
+impl MacStatus {
+    /// Return whether the MAC is idle and not paused, i.e. whether a join or
+    /// an uplink can be started.
+    pub fn is_ready(&self) -> bool {
+        self.state == MacState::Idle && !self.mac_paused
+    }
+}
+
+/// The battery level reported to the network in the Device Status Answer,
+/// see [`set_battery_level`](struct.Driver.html#method.set_battery_level).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BatteryLevel {
+    /// The device is connected to an external power source.
+    External,
+    /// The battery level, from 1 (minimum) to 254 (maximum).
+    Level(u8),
+    /// The device is not able to measure the battery level.
+    Unknown,
+}
+
+impl BatteryLevel {
+    /// Map a supply voltage in millivolts to a battery level, using the
+    /// specified discharge curve.
+    ///
+    /// The curve consists of `(millivolts, level)` points, sorted by
+    /// ascending voltage. Between two points, the level is interpolated
+    /// linearly. Voltages outside of the curve are mapped to the level of the
+    /// first or the last point. The resulting level is clamped to 1–254. If
+    /// the curve is empty, `BatteryLevel::Unknown` is returned.
+    ///
+    /// ```
+    /// use rn2xx3::BatteryLevel;
+    ///
+    /// let curve = [(2000, 1), (3000, 254)];
+    /// assert_eq!(BatteryLevel::from_millivolts(2500, &curve), BatteryLevel::Level(127));
+    /// ```
+    pub fn from_millivolts(millivolts: u16, curve: &[(u16, u8)]) -> Self {
+        let level = match curve.iter().position(|&(mv, _)| mv >= millivolts) {
+            None => match curve.last() {
+                Some(&(_, level)) => level,
+                None => return BatteryLevel::Unknown,
+            },
+            Some(0) => curve[0].1,
+            Some(i) => {
+                let (mv0, level0) = curve[i - 1];
+                let (mv1, level1) = curve[i];
+                let (mv0, level0, mv1, level1) = (
+                    i32::from(mv0),
+                    i32::from(level0),
+                    i32::from(mv1),
+                    i32::from(level1),
+                );
+                let level =
+                    level0 + (i32::from(millivolts) - mv0) * (level1 - level0) / (mv1 - mv0);
+                level as u8
+            }
+        };
+        BatteryLevel::Level(level.clamp(1, 254))
+    }
+}
+
+/// Configuration of the receive windows, see
+/// [`rx_windows`](struct.Driver.html#method.rx_windows).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RxWindows<D> {
+    /// Delay between the end of a transmission and the first receive window.
+    pub rx1_delay: Duration,
+    /// Delay between the end of a transmission and the second receive window.
+    pub rx2_delay: Duration,
+    /// Data rate of the second receive window.
+    pub rx2_data_rate: D,
+    /// Frequency of the second receive window in Hz.
+    pub rx2_frequency: u32,
+}
+
+/// Detect the model and band of the module connected to the specified serial
+/// port, and create a matching driver instance.
+///
+/// RN2483 modules keep their configured band (`mac get band`), use
+/// [`detect_with_band`](fn.detect_with_band.html) to select the band. If the
+/// module cannot be detected, the serial port is returned together with the
+/// error. See the [`any`](any/index.html) module for details.
+pub fn detect<S, E>(serial: S) -> Result<AnyDriver<S>, TransitionError<S, Error<E>>>
+where
+    S: Serial<Error = E>,
+{
+    any::detect(Driver::new(serial), None)
+}
+
+/// Detect the model of the module connected to the specified serial port,
+/// and create a matching driver instance.
+///
+/// The MAC of RN2483 modules is reset to the specified band (`mac reset`),
+/// which also resets all MAC parameters. The band is ignored for RN2903
+/// modules. If the module cannot be detected or the reset fails, the serial
+/// port is returned together with the error.
+pub fn detect_with_band<S, E>(
+    serial: S,
+    band: Band,
+) -> Result<AnyDriver<S>, TransitionError<S, Error<E>>>
+where
+    S: Serial<Error = E>,
+{
+    any::detect(Driver::new(serial), Some(band))
 }
 
 /// Create a new driver instance for the RN2483 (433 MHz), wrapping the
 /// specified serial port.
 pub fn rn2483_433<S, E>(serial: S) -> Driver<Freq433, S>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
 {
-    Driver {
-        frequency: PhantomData,
-        serial,
-        read_buf: [0; 64],
-        sleep: false,
-    }
+    Driver::new(serial)
 }
 
 /// Create a new driver instance for the RN2483 (868 MHz), wrapping the
 /// specified serial port.
 pub fn rn2483_868<S, E>(serial: S) -> Driver<Freq868, S>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
 {
-    Driver {
-        frequency: PhantomData,
-        serial,
-        read_buf: [0; 64],
-        sleep: false,
-    }
+    Driver::new(serial)
 }
 
 /// Create a new driver instance for the RN2903 (915 MHz), wrapping the
 /// specified serial port.
 pub fn rn2903_915<S, E>(serial: S) -> Driver<Freq915, S>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
+{
+    Driver::new(serial)
+}
+
+impl<F, S> Driver<F, S>
+where
+    S: Serial,
+    F: Frequency,
 {
-    Driver {
-        frequency: PhantomData,
-        serial,
-        read_buf: [0; 64],
-        sleep: false,
+    fn new(serial: S) -> Self {
+        Self {
+            frequency: PhantomData,
+            serial,
+            read_buf: LineBuffer::new(),
+            sleep: false,
+            clock: NoClock,
+            timeouts: None,
+            pending_events: EventQueue::new(),
+            multicast_dnctr: None,
+            held_downlink: HeldLine::new(),
+            capabilities: None,
+        }
     }
 }
 
 /// Basic commands.
-impl<F, S, E> Driver<F, S>
+impl<F, S, C, E> Driver<F, S, C>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
     F: Frequency,
+    C: Timer<S>,
 {
-    /// Write a single byte to the serial port.
+    /// Use the specified clock to enforce read timeouts.
+    ///
+    /// The [default timeouts](timeout/struct.Timeouts.html#impl-Default) are
+    /// used unless other timeouts are configured with
+    /// [`set_timeouts`](#method.set_timeouts). If a read does not complete
+    /// in time, `Error::Timeout` is returned.
+    ///
+    /// This requires a serial port that can be read without blocking, see
+    /// [`TryRead`](serial/trait.TryRead.html).
+    pub fn with_clock<C2: Clock>(self, clock: C2) -> Driver<F, S, C2>
+    where
+        S: TryRead,
+    {
+        Driver {
+            frequency: PhantomData,
+            serial: self.serial,
+            read_buf: self.read_buf,
+            sleep: self.sleep,
+            clock,
+            timeouts: Some(self.timeouts.unwrap_or_default()),
+            pending_events: self.pending_events,
+            multicast_dnctr: self.multicast_dnctr,
+            held_downlink: self.held_downlink,
+            capabilities: self.capabilities,
+        }
+    }
+
+    /// Change the frequency type parameter, without sending a command.
+    fn into_frequency<F2: Frequency>(self) -> Driver<F2, S, C> {
+        Driver {
+            frequency: PhantomData,
+            serial: self.serial,
+            read_buf: self.read_buf,
+            sleep: self.sleep,
+            clock: self.clock,
+            timeouts: self.timeouts,
+            pending_events: self.pending_events,
+            multicast_dnctr: self.multicast_dnctr,
+            held_downlink: self.held_downlink,
+            capabilities: self.capabilities,
+        }
+    }
+
+    /// Configure the read timeouts.
     ///
-    /// **Note:** For performance reasons, the `sleep` flag is not being
-    /// checked here. Make sure not to call this method while in sleep mode.
-    fn write_byte(&mut self, byte: u8) -> RnResult<(), E> {
-        block!(self.serial.write(byte)).map_err(Error::SerialWrite)
+    /// **Note:** Timeouts are only enforced if a clock has been configured
+    /// using [`with_clock`](#method.with_clock).
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = Some(timeouts);
+    }
+
+    /// Return the configured read timeouts, if any.
+    pub fn timeouts(&self) -> Option<Timeouts> {
+        self.timeouts
     }
 
     /// Ensure that the device is not currently in sleep mode.
@@ -428,50 +1118,93 @@ where
         }
     }
 
-    /// Write CR+LF bytes.
+    /// Write CR+LF bytes and flush the serial port.
     fn write_crlf(&mut self) -> RnResult<(), E> {
-        self.ensure_not_in_sleep_mode()?;
-        self.write_byte(CR)?;
-        self.write_byte(LF)
+        self.write_all(&[CR, LF])?;
+        self.serial.flush().map_err(Error::SerialWrite)
     }
 
     /// Write all bytes from the buffer to the serial port.
     fn write_all(&mut self, buffer: &[u8]) -> RnResult<(), E> {
         self.ensure_not_in_sleep_mode()?;
-        for byte in buffer {
-            self.write_byte(*byte)?;
-        }
-        Ok(())
-    }
-
-    /// Read a single byte from the serial port.
-    fn read_byte(&mut self) -> RnResult<u8, E> {
-        block!(self.serial.read()).map_err(Error::SerialRead)
+        self.serial.write_all(buffer).map_err(Error::SerialWrite)
     }
 
     /// Read a CR/LF terminated line from the serial port.
     ///
     /// The string is returned without the line termination.
+    ///
+    /// If timeouts are enabled, the line must be received within the
+    /// command timeout, otherwise `Error::Timeout` is returned.
     pub fn read_line(&mut self) -> RnResult<&[u8], E> {
-        let buflen = self.read_buf.len();
-        let mut i = 0;
+        let timeout = self.timeouts.map(|timeouts| timeouts.command);
+        self.read_line_timeout(timeout)
+    }
+
+    /// Read a CR/LF terminated line from the serial port, waiting at most for
+    /// the specified duration (or forever, if `timeout` is `None`).
+    fn read_line_timeout(&mut self, timeout: Option<Duration>) -> RnResult<&[u8], E> {
+        let line_len = self.wait_for_line(timeout)?;
+        let line = self.read_buf.take_line(line_len);
+        #[cfg(feature = "logging")]
+        log::debug!(
+            "Received response: {:?}",
+            from_utf8(line).unwrap_or("\"[invalid-utf8]\"")
+        );
+        Ok(line)
+    }
+
+    /// Read from the serial port until a complete line is buffered, waiting at
+    /// most for the specified duration (or forever, if `timeout` is `None`).
+    ///
+    /// Return the length of the line, which is not yet marked as consumed.
+    fn wait_for_line(&mut self, timeout: Option<Duration>) -> RnResult<usize, E> {
+        let start = self.clock.now_ms();
         loop {
-            match self.read_byte()? {
-                LF if self.read_buf[i - 1] == CR => {
-                    #[cfg(feature = "logging")]
-                    log::debug!(
-                        "Received response: {:?}",
-                        from_utf8(&self.read_buf[0..(i - 1)]).unwrap_or("\"[invalid-utf8]\"")
-                    );
-                    return Ok(&self.read_buf[0..(i - 1)]);
-                }
-                other => {
-                    self.read_buf[i] = other;
-                }
+            if let Some(line_len) = self.read_buf.find_line() {
+                return Ok(line_len);
             }
-            i += 1;
-            if i >= buflen {
-                return Err(Error::ReadBufferTooSmall);
+            self.fill_read_buf(start, timeout)?;
+        }
+    }
+
+    /// Read the next chunk of bytes from the serial port into the read
+    /// buffer. If no byte arrives within `timeout` after `start`, return
+    /// `Error::Timeout`.
+    fn fill_read_buf(&mut self, start: u64, timeout: Option<Duration>) -> RnResult<(), E> {
+        let spare = self.read_buf.spare()?;
+        let count = self
+            .clock
+            .timed_read(&mut self.serial, spare, start, timeout)?;
+        self.read_buf.commit(count);
+        Ok(())
+    }
+
+    /// Read the bytes that are available from the serial port into the read
+    /// buffer, without blocking. Return whether any bytes were read.
+    fn try_fill_read_buf(&mut self) -> RnResult<bool, E>
+    where
+        S: TryRead,
+    {
+        let spare = self.read_buf.spare()?;
+        let count = self.serial.try_read(spare).map_err(Error::SerialRead)?;
+        self.read_buf.commit(count);
+        Ok(count > 0)
+    }
+
+    /// Read the response to a command, waiting at most for the command
+    /// timeout.
+    ///
+    /// Asynchronous events (e.g. Class C downlinks) that arrive before the
+    /// response are not mistaken for the response. Instead, they are queued
+    /// and returned by the following calls to
+    /// [`poll_event`](#method.poll_event) or [`wait_event`](#method.wait_event).
+    fn read_response(&mut self) -> RnResult<&[u8], E> {
+        loop {
+            let timeout = self.timeouts.map(|timeouts| timeouts.command);
+            self.read_line_timeout(timeout)?;
+            if !self.pending_events.queue_event(self.read_buf.last_line()) {
+                return Ok(self.read_buf.last_line());
             }
         }
     }
@@ -492,9 +1225,12 @@ where
     }
 
     /// Send a raw command to the module and return the response.
+    ///
+    /// Asynchronous events received while waiting for the response are kept
+    /// for [`poll_event`](#method.poll_event).
     pub fn send_raw_command(&mut self, command: &[&str]) -> RnResult<&[u8], E> {
         self.send_raw_command_nowait(command)?;
-        self.read_line()
+        self.read_response()
     }
 
     /// Send a raw command and decode the resulting bytes to a `&str`.
@@ -507,11 +1243,7 @@ where
     /// is not 'OK', return `Error::CommandFailed`.
     fn send_raw_command_ok(&mut self, command: &[&str]) -> RnResult<(), E> {
         let response = self.send_raw_command(command)?;
-        if response == b"ok" {
-            Ok(())
-        } else {
-            Err(Error::CommandFailed)
-        }
+        protocol::parse_ok(response)
     }
 
     /// Clear the module serial buffers and ensure a known good state.
@@ -529,21 +1261,16 @@ where
     ///
     /// Unexpected errors while reading or writing are propagated to the
     /// caller.
-    pub fn ensure_known_state(&mut self) -> RnResult<(), E> {
+    ///
+    /// This method can also be used to recover after a command ran into a
+    /// timeout. It requires a serial port that can be read without blocking,
+    /// see [`TryRead`](serial/trait.TryRead.html).
+    pub fn ensure_known_state(&mut self) -> RnResult<(), E>
+    where
+        S: TryRead,
+    {
         // First, clear the input buffer
-        loop {
-            match self.serial.read() {
-                Ok(_) => {
-                    // A byte was returned, continue reading
-                    #[cfg(feature = "logging")]
-                    log::debug!("Clearing input buffer: Discarded 1 byte");
-                }
-                Err(nb::Error::WouldBlock) => break,
-                Err(nb::Error::Other(e)) => return Err(Error::SerialRead(e)),
-            }
-        }
-        #[cfg(feature = "logging")]
-        log::debug!("Input buffer is clear");
+        self.discard_input()?;
 
         // Max 3 attempts
         for _ in 0..3 {
@@ -552,11 +1279,11 @@ where
 
             // To ensure that there's no valid command in the input buffer, write
             // the letter 'z' followed by CRLF.
-            self.write_byte(b'z')?;
+            self.write_all(b"z")?;
             self.write_crlf()?;
 
             // Read the response, it should be "invalid_param".
-            match self.read_line()? {
+            match self.read_response()? {
                 b"invalid_param" => return Ok(()),
                 _other => {
                     #[cfg(feature = "logging")]
@@ -568,13 +1295,37 @@ where
         // Should not happen™
         Err(Error::InvalidState)
     }
+
+    /// Read and discard all bytes that are available from the serial port.
+    fn discard_input(&mut self) -> RnResult<(), E>
+    where
+        S: TryRead,
+    {
+        self.read_buf.clear();
+        let mut discard = [0; 16];
+        loop {
+            let count = self
+                .serial
+                .try_read(&mut discard)
+                .map_err(Error::SerialRead)?;
+            if count == 0 {
+                break;
+            }
+            #[cfg(feature = "logging")]
+            log::debug!("Clearing input buffer: Discarded {} bytes", count);
+        }
+        #[cfg(feature = "logging")]
+        log::debug!("Input buffer is clear");
+        Ok(())
+    }
 }
 
 /// System commands.
-impl<F, S, E> Driver<F, S>
+impl<F, S, C, E> Driver<F, S, C>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
     F: Frequency,
+    C: Timer<S>,
 {
     /// Destroy this driver instance, return the wrapped serial device.
     pub fn free(self) -> S {
@@ -595,17 +1346,22 @@ where
         self.send_raw_command_str(&["sys factoryRESET"])
     }
 
-    ///// Delete the current RN2483 module application firmware and ensure_known_state it
-    ///// for firmware upgrade. The module bootloader is then ready to receive
-    ///// new firmware.
-    /////
-    ///// This command is not unsafe in the sense of memory unsafety, but it can
-    ///// be dangerous because it removes the firmware.
-    //pub unsafe fn erase_fw(&mut self) -> RnResult<()> {
-    //    self.send_raw_command(&["sys eraseFW"])?;
-    //    TODO: Does this return anything?
-    //    Ok(())
-    //}
+    /// Delete the application firmware of the module and start the
+    /// bootloader, which is then ready to receive new firmware, see the
+    /// [`bootloader`](bootloader/index.html) module.
+    ///
+    /// **Warning:** The module cannot be used as a LoRaWAN module anymore
+    /// until new firmware has been written. If sending the command fails,
+    /// the driver is returned together with the error.
+    // The driver is returned by value on failure, just like on success.
+    #[allow(clippy::result_large_err)]
+    pub fn erase_fw(mut self) -> Result<Bootloader<S>, TransitionError<Self, Error<E>>> {
+        // The module does not respond to this command
+        match self.send_raw_command_nowait(&["sys eraseFW"]) {
+            Ok(()) => Ok(Bootloader::new(self.serial)),
+            Err(error) => Err(TransitionError { state: self, error }),
+        }
+    }
 
     /// Return the preprogrammed EUI node address as uppercase hex string.
     pub fn hweui(&mut self) -> RnResult<&str, E> {
@@ -620,17 +1376,43 @@ where
     /// Return the model of the module.
     pub fn model(&mut self) -> RnResult<Model, E> {
         let version = self.version()?;
-        match &version[0..6] {
-            "RN2483" => Ok(Model::RN2483),
-            "RN2903" => Ok(Model::RN2903),
-            _ => Err(Error::ParsingError),
+        protocol::parse_model(version)
+    }
+
+    /// Return the parsed firmware version.
+    pub fn firmware_info(&mut self) -> RnResult<FirmwareInfo<'_>, E> {
+        let version = self.version()?;
+        protocol::parse_firmware_info(version)
+    }
+
+    /// Return the optional commands that are supported by the firmware.
+    ///
+    /// The firmware version is only queried on the first call. Afterwards,
+    /// commands that are known to be unsupported (e.g. `set_class(Class::C)`
+    /// on firmware 1.0.3) return `Error::Unsupported` without being sent to
+    /// the module.
+    pub fn capabilities(&mut self) -> RnResult<Capabilities, E> {
+        if let Some(capabilities) = self.capabilities {
+            return Ok(capabilities);
+        }
+        let capabilities = self.firmware_info()?.capabilities();
+        self.capabilities = Some(capabilities);
+        Ok(capabilities)
+    }
+
+    /// Return `Error::Unsupported` if the capabilities are known and the
+    /// capability selected by `supported` is missing.
+    fn ensure_supported(&self, supported: fn(&Capabilities) -> bool) -> RnResult<(), E> {
+        match self.capabilities {
+            Some(ref capabilities) if !supported(capabilities) => Err(Error::Unsupported),
+            _ => Ok(()),
         }
     }
 
     /// Measure and return the Vdd voltage in millivolts.
     pub fn vdd(&mut self) -> RnResult<u16, E> {
         let vdd = self.send_raw_command_str(&["sys get vdd"])?;
-        vdd.parse().map_err(|_| Error::ParsingError)
+        protocol::parse_number(vdd)
     }
 
     /// Set the NVM byte at `addr` to the specified value.
@@ -638,22 +1420,13 @@ where
     /// The address must be between 0x300 and 0x3ff, otherwise
     /// `Error::BadParameter` is returned.
     pub fn nvm_set(&mut self, addr: u16, byte: u8) -> RnResult<(), E> {
-        if addr < 0x300 || addr > 0x3ff {
-            return Err(Error::BadParameter);
-        }
-
         let mut hex_addr_buf = [0; 4];
-        let addr_buf_byte_count = base16::encode_config_slice(
-            &addr.to_be_bytes(),
-            base16::EncodeLower,
-            &mut hex_addr_buf,
-        );
-        let hex_addr = from_utf8(&hex_addr_buf[..addr_buf_byte_count]).unwrap();
+        let hex_addr = protocol::encode_nvm_addr(addr, &mut hex_addr_buf)?;
 
-        let hex_byte_bytes = base16::encode_byte_l(byte);
-        let hex_byte = from_utf8(&hex_byte_bytes).unwrap();
+        let mut hex_byte_buf = [0; 2];
+        let hex_byte = protocol::encode_nvm_byte(byte, &mut hex_byte_buf);
 
-        let args = ["sys set nvm ", utils::ltrim_hex(&hex_addr), " ", &hex_byte];
+        let args = ["sys set nvm ", hex_addr, " ", hex_byte];
         self.send_raw_command_ok(&args)
     }
 
@@ -662,51 +1435,27 @@ where
     /// The address must be between 0x300 and 0x3ff, otherwise
     /// `Error::BadParameter` is returned.
     pub fn nvm_get(&mut self, addr: u16) -> RnResult<u8, E> {
-        if addr < 0x300 || addr > 0x3ff {
-            return Err(Error::BadParameter);
-        }
-
         let mut hex_addr_buf = [0; 4];
-        let addr_buf_byte_count = base16::encode_config_slice(
-            &addr.to_be_bytes(),
-            base16::EncodeLower,
-            &mut hex_addr_buf,
-        );
-        let hex_addr = from_utf8(&hex_addr_buf[..addr_buf_byte_count]).unwrap();
+        let hex_addr = protocol::encode_nvm_addr(addr, &mut hex_addr_buf)?;
 
-        let response = self.send_raw_command(&["sys get nvm ", utils::ltrim_hex(&hex_addr)])?;
-        if response.len() != 2 {
-            return Err(Error::ParsingError);
-        }
-        let mut buf = [0; 1];
-        base16::decode_slice(response, &mut buf).map_err(|_| Error::ParsingError)?;
-        Ok(buf[0])
+        let response = self.send_raw_command(&["sys get nvm ", hex_addr])?;
+        protocol::parse_nvm_byte(response)
     }
 
     /// Put the system to sleep (with millisecond precision).
     ///
     /// The module can be forced to exit from sleep by sending a break
-    /// condition followed by a 0x55 character at the new baud rate.
+    /// condition followed by a 0x55 character at the new baud rate, see
+    /// [`wake_now()`][wake_now].
     ///
     /// **Note:** This command is asynchronous, it will *not* wait for the module
     /// to wake up. You need to call [`wait_for_wakeup()`][wait_for_wakeup] to
     /// wait for the module before sending any other command.
     ///
     /// [wait_for_wakeup]: #method.wait_for_wakeup
+    /// [wake_now]: #method.wake_now
     pub fn sleep(&mut self, duration: Duration) -> RnResult<(), E> {
-        // Split duration into seconds and milliseconds
-        let secs: u64 = duration.as_secs();
-        let subsec_millis: u32 = duration.subsec_millis();
-
-        // Millis must be in the range [100, 2^32).
-        // Do this the awkward way to avoid using the u128 type that `as_millis` returns.
-        let millis: u32 = if secs == 0 && subsec_millis < 100 {
-            return Err(Error::BadParameter);
-        } else if (secs < 4_294_967) || (secs == 4_294_967 && subsec_millis <= 295) {
-            (secs * 1000) as u32 + duration.subsec_millis()
-        } else {
-            return Err(Error::BadParameter);
-        };
+        let millis = protocol::sleep_millis(duration)?;
 
         let mut buf = [0u8; 10];
         self.send_raw_command_nowait(&["sys sleep ", millis.numtoa_str(10, &mut buf)])?;
@@ -738,101 +1487,121 @@ where
             return Ok(());
         }
 
-        // Wait for "ok" response.
+        // Wait for "ok" response. The sleep duration is not known here, so
+        // wait without a timeout.
         // If any response is returned, the `sleep` flag will be cleared.
-        match self.read_line()? {
-            b"ok" => {
-                self.sleep = false;
-                Ok(())
-            }
-            _ => {
-                self.sleep = false;
-                Err(Error::ParsingError)
-            }
-        }
+        let response = self.read_line_timeout(None)?;
+        let result = protocol::parse_wakeup(response);
+        self.sleep = false;
+        result
     }
-}
 
-/// Macro to generate setters and getters for MAC parameters.
-macro_rules! hex_setter_getter {
-    (
-        $field:expr, $bytes:expr, $descr:expr,
-        $set_hex:ident, $set_slice:ident
-    ) => {
-        doc_comment! {
-            concat!(
-                "Set ",
-                $descr,
-                ".",
-                "\n\nThe parameter must be a ", stringify!($bytes), "-byte hex string, ",
-                "otherwise `Error::BadParameter` will be returned.",
-            ),
-            pub fn $set_hex(&mut self, val: &str) -> RnResult<(), E> {
-                if val.len() != $bytes * 2 {
-                    return Err(Error::BadParameter);
-                }
-                self.send_raw_command_ok(&[concat!("mac set ", $field, " "), val])
-            }
-        }
+    /// Wake up the module from [sleep mode][sleep] immediately.
+    ///
+    /// A break condition followed by 0x55 is sent, which also makes the
+    /// module detect the baud rate again. If the `sleep` flag is set, the
+    /// method waits for the "ok" response (within the command timeout) and
+    /// clears the flag. Finally, the module is brought into a known state
+    /// using [`ensure_known_state`](#method.ensure_known_state).
+    ///
+    /// This requires a serial port implementing the
+    /// [`WakeUp`](uart/trait.WakeUp.html) trait.
+    ///
+    /// **Note:** If the module responds with a response that is not the string
+    /// `"ok"`, a [`Error::ParsingError`][parsing-error] will be returned. If
+    /// no response arrives in time, `Error::Timeout` is returned. In both
+    /// cases, the `sleep` flag is still cleared and the module is brought
+    /// into a known state.
+    ///
+    /// [sleep]: #method.sleep
+    /// [parsing-error]: errors/enum.Error.html#variant.ParsingError
+    pub fn wake_now(&mut self) -> RnResult<(), E>
+    where
+        S: WakeUp<Error = E> + TryRead,
+    {
+        self.send_break(None)?;
 
-        doc_comment! {
-            concat!(
-                "Set ",
-                $descr,
-                ".",
-                "\n\nThe parameter must be a ", stringify!($bytes), "-byte ",
-                "big endian byte slice, otherwise `Error::BadParameter` will be returned.",
-            ),
-            pub fn $set_slice(&mut self, val: &[u8]) -> RnResult<(), E> {
-                if val.len() != $bytes {
-                    return Err(Error::BadParameter);
-                }
-                let mut buf = [0; $bytes * 2];
-                base16::encode_config_slice(val, base16::EncodeLower, &mut buf);
-                self.$set_hex(from_utf8(&buf)?)
-            }
-        }
-    };
-    (
-        $field:expr, $bytes:expr, $descr:expr,
-        $set_hex:ident, $set_slice:ident,
-        $get_hex:ident, $get_slice:ident,
-        $(,)?
-    ) => {
-        hex_setter_getter!($field, $bytes, $descr, $set_hex, $set_slice);
-
-        doc_comment! {
-            concat!("Get ", $descr, " as hex str."),
-            pub fn $get_hex(&mut self) -> RnResult<&str, E> {
-                self.send_raw_command_str(&[concat!("mac get ", $field)])
-            }
+        let mut result = Ok(());
+        if self.sleep {
+            let timeout = self.timeouts.map(|timeouts| timeouts.command);
+            result = match self.read_line_timeout(timeout) {
+                Ok(b"ok") => Ok(()),
+                Ok(_) => Err(Error::ParsingError),
+                Err(e) => Err(e),
+            };
+            self.sleep = false;
         }
 
-        doc_comment! {
-            concat!("Get ", $descr, " bytes."),
-            pub fn $get_slice(&mut self) -> RnResult<[u8; $bytes], E> {
-                let hex = self.$get_hex()?;
-                let mut buf = [0; $bytes];
-                base16::decode_slice(hex, &mut buf).map_err(|_| Error::ParsingError)?;
-                Ok(buf)
-            }
+        // Recover in any case, but report the error of the wakeup first
+        let recovered = self.ensure_known_state();
+        result.and(recovered)
+    }
+
+    /// Change the baud rate of the UART.
+    ///
+    /// A break condition is sent, the baud rate of the serial port is
+    /// changed and 0x55 is sent at the new baud rate, so that the module
+    /// detects it. Communication at the new baud rate is then verified with
+    /// `sys get ver`. If this fails, the previous baud rate is restored the
+    /// same way, the module is brought into a known state and the original
+    /// error is returned.
+    ///
+    /// This requires a serial port implementing the
+    /// [`BaudRateControl`](uart/trait.BaudRateControl.html) trait.
+    ///
+    /// **Note:** Without a [clock](#method.with_clock), the driver waits
+    /// forever for the response to `sys get ver`, so failures can only be
+    /// detected if the module returns an invalid response.
+    ///
+    /// The module returns to the default baud rate (57600) after a reset.
+    pub fn change_baud_rate(&mut self, baud_rate: u32) -> RnResult<(), E>
+    where
+        S: BaudRateControl<Error = E> + TryRead,
+    {
+        self.ensure_not_in_sleep_mode()?;
+        let previous = self.serial.baud_rate();
+        let result = self.send_break(Some(baud_rate)).and_then(|()| {
+            self.discard_input()?;
+            self.firmware_info().map(|_| ())
+        });
+        if result.is_err() {
+            #[cfg(feature = "logging")]
+            log::debug!("Restoring previous baud rate {}", previous);
+            // Errors while restoring are ignored, the original error is
+            // more useful to the caller.
+            let _ = self
+                .send_break(Some(previous))
+                .and_then(|()| self.ensure_known_state());
         }
-    };
+        result
+    }
 
-    // Allow trailing commas
-    ($field:expr, $bytes:expr, $descr:expr, $set_hex:ident, $set_slice:ident,) => {
-        hex_setter_getter!($field, $bytes, $descr, $set_hex, $set_slice);
-    };
-    ($field:expr, $bytes:expr, $descr:expr, $set_hex:ident, $set_slice:ident, $get_hex:ident, $get_slice:ident,) => {
-        hex_setter_getter!($field, $bytes, $descr, $set_hex, $set_slice, $get_hex, $get_slice);
-    };
+    /// Send a break condition followed by 0x55, which makes the module
+    /// detect the baud rate. If `baud_rate` is set, the baud rate of the
+    /// serial port is changed after the break condition.
+    fn send_break(&mut self, baud_rate: Option<u32>) -> RnResult<(), E>
+    where
+        S: WakeUp<Error = E>,
+    {
+        #[cfg(feature = "logging")]
+        log::debug!("Sending break condition");
+        self.serial.send_break().map_err(Error::SerialWrite)?;
+        if let Some(baud_rate) = baud_rate {
+            self.serial
+                .set_baud_rate(baud_rate)
+                .map_err(Error::SerialWrite)?;
+        }
+        self.serial.write_all(&[0x55]).map_err(Error::SerialWrite)?;
+        self.serial.flush().map_err(Error::SerialWrite)
+    }
 }
 
 /// MAC commands.
-impl<F, S, E> Driver<F, S>
+impl<F, S, C, E> Driver<F, S, C>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
     F: Frequency,
+    C: Timer<S>,
 {
     /// Save MAC configuration parameters.
     ///
@@ -902,19 +1671,85 @@ where
         set_app_key_slice,
     );
 
+    hex_setter_getter!(
+        "mcastdevaddr",
+        4,
+        "the multicast network device address",
+        set_multicast_dev_addr_hex,
+        set_multicast_dev_addr_slice,
+        get_multicast_dev_addr_hex,
+        get_multicast_dev_addr_slice,
+    );
+
+    hex_setter_getter!(
+        "mcastnwkskey",
+        16,
+        "the multicast network session key",
+        set_multicast_network_session_key_hex,
+        set_multicast_network_session_key_slice,
+    );
+
+    hex_setter_getter!(
+        "mcastappskey",
+        16,
+        "the multicast application session key",
+        set_multicast_app_session_key_hex,
+        set_multicast_app_session_key_slice,
+    );
+
+    /// Set whether multicast downlinks are received (firmware 1.0.5 and
+    /// later).
+    ///
+    /// While multicast is enabled, the multicast down frame counter is
+    /// queried when downlinks are received, to tell multicast downlinks from
+    /// unicast ones (see
+    /// [`Downlink::multicast`](struct.Downlink.html#method.multicast)). Events
+    /// that arrive in the meantime are queued.
+    pub fn set_multicast(&mut self, enabled: bool) -> RnResult<(), E> {
+        self.ensure_supported(|c| c.multicast)?;
+        self.send_raw_command_ok(&["mac set mcast ", protocol::on_off(enabled)])?;
+        self.multicast_dnctr = None;
+        if enabled {
+            self.multicast_dnctr = Some(self.get_multicast_dnctr()?);
+        }
+        // Downlinks received so far are not covered by the counter
+        self.pending_events.classify_downlinks(None);
+        Ok(())
+    }
+
+    /// Return whether multicast downlinks are received.
+    pub fn get_multicast(&mut self) -> RnResult<bool, E> {
+        let mcast = self.send_raw_command_str(&["mac get mcast"])?;
+        protocol::parse_on_off(mcast)
+    }
+
+    /// Set the multicast down frame counter.
+    pub fn set_multicast_dnctr(&mut self, dnctr: u32) -> RnResult<(), E> {
+        let mut buf = [0u8; 10];
+        self.send_raw_command_ok(&["mac set mcastdnctr ", dnctr.numtoa_str(10, &mut buf)])?;
+        if self.multicast_dnctr.is_some() {
+            self.multicast_dnctr = Some(dnctr);
+            // The change of the counter cannot be attributed anymore
+            self.pending_events.classify_downlinks(None);
+        }
+        Ok(())
+    }
+
+    /// Get the multicast down frame counter.
+    pub fn get_multicast_dnctr(&mut self) -> RnResult<u32, E> {
+        let ctr = self.send_raw_command_str(&["mac get mcastdnctr"])?;
+        protocol::parse_number(ctr)
+    }
+
     /// Set whether the ADR (adaptive data rate) mechanism is enabled.
     pub fn set_adr(&mut self, enabled: bool) -> RnResult<(), E> {
-        let state = if enabled { "on" } else { "off" };
-        self.send_raw_command_ok(&["mac set adr ", state])
+        self.send_raw_command_ok(&["mac set adr ", protocol::on_off(enabled)])
     }
 
     /// Return whether the ADR (adaptive data rate) mechanism is enabled.
     pub fn get_adr(&mut self) -> RnResult<bool, E> {
-        match self.send_raw_command_str(&["mac get adr"])? {
-            "on" => Ok(true),
-            "off" => Ok(false),
-            _ => Err(Error::ParsingError),
-        }
+        let adr = self.send_raw_command_str(&["mac get adr"])?;
+        protocol::parse_on_off(adr)
     }
 
     /// Set the up frame counter.
@@ -926,7 +1761,7 @@ where
     /// Get the up frame counter.
     pub fn get_upctr(&mut self) -> RnResult<u32, E> {
         let ctr = self.send_raw_command_str(&["mac get upctr"])?;
-        ctr.parse().map_err(|_| Error::ParsingError)
+        protocol::parse_number(ctr)
     }
 
     /// Set the down frame counter.
@@ -938,35 +1773,221 @@ where
     /// Get the down frame counter.
     pub fn get_dnctr(&mut self) -> RnResult<u32, E> {
         let ctr = self.send_raw_command_str(&["mac get dnctr"])?;
-        ctr.parse().map_err(|_| Error::ParsingError)
+        protocol::parse_number(ctr)
+    }
+
+    /// Set the output power to be used for the following transmissions.
+    ///
+    /// The available power levels depend on the frequency band, see
+    /// [`TxPower433`](enum.TxPower433.html), [`TxPower868`](enum.TxPower868.html)
+    /// and [`TxPower915`](enum.TxPower915.html).
+    pub fn set_tx_power(&mut self, power: F::TxPower) -> RnResult<(), E> {
+        self.send_raw_command_ok(&["mac set pwridx ", power.into()])
+    }
+
+    /// Return the currently configured output power.
+    pub fn get_tx_power(&mut self) -> RnResult<F::TxPower, E> {
+        let power = self.send_raw_command_str(&["mac get pwridx"])?;
+        protocol::parse_variant(power)
+    }
+
+    /// Set the number of retransmissions for confirmed uplinks.
+    pub fn set_retransmissions(&mut self, count: u8) -> RnResult<(), E> {
+        let mut buf = [0u8; 3];
+        self.send_raw_command_ok(&["mac set retx ", count.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the number of retransmissions for confirmed uplinks.
+    pub fn get_retransmissions(&mut self) -> RnResult<u8, E> {
+        let count = self.send_raw_command_str(&["mac get retx"])?;
+        protocol::parse_number(count)
+    }
+
+    /// Set the delay between the end of a transmission and the first receive
+    /// window.
+    ///
+    /// The delay is rounded down to milliseconds and must not be longer than
+    /// 65535 ms, otherwise `Error::BadParameter` is returned. The delay of the
+    /// second receive window is always one second longer.
+    pub fn set_rx1_delay(&mut self, delay: Duration) -> RnResult<(), E> {
+        let millis =
+            u16::try_from(utils::duration_to_millis(delay)).map_err(|_| Error::BadParameter)?;
+        let mut buf = [0u8; 5];
+        self.send_raw_command_ok(&["mac set rxdelay1 ", millis.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the delay between the end of a transmission and the first
+    /// receive window.
+    pub fn get_rx1_delay(&mut self) -> RnResult<Duration, E> {
+        let delay = self.send_raw_command_str(&["mac get rxdelay1"])?;
+        protocol::parse_number(delay).map(Duration::from_millis)
+    }
+
+    /// Return the delay between the end of a transmission and the second
+    /// receive window.
+    pub fn get_rx2_delay(&mut self) -> RnResult<Duration, E> {
+        let delay = self.send_raw_command_str(&["mac get rxdelay2"])?;
+        protocol::parse_number(delay).map(Duration::from_millis)
+    }
+
+    /// Set the data rate and the frequency (in Hz) of the second receive
+    /// window.
+    ///
+    /// For RN2903 modules, only the downlink data rates
+    /// ([`DataRateUsDownlink`](enum.DataRateUsDownlink.html)) are valid.
+    ///
+    /// The frequency must be within the band of the module (see
+    /// [`Frequency::RADIO_FREQUENCIES`](trait.Frequency.html#associatedconstant.RADIO_FREQUENCIES)),
+    /// otherwise `Error::BadParameter` is returned.
+    pub fn set_rx2(&mut self, data_rate: F::DownlinkDataRate, frequency: u32) -> RnResult<(), E> {
+        if !F::RADIO_FREQUENCIES.contains(&frequency) {
+            return Err(Error::BadParameter);
+        }
+        let mut buf = [0u8; 10];
+        self.send_raw_command_ok(&[
+            "mac set rx2 ",
+            data_rate.into(),
+            " ",
+            frequency.numtoa_str(10, &mut buf),
+        ])
+    }
+
+    /// Return the data rate and the frequency (in Hz) of the second receive
+    /// window.
+    pub fn get_rx2(&mut self) -> RnResult<(F::DownlinkDataRate, u32), E> {
+        let rx2 = match F::MAC_BAND {
+            Some(band) => self.send_raw_command_str(&["mac get rx2 ", band])?,
+            None => self.send_raw_command_str(&["mac get rx2"])?,
+        };
+        protocol::parse_rx2(rx2)
+    }
+
+    /// Return the configuration of both receive windows.
+    pub fn rx_windows(&mut self) -> RnResult<RxWindows<F::DownlinkDataRate>, E> {
+        let (rx2_data_rate, rx2_frequency) = self.get_rx2()?;
+        Ok(RxWindows {
+            rx1_delay: self.get_rx1_delay()?,
+            rx2_delay: self.get_rx2_delay()?,
+            rx2_data_rate,
+            rx2_frequency,
+        })
+    }
+
+    /// Set the interval for sending Link Check Requests to the network.
+    ///
+    /// A zero duration disables link checks. The interval is rounded down to
+    /// seconds and must not be longer than 65535 seconds, otherwise
+    /// `Error::BadParameter` is returned.
+    pub fn set_link_check_interval(&mut self, interval: Duration) -> RnResult<(), E> {
+        let secs = u16::try_from(interval.as_secs()).map_err(|_| Error::BadParameter)?;
+        let mut buf = [0u8; 5];
+        self.send_raw_command_ok(&["mac set linkchk ", secs.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the demodulation margin in dB, as reported in the last Link
+    /// Check Answer.
+    pub fn get_margin(&mut self) -> RnResult<u8, E> {
+        let margin = self.send_raw_command_str(&["mac get mrgn"])?;
+        protocol::parse_number(margin)
+    }
+
+    /// Return the number of gateways that received the last Link Check
+    /// Request.
+    pub fn get_gateway_count(&mut self) -> RnResult<u8, E> {
+        let gateways = self.send_raw_command_str(&["mac get gwnb"])?;
+        protocol::parse_number(gateways)
+    }
+
+    /// Return the quality of the link to the network.
+    ///
+    /// The SNR and RSSI refer to the last received packet, the margin and the
+    /// gateway count to the last Link Check Answer (see
+    /// [`set_link_check_interval`](#method.set_link_check_interval)). Call
+    /// this after a transmission to record the link quality per uplink.
+    pub fn link_quality(&mut self) -> RnResult<LinkQuality, E> {
+        let snr = self.radio().get_snr()?;
+        let rssi = match self.radio().get_rssi() {
+            Ok(rssi) => Some(rssi),
+            Err(Error::CommandFailed) | Err(Error::Unsupported) => None,
+            Err(e) => return Err(e),
+        };
+        Ok(LinkQuality {
+            snr,
+            rssi,
+            margin: self.get_margin()?,
+            gateways: self.get_gateway_count()?,
+        })
+    }
+
+    /// Set the battery level that is reported to the network in the Device
+    /// Status Answer.
+    ///
+    /// A `BatteryLevel::Level` must be between 1 and 254, otherwise
+    /// `Error::BadParameter` is returned.
+    pub fn set_battery_level(&mut self, level: BatteryLevel) -> RnResult<(), E> {
+        let level = match level {
+            BatteryLevel::External => 0,
+            BatteryLevel::Level(level @ 1..=254) => level,
+            BatteryLevel::Level(_) => return Err(Error::BadParameter),
+            BatteryLevel::Unknown => 255,
+        };
+        let mut buf = [0u8; 3];
+        self.send_raw_command_ok(&["mac set bat ", level.numtoa_str(10, &mut buf)])
+    }
+
+    /// Measure the supply voltage, map it to a battery level using the
+    /// specified discharge curve and report it to the network.
+    ///
+    /// See [`BatteryLevel::from_millivolts`](enum.BatteryLevel.html#method.from_millivolts)
+    /// for the format of the curve. Return the reported battery level.
+    pub fn set_battery_level_from_vdd(&mut self, curve: &[(u16, u8)]) -> RnResult<BatteryLevel, E> {
+        let level = BatteryLevel::from_millivolts(self.vdd()?, curve);
+        self.set_battery_level(level)?;
+        Ok(level)
+    }
+
+    /// Return the decoded MAC status.
+    ///
+    /// Check [`MacStatus::is_ready`](struct.MacStatus.html#method.is_ready)
+    /// before joining or transmitting to avoid `Busy` errors.
+    pub fn mac_status(&mut self) -> RnResult<MacStatus, E> {
+        let status = self.send_raw_command_str(&["mac get status"])?;
+        protocol::parse_mac_status(status)
     }
 
     /// Join the network.
     pub fn join(&mut self, mode: JoinMode) -> Result<(), JoinError<E>> {
-        let mode_str = match mode {
-            JoinMode::Otaa => "otaa",
-            JoinMode::Abp => "abp",
-        };
+        let mode_str = protocol::join_mode_str(mode);
 
         // First response is whether the join procedure was initialized properly.
-        match self.send_raw_command_str(&["mac join ", mode_str])? {
-            "ok" => {}
-            "invalid_param" => return Err(JoinError::BadParameter),
-            "keys_not_init" => return Err(JoinError::KeysNotInit),
-            "no_free_ch" => return Err(JoinError::NoFreeChannel),
-            "silent" => return Err(JoinError::Silent),
-            "busy" => return Err(JoinError::Busy),
-            "mac_paused" => return Err(JoinError::MacPaused),
-            "denied" => return Err(JoinError::JoinUnsuccessful),
-            _ => return Err(JoinError::UnknownResponse),
-        };
+        let response = self.send_raw_command_str(&["mac join ", mode_str])?;
+        protocol::parse_join_response(response)?;
 
         // Second response indicates whether the join procedure succeeded.
-        match self.read_line()? {
-            b"denied" => Err(JoinError::JoinUnsuccessful),
-            b"accepted" => Ok(()),
-            _ => Err(JoinError::UnknownResponse),
+        let timeout = self.timeouts.map(|timeouts| timeouts.join);
+        let result = self.read_line_timeout(timeout)?;
+        protocol::parse_join_result(result)
+    }
+
+    /// Set the LoRaWAN class of the device.
+    ///
+    /// In [Class C](enum.Class.html#variant.C) mode, downlinks can arrive at
+    /// any time. Use [`poll_event`](#method.poll_event) or
+    /// [`wait_event`](#method.wait_event) to receive them.
+    ///
+    /// Class C requires firmware 1.0.5 or newer, see
+    /// [`capabilities`](#method.capabilities).
+    pub fn set_class(&mut self, class: Class) -> RnResult<(), E> {
+        if class == Class::C {
+            self.ensure_supported(|c| c.class_c)?;
         }
+        self.send_raw_command_ok(&["mac set class ", protocol::class_str(class)])
+    }
+
+    /// Return the LoRaWAN class of the device.
+    pub fn get_class(&mut self) -> RnResult<Class, E> {
+        let class = self.send_raw_command_str(&["mac get class"])?;
+        protocol::parse_class(class)
     }
 
     /// Send a hex uplink on the specified port.
@@ -977,58 +1998,74 @@ where
         mode: ConfirmationMode,
         port: u8,
         data: &str,
-    ) -> Result<Option<Downlink>, TxError<E>> {
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
         // Validate and parse arguments
-        if data.len() % 2 != 0 {
-            return Err(TxError::BadParameter);
-        }
-        utils::validate_port(port, TxError::BadParameter)?;
-        let mode_str = match mode {
-            ConfirmationMode::Confirmed => "cnf",
-            ConfirmationMode::Unconfirmed => "uncnf",
-        };
+        let mode_str = protocol::validate_tx(mode, port, data)?;
         let mut buf = [0; 3];
         let port_str = utils::u8_to_str(port, &mut buf)?;
 
         // First response is whether the uplink transmission could be initialized.
-        match self.send_raw_command(&["mac tx ", mode_str, " ", port_str, " ", data])? {
-            b"ok" => {}
-            b"invalid_param" => return Err(TxError::BadParameter),
-            b"not_joined" => return Err(TxError::NotJoined),
-            b"no_free_ch" => return Err(TxError::NoFreeChannel),
-            b"silent" => return Err(TxError::Silent),
-            b"frame_counter_err_rejoin_needed" => return Err(TxError::FrameCounterRollover),
-            b"busy" => return Err(TxError::Busy),
-            b"mac_paused" => return Err(TxError::MacPaused),
-            b"invalid_data_len" => return Err(TxError::InvalidDataLenth),
-            _ => return Err(TxError::UnknownResponse),
-        };
+        let response = self.send_raw_command(&["mac tx ", mode_str, " ", port_str, " ", data])?;
+        protocol::parse_tx_response(response)?;
 
         // The second response could contain an error or a downlink.
-        match self.read_line()? {
-            b"mac_tx_ok" => Ok(None),
-            b"mac_err" => Err(TxError::TxUnsuccessful),
-            b"invalid_data_len" => Err(TxError::InvalidDataLenth),
-            val if val.starts_with(b"mac_rx ") => {
-                let mut parts = from_utf8(val)?.split_ascii_whitespace();
-
-                // Get port
-                let _ = parts.next().ok_or(TxError::Other(Error::ParsingError))?;
-                let port_str = parts.next().ok_or(TxError::Other(Error::ParsingError))?;
-                let port =
-                    u8::from_str(&port_str).map_err(|_| TxError::Other(Error::ParsingError))?;
-                utils::validate_port(port, TxError::Other(Error::ParsingError))?;
-
-                // Get data
-                let hexdata = parts.next().ok_or(TxError::Other(Error::ParsingError))?;
-                if hexdata.len() % 2 != 0 {
-                    return Err(TxError::Other(Error::ParsingError));
-                }
+        let timeout = self.timeouts.map(|timeouts| timeouts.tx);
+        self.read_line_timeout(timeout)?;
+        let result = self.read_buf.last_line();
+        if self.multicast_dnctr.is_some() && protocol::is_downlink(result) {
+            return self.take_tx_result_multicast(mode);
+        }
+        protocol::parse_tx_result(self.read_buf.last_line(), mode)
+    }
 
-                Ok(Some(Downlink { port, hexdata }))
-            }
-            _ => Err(TxError::UnknownResponse),
+    /// Parse the downlink in the second response of the transmit command,
+    /// and classify it together with the queued downlinks, see
+    /// [`classify_downlinks`](#method.classify_downlinks).
+    ///
+    /// The read buffer is needed to query the counter, so the downlink is
+    /// held in a copy until then.
+    fn take_tx_result_multicast(
+        &mut self,
+        mode: ConfirmationMode,
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        self.held_downlink.hold(self.read_buf.last_line());
+        let multicast = self.classify_downlinks(1)?;
+        let mut result = protocol::parse_tx_result(self.held_downlink.get(), mode)?;
+        if let Some(ref mut downlink) = result {
+            downlink.multicast = multicast;
         }
+        Ok(result)
+    }
+
+    /// Query the multicast down frame counter to tell whether the downlinks
+    /// received since the last query are multicast downlinks.
+    ///
+    /// These are the unclassified queued downlinks (including those that
+    /// arrive during the query) and `held` downlinks that are not queued.
+    /// If the counter did not change, they are all unicast downlinks. If it
+    /// changed by their number, they are all multicast downlinks. Otherwise
+    /// the change cannot be attributed and the result is unknown (`None`).
+    /// The queued downlinks are marked accordingly, and the result is
+    /// returned for the held ones.
+    fn classify_downlinks(&mut self, held: usize) -> RnResult<Option<bool>, E> {
+        let dnctr = self.get_multicast_dnctr()?;
+        let previous = self.multicast_dnctr.unwrap_or(dnctr);
+        self.multicast_dnctr = Some(dnctr);
+
+        let downlinks = held + self.pending_events.unclassified();
+        let change = dnctr.wrapping_sub(previous);
+        let multicast = if self.pending_events.has_dropped() {
+            // A dropped downlink might have changed the counter as well
+            None
+        } else if change == 0 {
+            Some(false)
+        } else if change as usize == downlinks {
+            Some(true)
+        } else {
+            None
+        };
+        self.pending_events.classify_downlinks(multicast);
+        Ok(multicast)
     }
 
     /// Send an uplink on the specified port.
@@ -1039,17 +2076,139 @@ where
         mode: ConfirmationMode,
         port: u8,
         data: &[u8],
-    ) -> Result<Option<Downlink>, TxError<E>> {
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
         let mut buf = [0; 256];
-        let bytes = base16::encode_config_slice(data, base16::EncodeLower, &mut buf);
-        self.transmit_hex(mode, port, from_utf8(&buf[0..bytes])?)
+        self.transmit_hex(mode, port, protocol::encode_tx_data(data, &mut buf)?)
+    }
+}
+
+/// Asynchronous events.
+impl<F, S, C, E> Driver<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Return the next asynchronous event, if one has been received.
+    ///
+    /// This method does not block: Only the bytes that are already available
+    /// are read from the serial port. If no complete line has been received
+    /// yet, `None` is returned and the partial line is kept for the next call.
+    ///
+    /// Events that were queued while waiting for command responses are
+    /// returned first. If the queue was full and events had to be dropped,
+    /// `Error::EventOverflow` is returned after the queued events. If a line
+    /// is received that is not an event, `Error::ParsingError` is returned.
+    pub fn poll_event(&mut self) -> RnResult<Option<Event<'_>>, E>
+    where
+        S: TryRead,
+    {
+        if self.pending_events.is_empty() {
+            while self.read_buf.find_line().is_none() {
+                if !self.try_fill_read_buf()? {
+                    return Ok(None);
+                }
+            }
+        }
+        self.take_event().map(Some)
+    }
+
+    /// Wait for the next asynchronous event and return it.
+    ///
+    /// Since events are sent by the module at arbitrary times, this method
+    /// waits without a timeout. Use [`poll_event`](#method.poll_event) to
+    /// implement custom deadlines.
+    ///
+    /// Queued events are returned first, see
+    /// [`poll_event`](#method.poll_event). If a line is received that is not
+    /// an event, `Error::ParsingError` is returned.
+    pub fn wait_event(&mut self) -> RnResult<Event<'_>, E> {
+        if self.pending_events.is_empty() {
+            self.wait_for_line(None)?;
+        }
+        self.take_event()
+    }
+
+    /// Parse the oldest queued event or, if there is none, the next buffered
+    /// line.
+    fn take_event(&mut self) -> RnResult<Event<'_>, E> {
+        if self.multicast_dnctr.is_some() {
+            return self.take_event_multicast();
+        }
+        protocol::take_event(&mut self.pending_events, &mut self.read_buf)
+    }
+
+    /// Like [`take_event`](#method.take_event), but classify unclassified
+    /// downlinks first, see
+    /// [`classify_downlinks`](#method.classify_downlinks).
+    ///
+    /// The read buffer is needed to query the counter, so a buffered line is
+    /// moved to the event queue first. Events that arrive while querying the
+    /// counter are queued behind it.
+    fn take_event_multicast(&mut self) -> RnResult<Event<'_>, E> {
+        if self.pending_events.is_empty() {
+            let line_len = self.read_buf.find_line().ok_or(Error::InvalidState)?;
+            let line = self.read_buf.take_line(line_len);
+            if !self.pending_events.queue_event(line) {
+                return Err(Error::ParsingError);
+            }
+        }
+        if self.pending_events.head_unclassified() {
+            self.classify_downlinks(0)?;
+        }
+        protocol::take_event(&mut self.pending_events, &mut self.read_buf)
+    }
+}
+
+/// MAC commands for RN2483 modules.
+impl<F, S, C, E> Driver<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Rn2483Frequency,
+    C: Timer<S>,
+{
+    /// Reset the LoRaWAN MAC to the defaults of the specified band and return
+    /// a driver for that band.
+    ///
+    /// All MAC parameters (e.g. keys, channels and data rate) are reset, so
+    /// this can also be used to reset the current band. If the command fails,
+    /// the driver is returned together with the error.
+    ///
+    /// ```no_run
+    /// # use linux_embedded_hal::Serial;
+    /// # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+    /// # let serialport = Serial(port);
+    /// use rn2xx3::Freq433;
+    ///
+    /// let rn = rn2xx3::rn2483_868(serialport);
+    /// let rn = rn.into_band::<Freq433>().expect("Could not change band");
+    /// ```
+    // The driver is returned by value on failure, just like on success.
+    #[allow(clippy::result_large_err)]
+    pub fn into_band<B: Rn2483Frequency>(
+        mut self,
+    ) -> Result<Driver<B, S, C>, TransitionError<Self, Error<E>>> {
+        match self.send_raw_command_ok(&["mac reset ", B::BAND]) {
+            Ok(()) => {
+                self.multicast_dnctr = None;
+                Ok(self.into_frequency())
+            }
+            Err(error) => Err(TransitionError { state: self, error }),
+        }
+    }
+
+    /// Return the band the LoRaWAN MAC is configured for.
+    pub fn get_band(&mut self) -> RnResult<Band, E> {
+        let band = self.send_raw_command_str(&["mac get band"])?;
+        protocol::parse_band(band)
     }
 }
 
 /// MAC commands for 433 MHz modules.
-impl<S, E> Driver<Freq433, S>
+impl<S, C, E> Driver<Freq433, S, C>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
+    C: Timer<S>,
 {
     /// Set the data rate to be used for the following transmissions.
     pub fn set_data_rate(&mut self, data_rate: DataRateEuCn) -> RnResult<(), E> {
@@ -1059,14 +2218,15 @@ where
     /// Return the currently configured data rate.
     pub fn get_data_rate(&mut self) -> RnResult<DataRateEuCn, E> {
         let dr = self.send_raw_command_str(&["mac get dr"])?;
-        DataRateEuCn::try_from(dr).map_err(|_| Error::ParsingError)
+        protocol::parse_variant(dr)
     }
 }
 
 /// MAC commands for 868 MHz modules.
-impl<S, E> Driver<Freq868, S>
+impl<S, C, E> Driver<Freq868, S, C>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
+    C: Timer<S>,
 {
     /// Set the data rate to be used for the following transmissions.
     pub fn set_data_rate(&mut self, data_rate: DataRateEuCn) -> RnResult<(), E> {
@@ -1076,14 +2236,15 @@ where
     /// Return the currently configured data rate.
     pub fn get_data_rate(&mut self) -> RnResult<DataRateEuCn, E> {
         let dr = self.send_raw_command_str(&["mac get dr"])?;
-        DataRateEuCn::try_from(dr).map_err(|_| Error::ParsingError)
+        protocol::parse_variant(dr)
     }
 }
 
 /// MAC commands for 915 MHz modules.
-impl<S, E> Driver<Freq915, S>
+impl<S, C, E> Driver<Freq915, S, C>
 where
-    S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    S: Serial<Error = E>,
+    C: Timer<S>,
 {
     /// Set the data rate to be used for the following transmissions.
     pub fn set_data_rate(&mut self, data_rate: DataRateUs) -> RnResult<(), E> {
@@ -1093,11 +2254,12 @@ where
     /// Return the currently configured data rate.
     pub fn get_data_rate(&mut self) -> RnResult<DataRateUs, E> {
         let dr = self.send_raw_command_str(&["mac get dr"])?;
-        DataRateUs::try_from(dr).map_err(|_| Error::ParsingError)
+        protocol::parse_variant(dr)
     }
 }
 
 #[cfg(test)]
+#[cfg(feature = "embedded-hal-02")]
 mod tests {
     use super::*;
 
@@ -1112,6 +2274,7 @@ mod tests {
     fn version() {
         let expectations = [
             Transaction::write_many(b"sys get ver\r\n"),
+            Transaction::flush(),
             Transaction::read_many(VERSION48.as_bytes()),
             Transaction::read_many(CRLF.as_bytes()),
         ];
@@ -1125,6 +2288,7 @@ mod tests {
     fn model_rn2483() {
         let expectations = [
             Transaction::write_many(b"sys get ver\r\n"),
+            Transaction::flush(),
             Transaction::read_many(VERSION48.as_bytes()),
             Transaction::read_many(CRLF.as_bytes()),
         ];
@@ -1138,6 +2302,7 @@ mod tests {
     fn model_rn2903() {
         let expectations = [
             Transaction::write_many(b"sys get ver\r\n"),
+            Transaction::flush(),
             Transaction::read_many(VERSION90.as_bytes()),
             Transaction::read_many(CRLF.as_bytes()),
         ];
@@ -1151,6 +2316,7 @@ mod tests {
     fn nvm_set() {
         let expectations = [
             Transaction::write_many(b"sys set nvm 3ab 2a\r\n"),
+            Transaction::flush(),
             Transaction::read_many(b"ok\r\n"),
         ];
         let mut mock = SerialMock::new(&expectations);
@@ -1163,6 +2329,7 @@ mod tests {
     fn nvm_get() {
         let expectations = [
             Transaction::write_many(b"sys get nvm 300\r\n"),
+            Transaction::flush(),
             Transaction::read_many(b"ff\r\n"),
         ];
         let mut mock = SerialMock::new(&expectations);
@@ -1194,6 +2361,7 @@ mod tests {
     fn _set_dev_addr() -> (SerialMock<u8>, Driver<Freq868, SerialMock<u8>>) {
         let expectations = [
             Transaction::write_many(b"mac set devaddr 010203ff\r\n"),
+            Transaction::flush(),
             Transaction::read_many(b"ok\r\n"),
         ];
         let mock = SerialMock::new(&expectations);
@@ -1218,6 +2386,7 @@ mod tests {
     fn _set_dev_eui() -> (SerialMock<u8>, Driver<Freq868, SerialMock<u8>>) {
         let expectations = [
             Transaction::write_many(b"mac set deveui 0004a30b001a55ed\r\n".as_ref()),
+            Transaction::flush(),
             Transaction::read_many(b"ok\r\n"),
         ];
         let mock = SerialMock::new(&expectations);
@@ -1244,6 +2413,7 @@ mod tests {
     fn _get_dev_eui() -> (SerialMock<u8>, Driver<Freq868, SerialMock<u8>>) {
         let expectations = [
             Transaction::write_many(b"mac get deveui\r\n".as_ref()),
+            Transaction::flush(),
             Transaction::read_many(b"0004a30b001a55ed\r\n"),
         ];
         let mock = SerialMock::new(&expectations);
@@ -1274,6 +2444,7 @@ mod tests {
         fn set_sf9_eucn() {
             let expectations = [
                 Transaction::write_many(b"mac set dr 3\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1286,6 +2457,7 @@ mod tests {
         fn set_sf9_us() {
             let expectations = [
                 Transaction::write_many(b"mac set dr 1\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1298,6 +2470,7 @@ mod tests {
         fn set_sf12_eucn() {
             let expectations = [
                 Transaction::write_many(b"mac set dr 0\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1310,6 +2483,7 @@ mod tests {
         fn get_sf7_us() {
             let expectations = [
                 Transaction::write_many(b"mac get dr\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"4\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1326,6 +2500,7 @@ mod tests {
         fn get_on() {
             let expectations = [
                 Transaction::write_many(b"mac get adr\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"on\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1338,6 +2513,7 @@ mod tests {
         fn get_off() {
             let expectations = [
                 Transaction::write_many(b"mac get adr\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"off\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1350,6 +2526,7 @@ mod tests {
         fn get_invalid() {
             let expectations = [
                 Transaction::write_many(b"mac get adr\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"of\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1362,6 +2539,7 @@ mod tests {
         fn set() {
             let expectations = [
                 Transaction::write_many(b"mac set adr on\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1377,14 +2555,20 @@ mod tests {
         #[test]
         fn sleep_min_max_duration() {
             // Min duration: 100ms
-            let expectations = [Transaction::write_many(b"sys sleep 100\r\n")];
+            let expectations = [
+                Transaction::write_many(b"sys sleep 100\r\n"),
+                Transaction::flush(),
+            ];
             let mut mock = SerialMock::new(&expectations);
             let mut rn = rn2483_868(mock.clone());
             assert!(rn.sleep(Duration::from_millis(100)).is_ok());
             mock.done();
 
             // Max duration: (2**32)-1 ms
-            let expectations = [Transaction::write_many(b"sys sleep 4294967295\r\n")];
+            let expectations = [
+                Transaction::write_many(b"sys sleep 4294967295\r\n"),
+                Transaction::flush(),
+            ];
             let mut mock = SerialMock::new(&expectations);
             let mut rn = rn2483_868(mock.clone());
             assert!(rn.sleep(Duration::from_millis((1 << 32) - 1)).is_ok());
@@ -1413,7 +2597,10 @@ mod tests {
         /// While the sleep mode flag is set, don't issue any serial writes.
         #[test]
         fn sleep_mode_no_write() {
-            let expectations = [Transaction::write_many(b"sys sleep 1000\r\n")];
+            let expectations = [
+                Transaction::write_many(b"sys sleep 1000\r\n"),
+                Transaction::flush(),
+            ];
             let mut mock = SerialMock::new(&expectations);
             let mut rn = rn2483_868(mock.clone());
 
@@ -1472,6 +2659,7 @@ mod tests {
         fn otaa_ok() {
             let expectations = [
                 Transaction::write_many(b"mac join otaa\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\naccepted\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1484,6 +2672,7 @@ mod tests {
         fn abp_ok() {
             let expectations = [
                 Transaction::write_many(b"mac join abp\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\naccepted\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1496,6 +2685,7 @@ mod tests {
         fn otaa_denied() {
             let expectations = [
                 Transaction::write_many(b"mac join otaa\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\ndenied\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1508,6 +2698,7 @@ mod tests {
         fn otaa_unknown_response_1() {
             let expectations = [
                 Transaction::write_many(b"mac join otaa\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"xyz\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1520,6 +2711,7 @@ mod tests {
         fn otaa_unknown_response_2() {
             let expectations = [
                 Transaction::write_many(b"mac join otaa\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\nxyz\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1532,6 +2724,7 @@ mod tests {
         fn otaa_no_free_ch() {
             let expectations = [
                 Transaction::write_many(b"mac join otaa\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"no_free_ch\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1548,6 +2741,7 @@ mod tests {
         fn transmit_hex_uncnf_no_downlink() {
             let expectations = [
                 Transaction::write_many(b"mac tx uncnf 42 23ff\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\nmac_tx_ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1563,6 +2757,7 @@ mod tests {
         fn transmit_hex_cnf_no_downlink() {
             let expectations = [
                 Transaction::write_many(b"mac tx cnf 42 23ff\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\nmac_tx_ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1578,6 +2773,7 @@ mod tests {
         fn transmit_hex_uncnf_downlink() {
             let expectations = [
                 Transaction::write_many(b"mac tx uncnf 42 23ff\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\nmac_rx 101 000102feff\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1587,6 +2783,8 @@ mod tests {
                 Ok(Some(Downlink {
                     port: 101,
                     hexdata: "000102feff",
+                    acknowledged: false,
+                    multicast: None,
                 }))
             );
             mock.done();
@@ -1596,6 +2794,7 @@ mod tests {
         fn transmit_slice_uncnf_no_downlink() {
             let expectations = [
                 Transaction::write_many(b"mac tx uncnf 42 23ff\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"ok\r\nmac_tx_ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1606,62 +2805,955 @@ mod tests {
             );
             mock.done();
         }
+
+        #[test]
+        fn transmit_hex_cnf_downlink() {
+            let expectations = [
+                Transaction::write_many(b"mac tx cnf 42 23ff\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\nmac_rx 1 00\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let downlink = rn
+                .transmit_hex(ConfirmationMode::Confirmed, 42, "23ff")
+                .unwrap()
+                .unwrap();
+            assert_eq!(downlink.port(), 1);
+            assert_eq!(downlink.hex(), "00");
+            assert!(downlink.acknowledged());
+            mock.done();
+        }
     }
 
-    mod ensure_known_state {
+    mod downlink {
         use super::*;
 
-        use std::io::ErrorKind;
+        use crate::errors::DecodeError;
+
+        fn downlink(hexdata: &str) -> Downlink<'_> {
+            Downlink {
+                port: 1,
+                hexdata,
+                acknowledged: false,
+                multicast: None,
+            }
+        }
 
         #[test]
-        fn already_clean() {
+        fn decode_into() {
+            let mut buf = [0; 8];
+            let downlink = downlink("000102feff");
+            assert_eq!(downlink.len(), 5);
+            assert_eq!(
+                downlink.decode_into(&mut buf),
+                Ok(&[0x00, 0x01, 0x02, 0xfe, 0xff][..])
+            );
+        }
+
+        #[test]
+        fn decode_into_exact_size() {
+            let mut buf = [0; 2];
+            assert_eq!(
+                downlink("23FF").decode_into(&mut buf),
+                Ok(&[0x23, 0xff][..])
+            );
+        }
+
+        #[test]
+        fn decode_into_empty() {
+            let mut buf = [];
+            let downlink = downlink("");
+            assert!(downlink.is_empty());
+            assert_eq!(downlink.decode_into(&mut buf), Ok(&[][..]));
+        }
+
+        #[test]
+        fn decode_into_buffer_too_small() {
+            let mut buf = [0; 4];
+            assert_eq!(
+                downlink("000102feff").decode_into(&mut buf),
+                Err(DecodeError::BufferTooSmall)
+            );
+        }
+
+        #[test]
+        fn decode_into_invalid_hex() {
+            let mut buf = [0; 4];
+            assert_eq!(
+                downlink("00zz").decode_into(&mut buf),
+                Err(DecodeError::InvalidHex)
+            );
+        }
+    }
+
+    mod class {
+        use super::*;
+
+        #[test]
+        fn set_class_c() {
             let expectations = [
-                // Our initial buffer is empty
-                Transaction::read_error(nb::Error::WouldBlock),
-                // Expect the 'z' write
-                Transaction::write_many(b"z\r\n"),
-                // Read returns invalid_param
-                Transaction::read_many(b"invalid_param\r\n"),
+                Transaction::write_many(b"mac set class c\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
             let mut rn = rn2483_868(mock.clone());
-            rn.ensure_known_state().unwrap();
+            assert!(rn.set_class(Class::C).is_ok());
             mock.done();
         }
 
         #[test]
-        fn non_empty_buffer() {
+        fn get_class() {
             let expectations = [
-                // Our initial buffer still contains some bytes
-                Transaction::read_many(b"sys "),
-                Transaction::read_many(b"reset"),
-                Transaction::read_error(nb::Error::WouldBlock),
-                // Expect the 'z' write
-                Transaction::write_many(b"z\r\n"),
-                // Read returns invalid_param
-                Transaction::read_many(b"invalid_param\r\n"),
+                Transaction::write_many(b"mac get class\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"A\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
             let mut rn = rn2483_868(mock.clone());
-            rn.ensure_known_state().unwrap();
+            assert_eq!(rn.get_class().unwrap(), Class::A);
             mock.done();
         }
+    }
+
+    mod band {
+        use super::*;
 
         #[test]
-        fn retry() {
+        fn into_band() {
             let expectations = [
-                // Initial buffer empty
-                Transaction::read_error(nb::Error::WouldBlock),
-                // Expect the 'z' write
-                Transaction::write_many(b"z\r\n"),
-                // Read returns unexpected data
+                Transaction::write_many(b"mac reset 433\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::write_many(b"mac get band\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"433\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let rn = rn2483_868(mock.clone());
+            let mut rn: Driver<Freq433, _> = rn.into_band().unwrap();
+            assert_eq!(rn.get_band(), Ok(Band::Mhz433));
+            mock.done();
+        }
+
+        /// If the reset fails, the driver is returned.
+        #[test]
+        fn into_band_failed() {
+            let expectations = [
+                Transaction::write_many(b"mac reset 868\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"invalid_param\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let rn = rn2483_868(mock.clone());
+            let failed = rn.into_band::<Freq868>().err().unwrap();
+            assert_eq!(failed.error, Error::CommandFailed);
+            let _rn: Driver<Freq868, _> = failed.state;
+            mock.done();
+        }
+    }
+
+    mod capabilities {
+        use super::*;
+
+        use crate::testing::version;
+
+        #[test]
+        fn firmware_info() {
+            let expectations = version(VERSION90.as_bytes());
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2903_915(mock.clone());
+            let info = rn.firmware_info().unwrap();
+            assert_eq!(info.model, Model::RN2903);
+            assert_eq!((info.major, info.minor, info.patch), (1, 0, 3));
+            assert_eq!(info.build_date, "Mar 22 2017 06:00:42");
+            mock.done();
+        }
+
+        /// Once the capabilities are known, unsupported commands are not sent.
+        #[test]
+        fn unsupported() {
+            let expectations = version(VERSION48.as_bytes());
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(!rn.capabilities().unwrap().class_c);
+            assert!(!rn.capabilities().unwrap().multicast);
+            assert_eq!(rn.set_class(Class::C), Err(Error::Unsupported));
+            assert_eq!(rn.set_multicast(true), Err(Error::Unsupported));
+            assert_eq!(rn.radio().get_rssi(), Err(Error::Unsupported));
+            mock.done();
+        }
+
+        #[test]
+        fn supported() {
+            let mut expectations = version(b"RN2483 1.0.5 Oct 31 2018 15:06:52");
+            expectations.extend(vec![
+                Transaction::write_many(b"mac set class c\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.capabilities().unwrap().class_c);
+            assert!(rn.set_class(Class::C).is_ok());
+            mock.done();
+        }
+    }
+
+    mod multicast {
+        use super::*;
+
+        fn enable() -> Vec<Transaction<u8>> {
+            vec![
+                Transaction::write_many(b"mac set mcast on\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"5\r\n"),
+            ]
+        }
+
+        #[test]
+        fn set_keys() {
+            let expectations = [
+                Transaction::write_many(b"mac set mcastdevaddr 0a0b0c0d\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::write_many(
+                    b"mac set mcastnwkskey 000102030405060708090a0b0c0d0e0f\r\n",
+                ),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_multicast_dev_addr_slice(&[10, 11, 12, 13]).is_ok());
+            let key: Vec<u8> = (0..16).collect();
+            assert!(rn.set_multicast_network_session_key_slice(&key).is_ok());
+            assert_eq!(
+                rn.set_multicast_app_session_key_hex("00"),
+                Err(Error::BadParameter)
+            );
+            mock.done();
+        }
+
+        /// Downlinks are multicast if the multicast down frame counter changed.
+        #[test]
+        fn detect_multicast() {
+            let mut expectations = enable();
+            expectations.extend(vec![
+                Transaction::read_many(b"mac_rx 1 0a\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"6\r\n"),
+                Transaction::read_many(b"mac_rx 1 0b\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"6\r\n"),
+                Transaction::read_many(b"rxstop\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.set_multicast(true).unwrap();
+            for (hex, multicast) in [("0a", Some(true)), ("0b", Some(false))] {
+                match rn.wait_event().unwrap() {
+                    Event::Downlink(downlink) => {
+                        assert_eq!(downlink.hex(), hex);
+                        assert_eq!(downlink.multicast(), multicast);
+                    }
+                    other => panic!("Unexpected event: {:?}", other),
+                }
+            }
+            assert_eq!(rn.wait_event(), Ok(Event::RxStopped));
+            mock.done();
+        }
+
+        /// Downlinks received after an uplink are detected as well.
+        #[test]
+        fn detect_multicast_tx() {
+            let mut expectations = enable();
+            expectations.extend(vec![
+                Transaction::write_many(b"mac tx uncnf 42 23\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"mac_rx 1 0a\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"6\r\n"),
+                Transaction::write_many(b"mac tx cnf 42 23\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"mac_rx 2 0b\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"6\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.set_multicast(true).unwrap();
+            let downlink = rn
+                .transmit_hex(ConfirmationMode::Unconfirmed, 42, "23")
+                .unwrap()
+                .unwrap();
+            assert_eq!(downlink.hex(), "0a");
+            assert_eq!(downlink.multicast(), Some(true));
+            let downlink = rn
+                .transmit_hex(ConfirmationMode::Confirmed, 42, "23")
+                .unwrap()
+                .unwrap();
+            assert_eq!(downlink.port(), 2);
+            assert!(downlink.acknowledged());
+            assert_eq!(downlink.multicast(), Some(false));
+            mock.done();
+        }
+
+        /// If only some of several queued downlinks changed the counter, they
+        /// cannot be told apart. Events that arrive while querying the
+        /// counter are queued.
+        #[test]
+        fn queued_downlinks_unknown() {
+            let mut expectations = enable();
+            expectations.extend(vec![
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"mac_rx 1 0a\r\n"),
+                Transaction::read_many(b"mac_rx 1 0b\r\n"),
+                Transaction::read_many(b"RN2483 1.0.5 Oct 31 2018 15:06:52\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"rxstop\r\n"),
+                Transaction::read_many(b"6\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.set_multicast(true).unwrap();
+            assert!(rn.version().is_ok());
+            for hex in ["0a", "0b"] {
+                match rn.wait_event().unwrap() {
+                    Event::Downlink(downlink) => {
+                        assert_eq!(downlink.hex(), hex);
+                        assert_eq!(downlink.multicast(), None);
+                    }
+                    other => panic!("Unexpected event: {:?}", other),
+                }
+            }
+            assert_eq!(rn.wait_event(), Ok(Event::RxStopped));
+            mock.done();
+        }
+
+        /// If every queued downlink changed the counter, they are all
+        /// multicast downlinks. The counter is only queried once.
+        #[test]
+        fn queued_downlinks_multicast() {
+            let mut expectations = enable();
+            expectations.extend(vec![
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"mac_rx 1 0a\r\n"),
+                Transaction::read_many(b"mac_rx 1 0b\r\n"),
+                Transaction::read_many(b"RN2483 1.0.5 Oct 31 2018 15:06:52\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"7\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.set_multicast(true).unwrap();
+            assert!(rn.version().is_ok());
+            for hex in ["0a", "0b"] {
+                match rn.wait_event().unwrap() {
+                    Event::Downlink(downlink) => {
+                        assert_eq!(downlink.hex(), hex);
+                        assert_eq!(downlink.multicast(), Some(true));
+                    }
+                    other => panic!("Unexpected event: {:?}", other),
+                }
+            }
+            mock.done();
+        }
+
+        /// A multicast event that arrives before the downlink of an uplink
+        /// makes both unknown.
+        #[test]
+        fn queued_downlink_before_tx_result() {
+            let mut expectations = enable();
+            expectations.extend(vec![
+                Transaction::write_many(b"mac tx uncnf 42 23\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"mac_rx 1 0a\r\n"),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"mac_rx 2 0b\r\n"),
+                Transaction::write_many(b"mac get mcastdnctr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"6\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.set_multicast(true).unwrap();
+            let downlink = rn
+                .transmit_hex(ConfirmationMode::Unconfirmed, 42, "23")
+                .unwrap()
+                .unwrap();
+            assert_eq!(downlink.hex(), "0b");
+            assert_eq!(downlink.multicast(), None);
+            match rn.wait_event().unwrap() {
+                Event::Downlink(downlink) => {
+                    assert_eq!(downlink.hex(), "0a");
+                    assert_eq!(downlink.multicast(), None);
+                }
+                other => panic!("Unexpected event: {:?}", other),
+            }
+            mock.done();
+        }
+
+        /// Without multicast, the counter is not queried.
+        #[test]
+        fn disabled() {
+            let expectations = [
+                Transaction::write_many(b"mac set mcast off\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"mac_rx 1 0a\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.set_multicast(false).unwrap();
+            match rn.wait_event().unwrap() {
+                Event::Downlink(downlink) => assert_eq!(downlink.multicast(), None),
+                other => panic!("Unexpected event: {:?}", other),
+            }
+            mock.done();
+        }
+    }
+
+    mod events {
+        use super::*;
+
+        #[test]
+        fn poll_no_event() {
+            let expectations = [Transaction::read_error(nb::Error::WouldBlock)];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.poll_event(), Ok(None));
+            mock.done();
+        }
+
+        #[test]
+        fn poll_partial_line() {
+            let expectations = [
+                Transaction::read_many(b"mac_rx 1"),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_many(b" 0a0b\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.poll_event(), Ok(None));
+            match rn.poll_event().unwrap() {
+                Some(Event::Downlink(downlink)) => {
+                    assert_eq!(downlink.port(), 1);
+                    assert_eq!(downlink.hex(), "0a0b");
+                    assert!(!downlink.acknowledged());
+                }
+                other => panic!("Unexpected event: {:?}", other),
+            }
+            mock.done();
+        }
+
+        #[test]
+        fn wait_mac_err() {
+            let expectations = [Transaction::read_many(b"mac_err\r\n")];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.wait_event(), Ok(Event::MacError));
+            mock.done();
+        }
+
+        #[test]
+        fn wait_unexpected_line() {
+            let expectations = [Transaction::read_many(b"ok\r\n")];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.wait_event(), Err(Error::ParsingError));
+            mock.done();
+        }
+
+        /// An event that arrives before a command response must not be
+        /// returned as the response, but be kept for `poll_event`.
+        #[test]
+        fn event_before_response() {
+            let expectations = [
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"mac_rx 42 ff\r\n"),
+                Transaction::read_many(VERSION48.as_bytes()),
+                Transaction::read_many(CRLF.as_bytes()),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.version().unwrap(), VERSION48);
+            match rn.poll_event().unwrap() {
+                Some(Event::Downlink(downlink)) => {
+                    assert_eq!(downlink.port(), 42);
+                    assert_eq!(downlink.hex(), "ff");
+                }
+                other => panic!("Unexpected event: {:?}", other),
+            }
+            mock.done();
+        }
+
+        /// Several events around a command response are queued in order.
+        #[test]
+        fn events_around_response() {
+            let expectations = [
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"mac_rx 1 aa\r\n"),
+                Transaction::read_many(b"mac_rx 2 bb\r\n"),
+                Transaction::read_many(VERSION48.as_bytes()),
+                Transaction::read_many(CRLF.as_bytes()),
+                Transaction::read_many(b"mac_rx 3 cc\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.version().unwrap(), VERSION48);
+            for port in 1..=3 {
+                match rn.wait_event().unwrap() {
+                    Event::Downlink(downlink) => assert_eq!(downlink.port(), port),
+                    other => panic!("Unexpected event: {:?}", other),
+                }
+            }
+            mock.done();
+        }
+
+        /// If the event queue is full, the overflow is reported after the
+        /// queued events.
+        #[test]
+        fn event_overflow() {
+            let mut expectations = vec![
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+            ];
+            for _ in 0..5 {
+                expectations.push(Transaction::read_many(b"mac_err\r\n"));
+            }
+            expectations.extend(vec![
+                Transaction::read_many(VERSION48.as_bytes()),
+                Transaction::read_many(CRLF.as_bytes()),
+                Transaction::read_error(nb::Error::WouldBlock),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.version().unwrap(), VERSION48);
+            for _ in 0..4 {
+                assert_eq!(rn.poll_event(), Ok(Some(Event::MacError)));
+            }
+            assert_eq!(rn.poll_event(), Err(Error::EventOverflow));
+            assert_eq!(rn.poll_event(), Ok(None));
+            mock.done();
+        }
+
+        /// The downlink in the second response to `mac tx` is not an
+        /// asynchronous event.
+        #[test]
+        fn transmit_downlink_is_not_stashed() {
+            let expectations = [
+                Transaction::write_many(b"mac tx uncnf 1 00\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"mac_rx 2 aa\r\n"),
+                Transaction::read_error(nb::Error::WouldBlock),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let downlink = rn
+                .transmit_hex(ConfirmationMode::Unconfirmed, 1, "00")
+                .unwrap()
+                .unwrap();
+            assert_eq!(downlink.port(), 2);
+            assert_eq!(rn.poll_event(), Ok(None));
+            mock.done();
+        }
+    }
+
+    mod tx_power {
+        use super::*;
+
+        #[test]
+        fn set_868() {
+            let expectations = [
+                Transaction::write_many(b"mac set pwridx 1\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_tx_power(TxPower868::Dbm14).is_ok());
+            mock.done();
+        }
+
+        #[test]
+        fn get_915() {
+            let expectations = [
+                Transaction::write_many(b"mac get pwridx\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"10\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2903_915(mock.clone());
+            assert_eq!(rn.get_tx_power().unwrap(), TxPower915::Dbm10);
+            mock.done();
+        }
+
+        /// Index 0 is not valid at 868 MHz.
+        #[test]
+        fn get_868_invalid() {
+            let expectations = [
+                Transaction::write_many(b"mac get pwridx\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"0\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.get_tx_power(), Err(Error::ParsingError));
+            mock.done();
+        }
+
+        #[test]
+        fn dbm() {
+            assert_eq!(TxPower433::DbmMinus5.dbm(), -5);
+            assert_eq!(TxPower868::Dbm2.dbm(), 2);
+            assert_eq!(TxPower915::Dbm20.dbm(), 20);
+        }
+    }
+
+    mod rx_windows {
+        use super::*;
+
+        #[test]
+        fn set_retransmissions() {
+            let expectations = [
+                Transaction::write_many(b"mac set retx 7\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_retransmissions(7).is_ok());
+            mock.done();
+        }
+
+        #[test]
+        fn set_rx1_delay() {
+            let expectations = [
+                Transaction::write_many(b"mac set rxdelay1 5000\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_rx1_delay(Duration::from_secs(5)).is_ok());
+            assert_eq!(
+                rn.set_rx1_delay(Duration::from_millis(65536)),
+                Err(Error::BadParameter)
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn set_rx2_868() {
+            let expectations = [
+                Transaction::write_many(b"mac set rx2 3 869525000\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_rx2(DataRateEuCn::Sf9Bw125, 869_525_000).is_ok());
+            mock.done();
+        }
+
+        #[test]
+        fn set_rx2_out_of_band() {
+            let mut mock = SerialMock::new(&[]);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.set_rx2(DataRateEuCn::Sf12Bw125, 923_300_000),
+                Err(Error::BadParameter)
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn rx_windows_868() {
+            let expectations = [
+                Transaction::write_many(b"mac get rx2 868\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"3 869525000\r\n"),
+                Transaction::write_many(b"mac get rxdelay1\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"1000\r\n"),
+                Transaction::write_many(b"mac get rxdelay2\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"2000\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.rx_windows().unwrap(),
+                RxWindows {
+                    rx1_delay: Duration::from_secs(1),
+                    rx2_delay: Duration::from_secs(2),
+                    rx2_data_rate: DataRateEuCn::Sf9Bw125,
+                    rx2_frequency: 869_525_000,
+                }
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn get_rx2_915() {
+            let expectations = [
+                Transaction::write_many(b"mac get rx2\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"8 923300000\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2903_915(mock.clone());
+            assert_eq!(
+                rn.get_rx2().unwrap(),
+                (DataRateUsDownlink::Sf12Bw500, 923_300_000)
+            );
+            mock.done();
+        }
+    }
+
+    mod battery_level {
+        use super::*;
+
+        const CURVE: [(u16, u8); 3] = [(2000, 1), (2800, 100), (3300, 254)];
+
+        #[test]
+        fn from_millivolts() {
+            let level = |mv| BatteryLevel::from_millivolts(mv, &CURVE);
+            assert_eq!(level(1800), BatteryLevel::Level(1));
+            assert_eq!(level(2000), BatteryLevel::Level(1));
+            assert_eq!(level(2400), BatteryLevel::Level(50));
+            assert_eq!(level(2800), BatteryLevel::Level(100));
+            assert_eq!(level(3050), BatteryLevel::Level(177));
+            assert_eq!(level(3600), BatteryLevel::Level(254));
+            assert_eq!(
+                BatteryLevel::from_millivolts(3000, &[]),
+                BatteryLevel::Unknown
+            );
+            assert_eq!(
+                BatteryLevel::from_millivolts(3000, &[(3000, 0)]),
+                BatteryLevel::Level(1)
+            );
+        }
+
+        #[test]
+        fn set_battery_level() {
+            let expectations = [
+                Transaction::write_many(b"mac set bat 0\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::write_many(b"mac set bat 255\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_battery_level(BatteryLevel::External).is_ok());
+            assert!(rn.set_battery_level(BatteryLevel::Unknown).is_ok());
+            assert_eq!(
+                rn.set_battery_level(BatteryLevel::Level(0)),
+                Err(Error::BadParameter)
+            );
+            assert_eq!(
+                rn.set_battery_level(BatteryLevel::Level(255)),
+                Err(Error::BadParameter)
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn set_battery_level_from_vdd() {
+            let expectations = [
+                Transaction::write_many(b"sys get vdd\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"2400\r\n"),
+                Transaction::write_many(b"mac set bat 50\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.set_battery_level_from_vdd(&CURVE).unwrap(),
+                BatteryLevel::Level(50)
+            );
+            mock.done();
+        }
+    }
+
+    mod mac_status {
+        use super::*;
+
+        #[test]
+        fn mac_status() {
+            let expectations = [
+                Transaction::write_many(b"mac get status\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"00000021\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let status = rn.mac_status().unwrap();
+            assert!(status.joined);
+            assert!(status.adr);
+            assert_eq!(status.state, MacState::Idle);
+            assert!(status.is_ready());
+            mock.done();
+        }
+    }
+
+    mod link_quality {
+        use super::*;
+
+        #[test]
+        fn set_link_check_interval() {
+            let expectations = [
+                Transaction::write_many(b"mac set linkchk 600\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(rn.set_link_check_interval(Duration::from_secs(600)).is_ok());
+            assert_eq!(
+                rn.set_link_check_interval(Duration::from_secs(65536)),
+                Err(Error::BadParameter)
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn link_quality() {
+            let expectations = [
+                Transaction::write_many(b"radio get snr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"-5\r\n"),
+                Transaction::write_many(b"radio get rssi\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"-97\r\n"),
+                Transaction::write_many(b"mac get mrgn\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"12\r\n"),
+                Transaction::write_many(b"mac get gwnb\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"3\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.link_quality(),
+                Ok(LinkQuality {
+                    snr: -5,
+                    rssi: Some(-97),
+                    margin: 12,
+                    gateways: 3,
+                })
+            );
+            mock.done();
+        }
+
+        /// Firmware versions before 1.0.5 do not support reading the RSSI.
+        #[test]
+        fn link_quality_without_rssi() {
+            let expectations = [
+                Transaction::write_many(b"radio get snr\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"7\r\n"),
+                Transaction::write_many(b"radio get rssi\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"invalid_param\r\n"),
+                Transaction::write_many(b"mac get mrgn\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"255\r\n"),
+                Transaction::write_many(b"mac get gwnb\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"0\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let quality = rn.link_quality().unwrap();
+            assert_eq!(quality.snr, 7);
+            assert_eq!(quality.rssi, None);
+            mock.done();
+        }
+    }
+
+    mod ensure_known_state {
+        use super::*;
+
+        use std::io::ErrorKind;
+
+        #[test]
+        fn already_clean() {
+            let expectations = [
+                // Our initial buffer is empty
+                Transaction::read_error(nb::Error::WouldBlock),
+                // Expect the 'z' write
+                Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
+                // Read returns invalid_param
+                Transaction::read_many(b"invalid_param\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.ensure_known_state().unwrap();
+            mock.done();
+        }
+
+        #[test]
+        fn non_empty_buffer() {
+            let expectations = [
+                // Our initial buffer still contains some bytes
+                Transaction::read_many(b"sys "),
+                Transaction::read_many(b"reset"),
+                Transaction::read_error(nb::Error::WouldBlock),
+                // Expect the 'z' write
+                Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
+                // Read returns invalid_param
+                Transaction::read_many(b"invalid_param\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            rn.ensure_known_state().unwrap();
+            mock.done();
+        }
+
+        #[test]
+        fn retry() {
+            let expectations = [
+                // Initial buffer empty
+                Transaction::read_error(nb::Error::WouldBlock),
+                // Expect the 'z' write
+                Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
+                // Read returns unexpected data
                 Transaction::read_many(b"ok\r\n"),
                 // Expect the 'z' write again (attempt 2)
                 Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
                 // Still unexpected data
                 Transaction::read_many(b"wtf\r\n"),
                 // Expect the 'z' write again (attempt 3)
                 Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
                 // Finally!
                 Transaction::read_many(b"invalid_param\r\n"),
             ];
@@ -1678,10 +3770,13 @@ mod tests {
                 Transaction::read_error(nb::Error::WouldBlock),
                 // Unexpected response for 3 consecutive attempts
                 Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"uhm\r\n"),
                 Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"lol\r\n"),
                 Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
                 Transaction::read_many(b"wat\r\n"),
             ];
             let mut mock = SerialMock::new(&expectations);
@@ -1711,4 +3806,227 @@ mod tests {
             mock.done();
         }
     }
+
+    mod timeout {
+        use super::*;
+
+        use crate::testing::TestClock;
+        use crate::timeout::Timeouts;
+
+        fn timeouts() -> Timeouts {
+            Timeouts {
+                command: Duration::from_millis(1000),
+                join: Duration::from_millis(2000),
+                tx: Duration::from_millis(3000),
+                radio: Duration::from_millis(3000),
+            }
+        }
+
+        #[test]
+        fn default_timeouts() {
+            let expectations = [];
+            let mut mock = SerialMock::new(&expectations);
+            let rn = rn2483_868(mock.clone());
+            assert_eq!(rn.timeouts(), None);
+            let rn = rn.with_clock(TestClock(0));
+            assert_eq!(rn.timeouts(), Some(Timeouts::default()));
+            mock.done();
+        }
+
+        #[test]
+        fn response_in_time() {
+            let expectations = [
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_many(VERSION48.as_bytes()),
+                Transaction::read_many(CRLF.as_bytes()),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone()).with_clock(TestClock(0));
+            rn.set_timeouts(timeouts());
+            assert_eq!(rn.version().unwrap(), VERSION48);
+            mock.done();
+        }
+
+        #[test]
+        fn command_timeout() {
+            let expectations = [
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_error(nb::Error::WouldBlock),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone()).with_clock(TestClock(0));
+            rn.set_timeouts(timeouts());
+            assert_eq!(rn.version(), Err(Error::Timeout));
+            mock.done();
+        }
+
+        #[test]
+        fn join_timeout() {
+            let expectations = [
+                Transaction::write_many(b"mac join otaa\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_error(nb::Error::WouldBlock),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone()).with_clock(TestClock(0));
+            rn.set_timeouts(timeouts());
+            assert_eq!(
+                rn.join(JoinMode::Otaa),
+                Err(JoinError::Other(Error::Timeout))
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn recover_after_timeout() {
+            let expectations = [
+                // A partial response is received
+                Transaction::write_many(b"sys get ver\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"RN24"),
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::read_error(nb::Error::WouldBlock),
+                // Recovery discards the partial response
+                Transaction::read_error(nb::Error::WouldBlock),
+                Transaction::write_many(b"z\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"invalid_param\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone()).with_clock(TestClock(0));
+            rn.set_timeouts(timeouts());
+            assert_eq!(rn.version(), Err(Error::Timeout));
+            rn.ensure_known_state().unwrap();
+            mock.done();
+        }
+    }
+}
+
+/// Tests using a serial port that implements the `embedded-io` traits
+/// natively and returns multiple bytes per read.
+#[cfg(test)]
+mod block_reads {
+    use super::*;
+
+    use crate::testing::ScriptedSerial;
+
+    #[test]
+    fn join_single_read() {
+        // Both responses arrive in a single read
+        let serial = ScriptedSerial::new(&[b"ok\r\naccepted\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        assert_eq!(rn.join(JoinMode::Otaa), Ok(()));
+        rn.free().into_inner().done(b"mac join otaa\r\n");
+    }
+
+    #[test]
+    fn transmit_hex_split_reads() {
+        let serial = ScriptedSerial::new(&[b"o", b"k\r\nmac_rx 1", b"01 000102feff\r", b"\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        assert_eq!(
+            rn.transmit_hex(ConfirmationMode::Unconfirmed, 42, "23ff"),
+            Ok(Some(Downlink {
+                port: 101,
+                hexdata: "000102feff",
+                acknowledged: false,
+                multicast: None,
+            }))
+        );
+        rn.free().into_inner().done(b"mac tx uncnf 42 23ff\r\n");
+    }
+
+    /// Downlinks with the maximum payload fit into the line buffer, both
+    /// after an uplink and when they are queued as events.
+    #[test]
+    fn max_downlink() {
+        let hex = "ab".repeat(242);
+        let line = format!("mac_rx 223 {}\r\n", hex);
+        let serial = ScriptedSerial::new(&[
+            b"ok\r\n",
+            line.as_bytes(),
+            line.as_bytes(),
+            b"RN2483 1.0.3 Mar 22 2017 06:00:42\r\n",
+        ]);
+        let mut rn = crate::io::rn2483_868(serial);
+        let mut buf = [0; 242];
+
+        let downlink = rn
+            .transmit_hex(ConfirmationMode::Unconfirmed, 42, "23")
+            .unwrap()
+            .unwrap();
+        assert_eq!(downlink.port(), 223);
+        assert_eq!(downlink.decode_into(&mut buf), Ok(&[0xab; 242][..]));
+
+        assert!(rn.version().is_ok());
+        match rn.wait_event().unwrap() {
+            Event::Downlink(downlink) => {
+                assert_eq!(downlink.hex(), hex);
+                assert_eq!(downlink.decode_into(&mut buf), Ok(&[0xab; 242][..]));
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+        rn.free()
+            .into_inner()
+            .done(b"mac tx uncnf 42 23\r\nsys get ver\r\n");
+    }
+
+    #[test]
+    fn line_too_long() {
+        let line = [b'x'; protocol::LINE_BUF_SIZE];
+        let serial = ScriptedSerial::new(&[&line]);
+        let mut rn = crate::io::rn2483_868(serial);
+        assert_eq!(rn.version(), Err(Error::ReadBufferTooSmall));
+        rn.free().into_inner().done(b"sys get ver\r\n");
+    }
+
+    #[test]
+    fn ensure_known_state_discards_buffered_bytes() {
+        let mut serial = ScriptedSerial::new(&[b"sys ", b"reset", b"invalid_param\r\n"]);
+        serial.ready = 2;
+        let mut rn = crate::io::rn2483_868(serial);
+        rn.ensure_known_state().unwrap();
+        rn.free().into_inner().done(b"z\r\n");
+    }
+
+    /// A serial port that does not implement `ReadReady`.
+    struct BlockingSerial(ScriptedSerial);
+
+    impl embedded_io::ErrorType for BlockingSerial {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    impl embedded_io::Read for BlockingSerial {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.0.read(buf)
+        }
+    }
+
+    impl embedded_io::Write for BlockingSerial {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            self.0.flush()
+        }
+    }
+
+    #[test]
+    fn without_read_ready() {
+        // Only nonblocking reads require `ReadReady`
+        let serial = BlockingSerial(ScriptedSerial::new(&[
+            b"RN2483 1.0.3 Mar 22 2017 06:00:42\r\n",
+        ]));
+        let mut rn = crate::io::rn2483_868(serial);
+        assert_eq!(rn.model(), Ok(Model::RN2483));
+        rn.free().into_inner().0.done(b"sys get ver\r\n");
+    }
 }
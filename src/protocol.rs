@@ -0,0 +1,1078 @@
+//! Command encoding and response parsing.
+//!
+//! Everything in here is independent of the serial port implementation, so
+//! that the blocking and the async driver behave exactly the same.
+
+use core::convert::TryFrom;
+use core::str::{from_utf8, FromStr};
+use core::time::Duration;
+
+use crate::any::Band;
+use crate::errors::{Error, JoinError, RadioError, RnResult, TxError};
+use crate::{
+    utils, Class, ConfirmationMode, Downlink, Event, FirmwareInfo, JoinMode, MacState, MacStatus,
+    Model,
+};
+
+pub(crate) const CR: u8 = 0x0d;
+pub(crate) const LF: u8 = 0x0a;
+
+/// Maximum application payload of a LoRaWAN downlink in bytes.
+const MAX_DOWNLINK_LEN: usize = 242;
+
+/// Size of the line buffer in bytes (including the CR/LF line termination).
+///
+/// A downlink with the maximum payload (`mac_rx <port> <hex>`) must fit into
+/// a line, so that it can be returned or queued as event.
+pub(crate) const LINE_BUF_SIZE: usize = "mac_rx 223 ".len() + 2 * MAX_DOWNLINK_LEN + 2;
+
+/// A buffer that splits incoming bytes into CR/LF terminated lines.
+///
+/// Bytes can be appended in chunks of arbitrary size. If a chunk contains
+/// more than one line, the remaining bytes are kept for the next line.
+pub(crate) struct LineBuffer {
+    buf: [u8; LINE_BUF_SIZE],
+    /// Number of valid bytes in `buf`.
+    len: usize,
+    /// Number of bytes (including CR/LF) belonging to the line that was
+    /// returned last. They will be discarded before searching the next line.
+    consumed: usize,
+}
+
+impl LineBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: [0; LINE_BUF_SIZE],
+            len: 0,
+            consumed: 0,
+        }
+    }
+
+    /// Discard all buffered bytes.
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+        self.consumed = 0;
+    }
+
+    /// Remove the previously returned line from the buffer.
+    fn discard_consumed(&mut self) {
+        if self.consumed > 0 {
+            self.buf.copy_within(self.consumed..self.len, 0);
+            self.len -= self.consumed;
+            self.consumed = 0;
+        }
+    }
+
+    /// If a complete line is buffered, return its length (without the CR/LF
+    /// line termination).
+    pub(crate) fn find_line(&mut self) -> Option<usize> {
+        self.discard_consumed();
+        self.buf[..self.len]
+            .windows(2)
+            .position(|window| window == [CR, LF])
+    }
+
+    /// Return the line with the specified length (as returned by
+    /// [`find_line`](#method.find_line)) and mark it as consumed.
+    pub(crate) fn take_line(&mut self, line_len: usize) -> &[u8] {
+        self.consumed = line_len + 2;
+        &self.buf[..line_len]
+    }
+
+    /// Return the line that was returned by the last call to
+    /// [`take_line`](#method.take_line) again.
+    ///
+    /// This must only be called before searching the next line.
+    pub(crate) fn last_line(&self) -> &[u8] {
+        &self.buf[..self.consumed.saturating_sub(2)]
+    }
+
+    /// Return all buffered bytes, including incomplete lines.
+    pub(crate) fn buffered(&mut self) -> &[u8] {
+        self.discard_consumed();
+        &self.buf[..self.len]
+    }
+
+    /// Mark the first `count` buffered bytes (as returned by
+    /// [`buffered`](#method.buffered)) as consumed.
+    pub(crate) fn consume(&mut self, count: usize) {
+        self.consumed = count;
+    }
+
+    /// Return the unused part of the buffer, to be filled from the serial
+    /// port. Afterwards, [`commit`](#method.commit) must be called with the
+    /// number of bytes that were written.
+    ///
+    /// If the buffer is full, `Error::ReadBufferTooSmall` is returned.
+    pub(crate) fn spare<S>(&mut self) -> RnResult<&mut [u8], S> {
+        self.discard_consumed();
+        if self.len >= self.buf.len() {
+            return Err(Error::ReadBufferTooSmall);
+        }
+        Ok(&mut self.buf[self.len..])
+    }
+
+    /// Mark `count` bytes of the spare buffer as filled.
+    pub(crate) fn commit(&mut self, count: usize) {
+        self.len += count;
+    }
+}
+
+/// Number of asynchronous events that can be queued while waiting for
+/// command responses.
+pub(crate) const EVENT_QUEUE_LEN: usize = 4;
+
+/// A bounded queue of asynchronous event lines that were received while
+/// waiting for command responses.
+///
+/// If the queue is full, further events are dropped. This is reported with
+/// `Error::EventOverflow` once the queued events have been taken.
+///
+/// Queued downlinks can be marked as multicast or unicast downlinks, see
+/// [`classify_downlinks`](#method.classify_downlinks).
+pub(crate) struct EventQueue {
+    lines: [[u8; LINE_BUF_SIZE]; EVENT_QUEUE_LEN],
+    lens: [usize; EVENT_QUEUE_LEN],
+    /// Whether the queued downlinks are multicast downlinks, if known.
+    multicast: [Option<bool>; EVENT_QUEUE_LEN],
+    /// Index of the oldest line.
+    head: usize,
+    /// Number of queued lines.
+    count: usize,
+    /// Number of the newest queued downlinks that have not been classified.
+    unclassified: usize,
+    /// This flag is set when an event is dropped.
+    overflow: bool,
+}
+
+impl EventQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            lines: [[0; LINE_BUF_SIZE]; EVENT_QUEUE_LEN],
+            lens: [0; EVENT_QUEUE_LEN],
+            multicast: [None; EVENT_QUEUE_LEN],
+            head: 0,
+            count: 0,
+            unclassified: 0,
+            overflow: false,
+        }
+    }
+
+    /// Return the queued line at the specified position (0 is the oldest).
+    fn line(&self, position: usize) -> &[u8] {
+        let index = (self.head + position) % EVENT_QUEUE_LEN;
+        &self.lines[index][..self.lens[index]]
+    }
+
+    /// Return the number of queued downlinks.
+    fn downlinks(&self) -> usize {
+        (0..self.count)
+            .filter(|&position| is_downlink(self.line(position)))
+            .count()
+    }
+
+    /// Return whether events have been dropped since the last
+    /// `Error::EventOverflow` was returned.
+    pub(crate) fn has_dropped(&self) -> bool {
+        self.overflow
+    }
+
+    /// Return the number of queued downlinks that have not been classified.
+    pub(crate) fn unclassified(&self) -> usize {
+        self.unclassified
+    }
+
+    /// Return whether the oldest queued line is a downlink that has not been
+    /// classified.
+    pub(crate) fn head_unclassified(&self) -> bool {
+        self.peek().map_or(false, is_downlink) && self.downlinks() == self.unclassified
+    }
+
+    /// Mark all unclassified downlinks as multicast (`Some(true)`), unicast
+    /// (`Some(false)`) or unknown (`None`) downlinks.
+    pub(crate) fn classify_downlinks(&mut self, multicast: Option<bool>) {
+        for position in (0..self.count).rev() {
+            if self.unclassified == 0 {
+                break;
+            }
+            if is_downlink(self.line(position)) {
+                self.multicast[(self.head + position) % EVENT_QUEUE_LEN] = multicast;
+                self.unclassified -= 1;
+            }
+        }
+    }
+
+    /// Return whether the oldest queued line is a multicast downlink, if
+    /// known.
+    pub(crate) fn head_multicast(&self) -> Option<bool> {
+        if self.count == 0 {
+            return None;
+        }
+        self.multicast[self.head]
+    }
+
+    /// Return whether no line is queued and no event has been dropped.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count == 0 && !self.overflow
+    }
+
+    /// If the line is an asynchronous event, queue a copy of it and return
+    /// `true`. If the queue is full, the event is dropped.
+    pub(crate) fn queue_event(&mut self, line: &[u8]) -> bool {
+        if !is_event(line) {
+            return false;
+        }
+        if self.count == EVENT_QUEUE_LEN {
+            #[cfg(feature = "logging")]
+            log::warn!("Dropping event, the event queue is full");
+            self.overflow = true;
+            return true;
+        }
+        let index = (self.head + self.count) % EVENT_QUEUE_LEN;
+        self.lines[index][..line.len()].copy_from_slice(line);
+        self.lens[index] = line.len();
+        self.multicast[index] = None;
+        self.count += 1;
+        if is_downlink(line) {
+            self.unclassified += 1;
+        }
+        true
+    }
+
+    /// Return the oldest queued line (if any) without removing it.
+    pub(crate) fn peek(&self) -> Option<&[u8]> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(&self.lines[self.head][..self.lens[self.head]])
+    }
+
+    /// Remove the oldest queued line and return it.
+    ///
+    /// If events have been dropped, `Error::EventOverflow` is returned once
+    /// after the last queued line. If the queue is empty, `None` is returned.
+    pub(crate) fn pop<S>(&mut self) -> Option<RnResult<&[u8], S>> {
+        if self.count == 0 {
+            if self.overflow {
+                self.overflow = false;
+                return Some(Err(Error::EventOverflow));
+            }
+            return None;
+        }
+        if self.head_unclassified() {
+            self.unclassified -= 1;
+        }
+        let index = self.head;
+        self.head = (self.head + 1) % EVENT_QUEUE_LEN;
+        self.count -= 1;
+        Some(Ok(&self.lines[index][..self.lens[index]]))
+    }
+}
+
+/// A copy of a single line, that is kept while the read buffer is used to
+/// send another command.
+pub(crate) struct HeldLine {
+    line: [u8; LINE_BUF_SIZE],
+    len: usize,
+}
+
+impl HeldLine {
+    pub(crate) fn new() -> Self {
+        Self {
+            line: [0; LINE_BUF_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Replace the held line with a copy of the specified line.
+    pub(crate) fn hold(&mut self, line: &[u8]) {
+        self.line[..line.len()].copy_from_slice(line);
+        self.len = line.len();
+    }
+
+    /// Return the held line.
+    pub(crate) fn get(&self) -> &[u8] {
+        &self.line[..self.len]
+    }
+}
+
+/// Macro to generate setters and getters for MAC parameters.
+///
+/// Prefix the arguments with `async` to generate the methods of the async
+/// driver.
+macro_rules! hex_setter_getter {
+    (async $($args:tt)*) => {
+        $crate::protocol::hex_setter_getter!(@methods [async await] $($args)*);
+    };
+    (
+        @methods [$($async:ident $await:ident)?]
+        $field:expr, $bytes:expr, $descr:expr,
+        $set_hex:ident, $set_slice:ident $(,)?
+    ) => {
+        ::doc_comment::doc_comment! {
+            concat!(
+                "Set ",
+                $descr,
+                ".",
+                "\n\nThe parameter must be a ", stringify!($bytes), "-byte hex string, ",
+                "otherwise `Error::BadParameter` will be returned.",
+            ),
+            pub $($async)? fn $set_hex(&mut self, val: &str) -> $crate::errors::RnResult<(), E> {
+                $crate::protocol::validate_hex_param(val, $bytes)?;
+                self.send_raw_command_ok(&[concat!("mac set ", $field, " "), val])$(.$await)?
+            }
+        }
+
+        ::doc_comment::doc_comment! {
+            concat!(
+                "Set ",
+                $descr,
+                ".",
+                "\n\nThe parameter must be a ", stringify!($bytes), "-byte ",
+                "big endian byte slice, otherwise `Error::BadParameter` will be returned.",
+            ),
+            pub $($async)? fn $set_slice(&mut self, val: &[u8]) -> $crate::errors::RnResult<(), E> {
+                let mut buf = [0; $bytes * 2];
+                let hex = $crate::protocol::encode_hex_param(val, &mut buf)?;
+                self.$set_hex(hex)$(.$await)?
+            }
+        }
+    };
+    (
+        @methods [$($async:ident $await:ident)?]
+        $field:expr, $bytes:expr, $descr:expr,
+        $set_hex:ident, $set_slice:ident,
+        $get_hex:ident, $get_slice:ident $(,)?
+    ) => {
+        $crate::protocol::hex_setter_getter!(
+            @methods [$($async $await)?] $field, $bytes, $descr, $set_hex, $set_slice
+        );
+
+        ::doc_comment::doc_comment! {
+            concat!("Get ", $descr, " as hex str."),
+            pub $($async)? fn $get_hex(&mut self) -> $crate::errors::RnResult<&str, E> {
+                self.send_raw_command_str(&[concat!("mac get ", $field)])$(.$await)?
+            }
+        }
+
+        ::doc_comment::doc_comment! {
+            concat!("Get ", $descr, " bytes."),
+            pub $($async)? fn $get_slice(&mut self) -> $crate::errors::RnResult<[u8; $bytes], E> {
+                let hex = self.$get_hex()$(.$await)??;
+                $crate::protocol::decode_hex_param(hex)
+            }
+        }
+    };
+    ($($args:tt)*) => {
+        $crate::protocol::hex_setter_getter!(@methods [] $($args)*);
+    };
+}
+
+pub(crate) use hex_setter_getter;
+
+/// Validate a hex string parameter that must encode the specified number of
+/// bytes.
+pub(crate) fn validate_hex_param<S>(val: &str, bytes: usize) -> RnResult<(), S> {
+    if val.len() != bytes * 2 {
+        return Err(Error::BadParameter);
+    }
+    Ok(())
+}
+
+/// Encode a byte slice parameter as hex string. The buffer must be exactly
+/// twice as long as the parameter, otherwise `Error::BadParameter` is
+/// returned.
+pub(crate) fn encode_hex_param<'a, S>(val: &[u8], buf: &'a mut [u8]) -> RnResult<&'a str, S> {
+    if val.len() * 2 != buf.len() {
+        return Err(Error::BadParameter);
+    }
+    base16::encode_config_slice(val, base16::EncodeLower, buf);
+    Ok(from_utf8(buf)?)
+}
+
+/// Decode a hex string parameter returned by the module.
+pub(crate) fn decode_hex_param<S, const N: usize>(hex: &str) -> RnResult<[u8; N], S> {
+    if hex.len() != N * 2 {
+        return Err(Error::ParsingError);
+    }
+    let mut buf = [0; N];
+    base16::decode_slice(hex, &mut buf).map_err(|_| Error::ParsingError)?;
+    Ok(buf)
+}
+
+/// Return the command argument for the specified boolean flag.
+pub(crate) fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Parse an "on" / "off" response.
+pub(crate) fn parse_on_off<S>(response: &str) -> RnResult<bool, S> {
+    match response {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(Error::ParsingError),
+    }
+}
+
+/// Parse a decimal number response.
+pub(crate) fn parse_number<T: FromStr, S>(response: &str) -> RnResult<T, S> {
+    response.parse().map_err(|_| Error::ParsingError)
+}
+
+/// Parse a response that is expected to be "ok".
+pub(crate) fn parse_ok<S>(response: &[u8]) -> RnResult<(), S> {
+    if response == b"ok" {
+        Ok(())
+    } else {
+        Err(Error::CommandFailed)
+    }
+}
+
+/// Parse the band the LoRaWAN MAC of an RN2483 is configured for.
+pub(crate) fn parse_band<S>(response: &str) -> RnResult<Band, S> {
+    match response {
+        "433" => Ok(Band::Mhz433),
+        "868" => Ok(Band::Mhz868),
+        _ => Err(Error::ParsingError),
+    }
+}
+
+/// Determine the module model from the version string.
+pub(crate) fn parse_model<S>(version: &str) -> RnResult<Model, S> {
+    match version.split_ascii_whitespace().next() {
+        Some("RN2483") => Ok(Model::RN2483),
+        Some("RN2903") => Ok(Model::RN2903),
+        _ => Err(Error::ParsingError),
+    }
+}
+
+/// Parse the version string (e.g. "RN2483 1.0.3 Mar 22 2017 06:00:42").
+pub(crate) fn parse_firmware_info<S>(version: &str) -> RnResult<FirmwareInfo<'_>, S> {
+    let model = parse_model(version)?;
+    let mut parts = version.splitn(3, ' ');
+    let _ = parts.next();
+    let mut numbers = parts.next().ok_or(Error::ParsingError)?.split('.');
+    let mut next_number =
+        || -> RnResult<u8, S> { parse_number(numbers.next().ok_or(Error::ParsingError)?) };
+    let (major, minor, patch) = (next_number()?, next_number()?, next_number()?);
+    if numbers.next().is_some() {
+        return Err(Error::ParsingError);
+    }
+    Ok(FirmwareInfo {
+        model,
+        major,
+        minor,
+        patch,
+        build_date: parts.next().unwrap_or("").trim(),
+    })
+}
+
+/// Return the command argument for the LoRaWAN class.
+pub(crate) fn class_str(class: Class) -> &'static str {
+    match class {
+        Class::A => "a",
+        Class::C => "c",
+    }
+}
+
+/// Parse a LoRaWAN class response.
+pub(crate) fn parse_class<S>(response: &str) -> RnResult<Class, S> {
+    match response {
+        "a" | "A" => Ok(Class::A),
+        "c" | "C" => Ok(Class::C),
+        _ => Err(Error::ParsingError),
+    }
+}
+
+/// Parse the hex encoded MAC status word.
+pub(crate) fn parse_mac_status<S>(response: &str) -> RnResult<MacStatus, S> {
+    if response.is_empty() || response.len() > 8 {
+        return Err(Error::ParsingError);
+    }
+    let status = u32::from_str_radix(response, 16).map_err(|_| Error::ParsingError)?;
+    let bit = |n: u32| status & (1 << n) != 0;
+    let state = match (status >> 1) & 0b111 {
+        0 => MacState::Idle,
+        1 => MacState::TransmissionOccurring,
+        2 => MacState::BeforeRx1,
+        3 => MacState::Rx1Open,
+        4 => MacState::BetweenRx1Rx2,
+        5 => MacState::Rx2Open,
+        6 => MacState::RetransmissionDelay,
+        _ => MacState::AbpDelay,
+    };
+    Ok(MacStatus {
+        joined: bit(0),
+        state,
+        auto_reply: bit(4),
+        adr: bit(5),
+        silent: bit(6),
+        mac_paused: bit(7),
+        rx_done: bit(8),
+        link_check: bit(9),
+        channels_updated: bit(10),
+        output_power_updated: bit(11),
+        nb_rep_updated: bit(12),
+        prescaler_updated: bit(13),
+        rx2_updated: bit(14),
+        rx_timing_updated: bit(15),
+        rejoin_needed: bit(16),
+        multicast: bit(17),
+    })
+}
+
+/// Parse a response into an enum variant (e.g. a data rate).
+pub(crate) fn parse_variant<'a, T, S>(response: &'a str) -> RnResult<T, S>
+where
+    T: TryFrom<&'a str>,
+{
+    T::try_from(response).map_err(|_| Error::ParsingError)
+}
+
+/// Parse a data rate range response (e.g. "0 5").
+pub(crate) fn parse_data_rate_range<'a, D, S>(response: &'a str) -> RnResult<(D, D), S>
+where
+    D: TryFrom<&'a str>,
+{
+    let mut parts = response.split_ascii_whitespace();
+    let min = parts.next().ok_or(Error::ParsingError)?;
+    let max = parts.next().ok_or(Error::ParsingError)?;
+    if parts.next().is_some() {
+        return Err(Error::ParsingError);
+    }
+    Ok((parse_variant(min)?, parse_variant(max)?))
+}
+
+/// Parse the parameters of the second receive window (e.g. "3 869525000").
+pub(crate) fn parse_rx2<'a, D, S>(response: &'a str) -> RnResult<(D, u32), S>
+where
+    D: TryFrom<&'a str>,
+{
+    let mut parts = response.split_ascii_whitespace();
+    let data_rate = parts.next().ok_or(Error::ParsingError)?;
+    let frequency = parts.next().ok_or(Error::ParsingError)?;
+    if parts.next().is_some() {
+        return Err(Error::ParsingError);
+    }
+    Ok((parse_variant(data_rate)?, parse_number(frequency)?))
+}
+
+/// Validate the NVM address and encode it as hex string without leading
+/// zeroes.
+///
+/// The address must be between 0x300 and 0x3ff, otherwise
+/// `Error::BadParameter` is returned.
+pub(crate) fn encode_nvm_addr<S>(addr: u16, buf: &mut [u8; 4]) -> RnResult<&str, S> {
+    if !(0x300..=0x3ff).contains(&addr) {
+        return Err(Error::BadParameter);
+    }
+    let byte_count = base16::encode_config_slice(&addr.to_be_bytes(), base16::EncodeLower, buf);
+    Ok(utils::ltrim_hex(from_utf8(&buf[..byte_count])?))
+}
+
+/// Encode an NVM byte as hex string.
+pub(crate) fn encode_nvm_byte(byte: u8, buf: &mut [u8; 2]) -> &str {
+    *buf = base16::encode_byte_l(byte);
+    from_utf8(buf).unwrap()
+}
+
+/// Parse a hex encoded NVM byte.
+pub(crate) fn parse_nvm_byte<S>(response: &[u8]) -> RnResult<u8, S> {
+    if response.len() != 2 {
+        return Err(Error::ParsingError);
+    }
+    let mut buf = [0; 1];
+    base16::decode_slice(response, &mut buf).map_err(|_| Error::ParsingError)?;
+    Ok(buf[0])
+}
+
+/// Convert a sleep duration to milliseconds.
+///
+/// Millis must be in the range [100, 2^32), otherwise `Error::BadParameter`
+/// is returned.
+pub(crate) fn sleep_millis<S>(duration: Duration) -> RnResult<u32, S> {
+    // Split duration into seconds and milliseconds
+    let secs: u64 = duration.as_secs();
+    let subsec_millis: u32 = duration.subsec_millis();
+
+    // Do this the awkward way to avoid using the u128 type that `as_millis` returns.
+    if secs == 0 && subsec_millis < 100 {
+        Err(Error::BadParameter)
+    } else if (secs < 4_294_967) || (secs == 4_294_967 && subsec_millis <= 295) {
+        Ok((secs * 1000) as u32 + subsec_millis)
+    } else {
+        Err(Error::BadParameter)
+    }
+}
+
+/// Parse the line the module sends when waking up from sleep mode.
+pub(crate) fn parse_wakeup<S>(response: &[u8]) -> RnResult<(), S> {
+    if response == b"ok" {
+        Ok(())
+    } else {
+        Err(Error::ParsingError)
+    }
+}
+
+/// Return the command argument for the join mode.
+pub(crate) fn join_mode_str(mode: JoinMode) -> &'static str {
+    match mode {
+        JoinMode::Otaa => "otaa",
+        JoinMode::Abp => "abp",
+    }
+}
+
+/// Parse the first response of the join command, indicating whether the
+/// join procedure was initialized properly.
+pub(crate) fn parse_join_response<S>(response: &str) -> Result<(), JoinError<S>> {
+    match response {
+        "ok" => Ok(()),
+        "invalid_param" => Err(JoinError::BadParameter),
+        "keys_not_init" => Err(JoinError::KeysNotInit),
+        "no_free_ch" => Err(JoinError::NoFreeChannel),
+        "silent" => Err(JoinError::Silent),
+        "busy" => Err(JoinError::Busy),
+        "mac_paused" => Err(JoinError::MacPaused),
+        "denied" => Err(JoinError::JoinUnsuccessful),
+        _ => Err(JoinError::UnknownResponse),
+    }
+}
+
+/// Parse the second response of the join command, indicating whether the
+/// join procedure succeeded.
+pub(crate) fn parse_join_result<S>(response: &[u8]) -> Result<(), JoinError<S>> {
+    match response {
+        b"denied" => Err(JoinError::JoinUnsuccessful),
+        b"accepted" => Ok(()),
+        _ => Err(JoinError::UnknownResponse),
+    }
+}
+
+/// Validate the transmit arguments and return the command argument for the
+/// confirmation mode.
+pub(crate) fn validate_tx<S>(
+    mode: ConfirmationMode,
+    port: u8,
+    data: &str,
+) -> Result<&'static str, TxError<S>> {
+    if data.len() % 2 != 0 {
+        return Err(TxError::BadParameter);
+    }
+    utils::validate_port(port, TxError::BadParameter)?;
+    Ok(match mode {
+        ConfirmationMode::Confirmed => "cnf",
+        ConfirmationMode::Unconfirmed => "uncnf",
+    })
+}
+
+/// Parse the first response of the transmit command, indicating whether the
+/// uplink transmission could be initialized.
+pub(crate) fn parse_tx_response<S>(response: &[u8]) -> Result<(), TxError<S>> {
+    match response {
+        b"ok" => Ok(()),
+        b"invalid_param" => Err(TxError::BadParameter),
+        b"not_joined" => Err(TxError::NotJoined),
+        b"no_free_ch" => Err(TxError::NoFreeChannel),
+        b"silent" => Err(TxError::Silent),
+        b"frame_counter_err_rejoin_needed" => Err(TxError::FrameCounterRollover),
+        b"busy" => Err(TxError::Busy),
+        b"mac_paused" => Err(TxError::MacPaused),
+        b"invalid_data_len" => Err(TxError::InvalidDataLenth),
+        _ => Err(TxError::UnknownResponse),
+    }
+}
+
+/// Parse the second response of the transmit command, which could contain an
+/// error or a downlink.
+///
+/// A downlink received after a confirmed uplink acknowledges that uplink.
+pub(crate) fn parse_tx_result<S>(
+    response: &[u8],
+    mode: ConfirmationMode,
+) -> Result<Option<Downlink<'_>>, TxError<S>> {
+    match response {
+        b"mac_tx_ok" => Ok(None),
+        b"mac_err" => Err(TxError::TxUnsuccessful),
+        b"invalid_data_len" => Err(TxError::InvalidDataLenth),
+        val if val.starts_with(b"mac_rx ") => Ok(Some(parse_downlink(
+            val,
+            mode == ConfirmationMode::Confirmed,
+        )?)),
+        _ => Err(TxError::UnknownResponse),
+    }
+}
+
+/// Encode the payload of an uplink as hex string.
+pub(crate) fn encode_tx_data<'a, S>(data: &[u8], buf: &'a mut [u8; 256]) -> RnResult<&'a str, S> {
+    let bytes = base16::encode_config_slice(data, base16::EncodeLower, buf);
+    Ok(from_utf8(&buf[0..bytes])?)
+}
+
+/// Validate the hex data for a radio transmission.
+///
+/// The data must contain between 1 and 255 bytes.
+pub(crate) fn validate_radio_tx<S>(data: &str) -> Result<(), RadioError<S>> {
+    if data.is_empty() || data.len() > 255 * 2 || data.len() % 2 != 0 {
+        return Err(RadioError::BadParameter);
+    }
+    Ok(())
+}
+
+/// Parse the first response of the `radio tx` and `radio rx` commands.
+pub(crate) fn parse_radio_response<S>(response: &[u8]) -> Result<(), RadioError<S>> {
+    match response {
+        b"ok" => Ok(()),
+        b"invalid_param" => Err(RadioError::BadParameter),
+        b"busy" => Err(RadioError::Busy),
+        _ => Err(RadioError::UnknownResponse),
+    }
+}
+
+/// Parse the second response of the `radio tx` command.
+pub(crate) fn parse_radio_tx_result<S>(response: &[u8]) -> Result<(), RadioError<S>> {
+    match response {
+        b"radio_tx_ok" => Ok(()),
+        b"radio_err" => Err(RadioError::TxUnsuccessful),
+        _ => Err(RadioError::UnknownResponse),
+    }
+}
+
+/// Parse the second response of the `radio rx` command, if it does not
+/// contain a packet.
+pub(crate) fn parse_radio_rx_error<S>(response: &[u8]) -> RadioError<S> {
+    match response {
+        b"radio_err" => RadioError::RxTimeout,
+        _ => RadioError::UnknownResponse,
+    }
+}
+
+/// Parse a `mac_rx <port> <data>` line.
+fn parse_downlink<S>(line: &[u8], acknowledged: bool) -> RnResult<Downlink<'_>, S> {
+    let mut parts = from_utf8(line)?.split_ascii_whitespace();
+
+    // Get port
+    let _ = parts.next().ok_or(Error::ParsingError)?;
+    let port_str = parts.next().ok_or(Error::ParsingError)?;
+    let port = u8::from_str(port_str).map_err(|_| Error::ParsingError)?;
+    utils::validate_port(port, Error::ParsingError)?;
+
+    // Get data
+    let hexdata = parts.next().ok_or(Error::ParsingError)?;
+    if hexdata.len() % 2 != 0 {
+        return Err(Error::ParsingError);
+    }
+
+    Ok(Downlink {
+        port,
+        hexdata,
+        acknowledged,
+        multicast: None,
+    })
+}
+
+/// Return whether the line is a downlink (`mac_rx <port> <data>`).
+pub(crate) fn is_downlink(line: &[u8]) -> bool {
+    line.starts_with(b"mac_rx ")
+}
+
+/// Return whether the line is an asynchronous event that is not a response
+/// to a command.
+pub(crate) fn is_event(line: &[u8]) -> bool {
+    is_downlink(line) || line == b"mac_err" || line == b"rxstop"
+}
+
+/// Parse an asynchronous event line.
+pub(crate) fn parse_event<S>(line: &[u8]) -> RnResult<Event<'_>, S> {
+    match line {
+        b"mac_err" => Ok(Event::MacError),
+        b"rxstop" => Ok(Event::RxStopped),
+        val if is_downlink(val) => Ok(Event::Downlink(parse_downlink(val, false)?)),
+        _ => Err(Error::ParsingError),
+    }
+}
+
+/// Parse the oldest queued event or, if there is none, the next line in the
+/// read buffer.
+pub(crate) fn take_event<'a, S>(
+    events: &'a mut EventQueue,
+    read_buf: &'a mut LineBuffer,
+) -> RnResult<Event<'a>, S> {
+    let multicast = events.head_multicast();
+    let line = match events.pop() {
+        Some(line) => line?,
+        None => {
+            let line_len = read_buf.find_line().ok_or(Error::InvalidState)?;
+            read_buf.take_line(line_len)
+        }
+    };
+    #[cfg(feature = "logging")]
+    log::debug!(
+        "Received event: {:?}",
+        from_utf8(line).unwrap_or("\"[invalid-utf8]\"")
+    );
+    let mut event = parse_event(line)?;
+    if let Event::Downlink(ref mut downlink) = event {
+        downlink.multicast = multicast;
+    }
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod line_buffer {
+        use super::*;
+
+        fn fill(buf: &mut LineBuffer, data: &[u8]) {
+            let spare = buf.spare::<()>().unwrap();
+            spare[..data.len()].copy_from_slice(data);
+            buf.commit(data.len());
+        }
+
+        #[test]
+        fn incomplete_line() {
+            let mut buf = LineBuffer::new();
+            fill(&mut buf, b"ok\r");
+            assert_eq!(buf.find_line(), None);
+            fill(&mut buf, b"\n");
+            let len = buf.find_line().unwrap();
+            assert_eq!(buf.take_line(len), b"ok");
+        }
+
+        #[test]
+        fn multiple_lines() {
+            let mut buf = LineBuffer::new();
+            fill(&mut buf, b"ok\r\naccepted\r\nmac_");
+            let len = buf.find_line().unwrap();
+            assert_eq!(buf.take_line(len), b"ok");
+            let len = buf.find_line().unwrap();
+            assert_eq!(buf.take_line(len), b"accepted");
+            assert_eq!(buf.last_line(), b"accepted");
+            assert_eq!(buf.find_line(), None);
+            fill(&mut buf, b"err\r\n");
+            let len = buf.find_line().unwrap();
+            assert_eq!(buf.take_line(len), b"mac_err");
+        }
+
+        #[test]
+        fn consume_partial() {
+            let mut buf = LineBuffer::new();
+            fill(&mut buf, b"radio_rx  0a");
+            assert_eq!(buf.buffered(), b"radio_rx  0a");
+            buf.consume(10);
+            assert_eq!(buf.buffered(), b"0a");
+            fill(&mut buf, b"\r\n");
+            let len = buf.find_line().unwrap();
+            assert_eq!(buf.take_line(len), b"0a");
+        }
+
+        #[test]
+        fn full() {
+            let mut buf = LineBuffer::new();
+            fill(&mut buf, &[b'a'; LINE_BUF_SIZE]);
+            assert_eq!(buf.find_line(), None);
+            assert_eq!(buf.spare::<()>().unwrap_err(), Error::ReadBufferTooSmall);
+            buf.clear();
+            assert!(buf.spare::<()>().is_ok());
+        }
+    }
+
+    mod event_queue {
+        use super::*;
+
+        #[test]
+        fn queue_and_pop() {
+            let mut queue = EventQueue::new();
+            assert!(queue.is_empty());
+            assert_eq!(queue.pop::<()>(), None);
+            assert!(!queue.queue_event(b"ok"));
+            assert!(queue.queue_event(b"mac_rx 1 ab"));
+            assert!(queue.queue_event(b"mac_err"));
+            assert!(!queue.is_empty());
+            assert_eq!(queue.peek(), Some(&b"mac_rx 1 ab"[..]));
+            assert_eq!(queue.pop::<()>(), Some(Ok(&b"mac_rx 1 ab"[..])));
+            assert_eq!(queue.pop::<()>(), Some(Ok(&b"mac_err"[..])));
+            assert_eq!(queue.pop::<()>(), None);
+            assert!(queue.is_empty());
+        }
+
+        #[test]
+        fn overflow() {
+            let mut queue = EventQueue::new();
+            for port in b'1'..=b'6' {
+                assert!(queue.queue_event(&[b"mac_rx ", &[port][..], b" ab"].concat()));
+            }
+            for port in b'1'..=b'4' {
+                let expected = [b"mac_rx ", &[port][..], b" ab"].concat();
+                assert_eq!(queue.pop::<()>(), Some(Ok(&expected[..])));
+            }
+            assert_eq!(queue.pop::<()>(), Some(Err(Error::EventOverflow)));
+            assert_eq!(queue.pop::<()>(), None);
+
+            // The queue wraps around
+            assert!(queue.queue_event(b"rxstop"));
+            assert_eq!(queue.pop::<()>(), Some(Ok(&b"rxstop"[..])));
+        }
+    }
+
+    mod parse_event {
+        use super::*;
+
+        #[test]
+        fn events() {
+            assert_eq!(parse_event::<()>(b"mac_err"), Ok(Event::MacError));
+            assert_eq!(parse_event::<()>(b"rxstop"), Ok(Event::RxStopped));
+            assert_eq!(
+                parse_event::<()>(b"mac_rx 12 0a0b"),
+                Ok(Event::Downlink(Downlink {
+                    port: 12,
+                    hexdata: "0a0b",
+                    acknowledged: false,
+                    multicast: None,
+                }))
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            assert!(!is_event(b"ok"));
+            assert_eq!(parse_event::<()>(b"ok"), Err(Error::ParsingError));
+            assert_eq!(parse_event::<()>(b"mac_rx 0 0a"), Err(Error::ParsingError));
+            assert_eq!(parse_event::<()>(b"mac_rx 1 0a0"), Err(Error::ParsingError));
+        }
+    }
+
+    mod parse_firmware_info {
+        use super::*;
+
+        #[test]
+        fn rn2483() {
+            assert_eq!(
+                parse_firmware_info::<()>("RN2483 1.0.3 Mar 22 2017 06:00:42"),
+                Ok(FirmwareInfo {
+                    model: Model::RN2483,
+                    major: 1,
+                    minor: 0,
+                    patch: 3,
+                    build_date: "Mar 22 2017 06:00:42",
+                })
+            );
+        }
+
+        #[test]
+        fn capabilities() {
+            let old = parse_firmware_info::<()>("RN2903 1.0.3 Aug 8 2017 15:11:09").unwrap();
+            assert!(!old.capabilities().class_c);
+            let new = parse_firmware_info::<()>("RN2483 1.0.5 Oct 31 2018 15:06:52").unwrap();
+            assert!(new.capabilities().class_c);
+            assert!(new.capabilities().radio_rssi);
+        }
+
+        /// Short or malformed version strings must not panic.
+        #[test]
+        fn invalid() {
+            assert_eq!(parse_model::<()>("RN24"), Err(Error::ParsingError));
+            assert_eq!(parse_model::<()>(""), Err(Error::ParsingError));
+            assert_eq!(
+                parse_firmware_info::<()>("RN2483"),
+                Err(Error::ParsingError)
+            );
+            assert_eq!(
+                parse_firmware_info::<()>("RN2483 1.0 Mar 22 2017"),
+                Err(Error::ParsingError)
+            );
+            assert_eq!(
+                parse_firmware_info::<()>("RN2483 1.0.3.1 Mar 22 2017"),
+                Err(Error::ParsingError)
+            );
+            assert_eq!(
+                parse_firmware_info::<()>("RN9999 1.0.3 Mar 22 2017"),
+                Err(Error::ParsingError)
+            );
+        }
+    }
+
+    mod parse_mac_status {
+        use super::*;
+
+        #[test]
+        fn flags() {
+            let status = parse_mac_status::<()>("00000000").unwrap();
+            assert!(!status.joined);
+            assert_eq!(status.state, MacState::Idle);
+            assert!(!status.multicast);
+
+            let status = parse_mac_status::<()>("00020483").unwrap();
+            assert!(status.joined);
+            assert_eq!(status.state, MacState::TransmissionOccurring);
+            assert!(status.mac_paused);
+            assert!(status.channels_updated);
+            assert!(status.multicast);
+            assert!(!status.rejoin_needed);
+            assert!(!status.is_ready());
+
+            let status = parse_mac_status::<()>("000e").unwrap();
+            assert_eq!(status.state, MacState::AbpDelay);
+        }
+
+        #[test]
+        fn invalid() {
+            assert_eq!(parse_mac_status::<()>(""), Err(Error::ParsingError));
+            assert_eq!(parse_mac_status::<()>("xyz"), Err(Error::ParsingError));
+            assert_eq!(
+                parse_mac_status::<()>("000000001"),
+                Err(Error::ParsingError)
+            );
+        }
+    }
+
+    mod hex_param {
+        use super::*;
+
+        #[test]
+        fn encode_decode() {
+            let mut buf = [0; 4];
+            assert_eq!(encode_hex_param::<()>(&[0x0a, 0xff], &mut buf), Ok("0aff"));
+            assert_eq!(decode_hex_param::<(), 2>("0aff"), Ok([0x0a, 0xff]));
+        }
+
+        #[test]
+        fn invalid() {
+            let mut buf = [0; 4];
+            assert_eq!(validate_hex_param::<()>("0aff", 2), Ok(()));
+            assert_eq!(validate_hex_param::<()>("0a", 2), Err(Error::BadParameter));
+            assert_eq!(
+                encode_hex_param::<()>(&[0x0a], &mut buf),
+                Err(Error::BadParameter)
+            );
+            assert_eq!(decode_hex_param::<(), 2>("0axx"), Err(Error::ParsingError));
+            assert_eq!(decode_hex_param::<(), 2>("0a"), Err(Error::ParsingError));
+        }
+    }
+
+    mod sleep_millis {
+        use super::*;
+
+        #[test]
+        fn bounds() {
+            assert_eq!(sleep_millis::<()>(Duration::from_millis(100)), Ok(100));
+            assert_eq!(
+                sleep_millis::<()>(Duration::from_millis((1 << 32) - 1)),
+                Ok(u32::MAX)
+            );
+            assert_eq!(
+                sleep_millis::<()>(Duration::from_millis(99)),
+                Err(Error::BadParameter)
+            );
+            assert_eq!(
+                sleep_millis::<()>(Duration::from_millis(1 << 32)),
+                Err(Error::BadParameter)
+            );
+        }
+    }
+}
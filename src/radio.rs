@@ -0,0 +1,1433 @@
+//! Direct access to the radio, for point-to-point communication without a
+//! LoRaWAN network.
+//!
+//! The radio can only be used while the LoRaWAN MAC is paused. By default,
+//! the [`Radio`](struct.Radio.html) handle pauses the MAC before every
+//! operation and resumes it afterwards:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! let mut rn = rn2xx3::rn2483_868(serialport);
+//! let mut radio = rn.radio();
+//!
+//! // Send a packet
+//! radio.transmit_slice(&[23, 42]).expect("Could not transmit packet");
+//!
+//! // Wait for a packet (without a receive window)
+//! let mut buf = [0; 255];
+//! let packet = radio.receive(0, &mut buf).expect("Could not receive packet");
+//! println!("Received {:?}", packet);
+//! ```
+//!
+//! To avoid pausing the MAC for every single operation, pause it once using
+//! [`pause_mac`](../struct.Driver.html#method.pause_mac). The MAC is resumed
+//! when the returned guard is dropped:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! let mut rn = rn2xx3::rn2483_868(serialport);
+//! let mut paused = rn.pause_mac().expect("Could not pause MAC");
+//! println!("MAC paused for {:?}", paused.duration());
+//!
+//! let mut radio = paused.radio();
+//! radio.transmit_slice(&[1]).expect("Could not transmit packet");
+//! radio.transmit_slice(&[2]).expect("Could not transmit packet");
+//!
+//! paused.resume().expect("Could not resume MAC");
+//! ```
+//!
+//! The modulation parameters can be configured one by one, or all at once
+//! using a [`RadioConfig`](struct.RadioConfig.html). Frequency and output
+//! power are validated against the band of the driver.
+
+use core::convert::TryFrom;
+use core::mem::ManuallyDrop;
+use core::str::from_utf8;
+use core::time::Duration;
+
+use numtoa::NumToA;
+
+use crate::errors::{Error, RadioError, RnResult};
+use crate::protocol::{self, CR, LF};
+use crate::serial::Serial;
+use crate::timeout::{NoClock, Timer};
+use crate::{utils, Driver, Frequency};
+
+/// Prefix of the `radio rx` response that contains a received packet.
+const RX_PREFIX: &[u8] = b"radio_rx ";
+
+/// The radio modulation.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Modulation {
+    /// LoRa modulation.
+    Lora,
+    /// FSK modulation.
+    Fsk,
+}
+
+impl From<Modulation> for &str {
+    fn from(modulation: Modulation) -> Self {
+        match modulation {
+            Modulation::Lora => "lora",
+            Modulation::Fsk => "fsk",
+        }
+    }
+}
+
+impl TryFrom<&str> for Modulation {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "lora" => Ok(Modulation::Lora),
+            "fsk" => Ok(Modulation::Fsk),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The LoRa spreading factor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SpreadingFactor {
+    Sf7,
+    Sf8,
+    Sf9,
+    Sf10,
+    Sf11,
+    Sf12,
+}
+
+impl From<SpreadingFactor> for &str {
+    fn from(sf: SpreadingFactor) -> Self {
+        match sf {
+            SpreadingFactor::Sf7 => "sf7",
+            SpreadingFactor::Sf8 => "sf8",
+            SpreadingFactor::Sf9 => "sf9",
+            SpreadingFactor::Sf10 => "sf10",
+            SpreadingFactor::Sf11 => "sf11",
+            SpreadingFactor::Sf12 => "sf12",
+        }
+    }
+}
+
+impl TryFrom<&str> for SpreadingFactor {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "sf7" => Ok(SpreadingFactor::Sf7),
+            "sf8" => Ok(SpreadingFactor::Sf8),
+            "sf9" => Ok(SpreadingFactor::Sf9),
+            "sf10" => Ok(SpreadingFactor::Sf10),
+            "sf11" => Ok(SpreadingFactor::Sf11),
+            "sf12" => Ok(SpreadingFactor::Sf12),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The LoRa signal bandwidth.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Bandwidth {
+    /// 125 kHz
+    Bw125,
+    /// 250 kHz
+    Bw250,
+    /// 500 kHz
+    Bw500,
+}
+
+impl From<Bandwidth> for &str {
+    fn from(bw: Bandwidth) -> Self {
+        match bw {
+            Bandwidth::Bw125 => "125",
+            Bandwidth::Bw250 => "250",
+            Bandwidth::Bw500 => "500",
+        }
+    }
+}
+
+impl TryFrom<&str> for Bandwidth {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "125" => Ok(Bandwidth::Bw125),
+            "250" => Ok(Bandwidth::Bw250),
+            "500" => Ok(Bandwidth::Bw500),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The LoRa coding rate.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CodingRate {
+    /// 4/5
+    Cr4_5,
+    /// 4/6
+    Cr4_6,
+    /// 4/7
+    Cr4_7,
+    /// 4/8
+    Cr4_8,
+}
+
+impl From<CodingRate> for &str {
+    fn from(cr: CodingRate) -> Self {
+        match cr {
+            CodingRate::Cr4_5 => "4/5",
+            CodingRate::Cr4_6 => "4/6",
+            CodingRate::Cr4_7 => "4/7",
+            CodingRate::Cr4_8 => "4/8",
+        }
+    }
+}
+
+impl TryFrom<&str> for CodingRate {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        match val {
+            "4/5" => Ok(CodingRate::Cr4_5),
+            "4/6" => Ok(CodingRate::Cr4_6),
+            "4/7" => Ok(CodingRate::Cr4_7),
+            "4/8" => Ok(CodingRate::Cr4_8),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The FSK receive (or AFC) bandwidth.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RxBandwidth {
+    /// 250 kHz
+    Khz250,
+    /// 125 kHz
+    Khz125,
+    /// 83.3 kHz
+    Khz83_3,
+    /// 62.5 kHz
+    Khz62_5,
+    /// 50 kHz
+    Khz50,
+    /// 41.7 kHz
+    Khz41_7,
+    /// 31.3 kHz
+    Khz31_3,
+    /// 25 kHz
+    Khz25,
+    /// 20.8 kHz
+    Khz20_8,
+    /// 15.6 kHz
+    Khz15_6,
+    /// 12.5 kHz
+    Khz12_5,
+    /// 10.4 kHz
+    Khz10_4,
+    /// 7.8 kHz
+    Khz7_8,
+    /// 6.3 kHz
+    Khz6_3,
+    /// 5.2 kHz
+    Khz5_2,
+    /// 3.9 kHz
+    Khz3_9,
+    /// 3.1 kHz
+    Khz3_1,
+    /// 2.6 kHz
+    Khz2_6,
+}
+
+impl From<RxBandwidth> for &str {
+    fn from(bw: RxBandwidth) -> Self {
+        match bw {
+            RxBandwidth::Khz250 => "250",
+            RxBandwidth::Khz125 => "125",
+            RxBandwidth::Khz83_3 => "83.3",
+            RxBandwidth::Khz62_5 => "62.5",
+            RxBandwidth::Khz50 => "50",
+            RxBandwidth::Khz41_7 => "41.7",
+            RxBandwidth::Khz31_3 => "31.3",
+            RxBandwidth::Khz25 => "25",
+            RxBandwidth::Khz20_8 => "20.8",
+            RxBandwidth::Khz15_6 => "15.6",
+            RxBandwidth::Khz12_5 => "12.5",
+            RxBandwidth::Khz10_4 => "10.4",
+            RxBandwidth::Khz7_8 => "7.8",
+            RxBandwidth::Khz6_3 => "6.3",
+            RxBandwidth::Khz5_2 => "5.2",
+            RxBandwidth::Khz3_9 => "3.9",
+            RxBandwidth::Khz3_1 => "3.1",
+            RxBandwidth::Khz2_6 => "2.6",
+        }
+    }
+}
+
+impl TryFrom<&str> for RxBandwidth {
+    type Error = ();
+    fn try_from(val: &str) -> Result<Self, Self::Error> {
+        // Integer values may be returned with a trailing ".0"
+        match val.strip_suffix(".0").unwrap_or(val) {
+            "250" => Ok(RxBandwidth::Khz250),
+            "125" => Ok(RxBandwidth::Khz125),
+            "83.3" => Ok(RxBandwidth::Khz83_3),
+            "62.5" => Ok(RxBandwidth::Khz62_5),
+            "50" => Ok(RxBandwidth::Khz50),
+            "41.7" => Ok(RxBandwidth::Khz41_7),
+            "31.3" => Ok(RxBandwidth::Khz31_3),
+            "25" => Ok(RxBandwidth::Khz25),
+            "20.8" => Ok(RxBandwidth::Khz20_8),
+            "15.6" => Ok(RxBandwidth::Khz15_6),
+            "12.5" => Ok(RxBandwidth::Khz12_5),
+            "10.4" => Ok(RxBandwidth::Khz10_4),
+            "7.8" => Ok(RxBandwidth::Khz7_8),
+            "6.3" => Ok(RxBandwidth::Khz6_3),
+            "5.2" => Ok(RxBandwidth::Khz5_2),
+            "3.9" => Ok(RxBandwidth::Khz3_9),
+            "3.1" => Ok(RxBandwidth::Khz3_1),
+            "2.6" => Ok(RxBandwidth::Khz2_6),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The complete radio configuration, see
+/// [`Radio::apply_config`](struct.Radio.html#method.apply_config) and
+/// [`Radio::read_config`](struct.Radio.html#method.read_config).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RadioConfig {
+    /// Frequency in Hz.
+    pub frequency: u32,
+    /// Output power in dBm.
+    pub power: i8,
+    /// Whether a CRC header is used.
+    pub crc: bool,
+    /// Preamble length in symbols.
+    pub preamble_length: u16,
+    /// Time after which a transmission or reception is aborted. Zero
+    /// disables the watchdog timer.
+    pub watchdog_timeout: Duration,
+    /// Modulation specific parameters.
+    pub modulation: ModulationConfig,
+}
+
+/// Modulation specific radio parameters.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ModulationConfig {
+    /// LoRa modulation.
+    Lora(LoraConfig),
+    /// FSK modulation.
+    Fsk(FskConfig),
+}
+
+/// Radio parameters for LoRa modulation.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct LoraConfig {
+    /// Spreading factor (SF7–SF12).
+    pub spreading_factor: SpreadingFactor,
+    /// Signal bandwidth (125, 250 or 500 kHz).
+    pub bandwidth: Bandwidth,
+    /// Coding rate (4/5–4/8).
+    pub coding_rate: CodingRate,
+    /// Whether the I and Q signals are inverted.
+    pub iq_inversion: bool,
+    /// Sync word (0x00–0xff). Use `0x34` for public LoRaWAN networks and
+    /// `0x12` for private networks.
+    pub sync_word: u8,
+}
+
+/// Radio parameters for FSK modulation.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct FskConfig {
+    /// Bit rate in bit/s (1–300000).
+    pub bitrate: u32,
+    /// Frequency deviation in Hz (0–200000).
+    pub frequency_deviation: u32,
+    /// Receive signal bandwidth.
+    pub rx_bandwidth: RxBandwidth,
+    /// Automatic frequency correction bandwidth.
+    pub afc_bandwidth: RxBandwidth,
+}
+
+/// Handle for the radio commands, see the [module documentation](index.html).
+pub struct Radio<'a, F: Frequency, S, C = NoClock> {
+    driver: &'a mut Driver<F, S, C>,
+
+    /// Whether to pause and resume the MAC around every radio operation.
+    auto_pause: bool,
+}
+
+/// Guard that keeps the LoRaWAN MAC paused, see
+/// [`pause_mac`](../struct.Driver.html#method.pause_mac).
+///
+/// The MAC is resumed when the guard is dropped. Errors while resuming are
+/// ignored in that case, use [`resume`](#method.resume) to handle them.
+pub struct MacPausedGuard<'a, F, S, C = NoClock>
+where
+    S: Serial,
+    F: Frequency,
+    C: Timer<S>,
+{
+    driver: &'a mut Driver<F, S, C>,
+
+    /// How long the MAC is paused for.
+    duration: Duration,
+}
+
+/// Radio commands.
+impl<F, S, C, E> Driver<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Return a handle for direct access to the radio.
+    pub fn radio(&mut self) -> Radio<'_, F, S, C> {
+        Radio {
+            driver: self,
+            auto_pause: true,
+        }
+    }
+
+    /// Pause the LoRaWAN MAC, to use the radio for multiple operations.
+    ///
+    /// The MAC stays paused until the returned guard is dropped or
+    /// [`resume`](radio/struct.MacPausedGuard.html#method.resume) is called.
+    /// If the MAC cannot be paused, `RadioError::MacNotPaused` is returned.
+    pub fn pause_mac(&mut self) -> Result<MacPausedGuard<'_, F, S, C>, RadioError<E>> {
+        let duration = self.send_mac_pause()?;
+        Ok(MacPausedGuard {
+            driver: self,
+            duration,
+        })
+    }
+
+    /// Send `mac pause` and return the duration the MAC is paused for.
+    pub(crate) fn send_mac_pause(&mut self) -> Result<Duration, RadioError<E>> {
+        // The response is the number of milliseconds the MAC is paused for.
+        // If it is 0, the MAC could not be paused.
+        let millis: u32 = protocol::parse_number(self.send_raw_command_str(&["mac pause"])?)?;
+        if millis == 0 {
+            return Err(RadioError::MacNotPaused);
+        }
+        Ok(Duration::from_millis(u64::from(millis)))
+    }
+}
+
+impl<F, S, C, E> MacPausedGuard<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Return how long the MAC is paused for, as reported by the module.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Return a handle for direct access to the radio.
+    ///
+    /// Since the MAC is already paused, the handle does not pause and resume
+    /// it around the radio operations.
+    pub fn radio(&mut self) -> Radio<'_, F, S, C> {
+        Radio {
+            driver: self.driver,
+            auto_pause: false,
+        }
+    }
+
+    /// Resume the LoRaWAN MAC.
+    pub fn resume(self) -> RnResult<(), E> {
+        let mut guard = ManuallyDrop::new(self);
+        guard.driver.send_raw_command_ok(&["mac resume"])
+    }
+}
+
+impl<F, S, C> Drop for MacPausedGuard<'_, F, S, C>
+where
+    S: Serial,
+    F: Frequency,
+    C: Timer<S>,
+{
+    fn drop(&mut self) {
+        let _ = self.driver.send_raw_command_ok(&["mac resume"]);
+    }
+}
+
+impl<F, S, C, E> Radio<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Set whether the MAC is paused before and resumed after every radio
+    /// operation. This is enabled by default.
+    ///
+    /// If disabled, the MAC must be paused (`mac pause`) before using the
+    /// radio, otherwise the module responds with `RadioError::Busy`.
+    pub fn set_auto_pause(&mut self, enabled: bool) {
+        self.auto_pause = enabled;
+    }
+
+    /// Run the radio operation while the MAC is paused (if auto pause is
+    /// enabled).
+    ///
+    /// The MAC is resumed even if the operation fails. In that case, the
+    /// error of the operation is returned.
+    fn with_mac_paused<T>(
+        &mut self,
+        operation: impl FnOnce(&mut Self) -> Result<T, RadioError<E>>,
+    ) -> Result<T, RadioError<E>> {
+        if !self.auto_pause {
+            return operation(self);
+        }
+
+        self.driver.send_mac_pause()?;
+        let result = operation(self);
+        let resumed = self.driver.send_raw_command_ok(&["mac resume"]);
+        let value = result?;
+        resumed?;
+        Ok(value)
+    }
+
+    /// Transmit a hex packet.
+    ///
+    /// The data must contain between 1 and 255 bytes, otherwise
+    /// `RadioError::BadParameter` is returned.
+    pub fn transmit_hex(&mut self, data: &str) -> Result<(), RadioError<E>> {
+        protocol::validate_radio_tx(data)?;
+        self.with_mac_paused(|radio| {
+            // First response is whether the transmission could be initialized.
+            let response = radio.driver.send_raw_command(&["radio tx ", data])?;
+            protocol::parse_radio_response(response)?;
+
+            // Second response indicates whether the transmission succeeded.
+            let timeout = radio.driver.timeouts.map(|timeouts| timeouts.radio);
+            let result = radio.driver.read_line_timeout(timeout)?;
+            protocol::parse_radio_tx_result(result)
+        })
+    }
+
+    /// Transmit a packet.
+    ///
+    /// The data must contain between 1 and 255 bytes, otherwise
+    /// `RadioError::BadParameter` is returned.
+    pub fn transmit_slice(&mut self, data: &[u8]) -> Result<(), RadioError<E>> {
+        if data.len() > 255 {
+            return Err(RadioError::BadParameter);
+        }
+        let mut buf = [0; 255 * 2];
+        let bytes = base16::encode_config_slice(data, base16::EncodeLower, &mut buf);
+        self.transmit_hex(from_utf8(&buf[0..bytes])?)
+    }
+
+    /// Receive a packet and decode it into the specified buffer.
+    ///
+    /// The receive window size is specified in symbols (LoRa modulation) or
+    /// milliseconds (FSK modulation). If it is 0, the radio stays in receive
+    /// mode until a packet is received or the radio watchdog timer expires.
+    ///
+    /// If no packet is received, `RadioError::RxTimeout` is returned. If the
+    /// packet does not fit into the buffer, `RadioError::BufferTooSmall` is
+    /// returned.
+    pub fn receive<'b>(
+        &mut self,
+        window: u16,
+        buf: &'b mut [u8],
+    ) -> Result<&'b [u8], RadioError<E>> {
+        self.with_mac_paused(move |radio| {
+            // First response is whether the radio entered receive mode.
+            let mut window_buf = [0; 5];
+            let window_str = window.numtoa_str(10, &mut window_buf);
+            let response = radio.driver.send_raw_command(&["radio rx ", window_str])?;
+            protocol::parse_radio_response(response)?;
+
+            // Second response contains the packet or an error.
+            let timeout = match window {
+                0 => None,
+                _ => radio.driver.timeouts.map(|timeouts| timeouts.radio),
+            };
+            radio.read_packet(timeout, buf)
+        })
+    }
+
+    /// Return the signal-to-noise ratio (in dB) of the last received packet.
+    pub fn get_snr(&mut self) -> RnResult<i8, E> {
+        let snr = self.driver.send_raw_command_str(&["radio get snr"])?;
+        protocol::parse_number(snr)
+    }
+
+    /// Return the RSSI (in dBm) of the last received packet.
+    ///
+    /// This requires firmware 1.0.5 or newer. Older firmware versions reject
+    /// the command, in which case `Error::CommandFailed` is returned. If the
+    /// [capabilities](../struct.Driver.html#method.capabilities) are known,
+    /// `Error::Unsupported` is returned without sending the command.
+    pub fn get_rssi(&mut self) -> RnResult<i16, E> {
+        self.driver.ensure_supported(|c| c.radio_rssi)?;
+        let rssi = self.driver.send_raw_command_str(&["radio get rssi"])?;
+        if rssi == "invalid_param" {
+            return Err(Error::CommandFailed);
+        }
+        protocol::parse_number(rssi)
+    }
+
+    /// Read the second response to `radio rx`.
+    ///
+    /// A received packet is decoded while it is being read, so that it may be
+    /// longer than the read buffer.
+    fn read_packet<'b>(
+        &mut self,
+        timeout: Option<Duration>,
+        buf: &'b mut [u8],
+    ) -> Result<&'b [u8], RadioError<E>> {
+        let driver = &mut *self.driver;
+        let start = driver.clock.now_ms();
+
+        // Wait for the packet prefix or for an error response
+        loop {
+            if driver.read_buf.buffered().starts_with(RX_PREFIX) {
+                driver.read_buf.consume(RX_PREFIX.len());
+                break;
+            }
+            if let Some(line_len) = driver.read_buf.find_line() {
+                let line = driver.read_buf.take_line(line_len);
+                #[cfg(feature = "logging")]
+                log::debug!(
+                    "Received response: {:?}",
+                    from_utf8(line).unwrap_or("\"[invalid-utf8]\"")
+                );
+                return Err(protocol::parse_radio_rx_error(line));
+            }
+            driver.fill_read_buf(start, timeout)?;
+        }
+
+        // Decode the hex data up to the end of the line. Errors are only
+        // returned after the entire line has been read, so that the remaining
+        // bytes are not mistaken for the next response.
+        let mut len = 0;
+        let mut result = Ok(());
+        loop {
+            let data = driver.read_buf.buffered();
+            let mut pos = 0;
+            while pos + 1 < data.len() {
+                match (data[pos], data[pos + 1]) {
+                    (CR, LF) => {
+                        driver.read_buf.consume(pos + 2);
+                        #[cfg(feature = "logging")]
+                        log::debug!("Received packet with {} bytes", len);
+                        result?;
+                        return Ok(&buf[..len]);
+                    }
+                    (b' ', _) => pos += 1,
+                    (_, CR) => {
+                        // Odd number of hex digits
+                        result = Err(RadioError::Other(Error::ParsingError));
+                        pos += 1;
+                    }
+                    (high, low) => {
+                        match (base16::decode_byte(high), base16::decode_byte(low)) {
+                            (Some(high), Some(low)) if len < buf.len() => {
+                                buf[len] = (high << 4) | low;
+                                len += 1;
+                            }
+                            (Some(_), Some(_)) => result = Err(RadioError::BufferTooSmall),
+                            _ => result = Err(RadioError::InvalidHex),
+                        }
+                        pos += 2;
+                    }
+                }
+            }
+            driver.read_buf.consume(pos);
+            driver.fill_read_buf(start, timeout)?;
+        }
+    }
+}
+
+/// Radio configuration.
+///
+/// **Note:** The MAC reconfigures the radio when transmitting LoRaWAN
+/// uplinks, so the configuration must be applied again afterwards.
+impl<F, S, C, E> Radio<'_, F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Set the modulation.
+    pub fn set_modulation(&mut self, modulation: Modulation) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set mod ", modulation.into()])
+    }
+
+    /// Return the modulation.
+    pub fn get_modulation(&mut self) -> RnResult<Modulation, E> {
+        let modulation = self.driver.send_raw_command_str(&["radio get mod"])?;
+        protocol::parse_variant(modulation)
+    }
+
+    /// Set the frequency in Hz.
+    ///
+    /// The frequency must be within the band of the module (see
+    /// [`Frequency::RADIO_FREQUENCIES`](../trait.Frequency.html#associatedconstant.RADIO_FREQUENCIES)),
+    /// otherwise `Error::BadParameter` is returned.
+    pub fn set_frequency(&mut self, frequency: u32) -> RnResult<(), E> {
+        if !F::RADIO_FREQUENCIES.contains(&frequency) {
+            return Err(Error::BadParameter);
+        }
+        let mut buf = [0; 10];
+        self.driver
+            .send_raw_command_ok(&["radio set freq ", frequency.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the frequency in Hz.
+    pub fn get_frequency(&mut self) -> RnResult<u32, E> {
+        let frequency = self.driver.send_raw_command_str(&["radio get freq"])?;
+        protocol::parse_number(frequency)
+    }
+
+    /// Set the output power in dBm.
+    ///
+    /// The power must be supported by the module (see
+    /// [`Frequency::RADIO_POWER`](../trait.Frequency.html#associatedconstant.RADIO_POWER)),
+    /// otherwise `Error::BadParameter` is returned.
+    pub fn set_power(&mut self, power: i8) -> RnResult<(), E> {
+        if !F::RADIO_POWER.contains(&power) {
+            return Err(Error::BadParameter);
+        }
+        let mut buf = [0; 4];
+        self.driver
+            .send_raw_command_ok(&["radio set pwr ", power.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the output power in dBm.
+    pub fn get_power(&mut self) -> RnResult<i8, E> {
+        let power = self.driver.send_raw_command_str(&["radio get pwr"])?;
+        protocol::parse_number(power)
+    }
+
+    /// Set the LoRa spreading factor.
+    pub fn set_spreading_factor(&mut self, sf: SpreadingFactor) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set sf ", sf.into()])
+    }
+
+    /// Return the LoRa spreading factor.
+    pub fn get_spreading_factor(&mut self) -> RnResult<SpreadingFactor, E> {
+        let sf = self.driver.send_raw_command_str(&["radio get sf"])?;
+        protocol::parse_variant(sf)
+    }
+
+    /// Set the LoRa signal bandwidth.
+    pub fn set_bandwidth(&mut self, bw: Bandwidth) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set bw ", bw.into()])
+    }
+
+    /// Return the LoRa signal bandwidth.
+    pub fn get_bandwidth(&mut self) -> RnResult<Bandwidth, E> {
+        let bw = self.driver.send_raw_command_str(&["radio get bw"])?;
+        protocol::parse_variant(bw)
+    }
+
+    /// Set the LoRa coding rate.
+    pub fn set_coding_rate(&mut self, cr: CodingRate) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set cr ", cr.into()])
+    }
+
+    /// Return the LoRa coding rate.
+    pub fn get_coding_rate(&mut self) -> RnResult<CodingRate, E> {
+        let cr = self.driver.send_raw_command_str(&["radio get cr"])?;
+        protocol::parse_variant(cr)
+    }
+
+    /// Set whether a CRC header is used.
+    pub fn set_crc(&mut self, enabled: bool) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set crc ", protocol::on_off(enabled)])
+    }
+
+    /// Return whether a CRC header is used.
+    pub fn get_crc(&mut self) -> RnResult<bool, E> {
+        let crc = self.driver.send_raw_command_str(&["radio get crc"])?;
+        protocol::parse_on_off(crc)
+    }
+
+    /// Set whether the I and Q signals are inverted (LoRa only).
+    pub fn set_iq_inversion(&mut self, enabled: bool) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set iqi ", protocol::on_off(enabled)])
+    }
+
+    /// Return whether the I and Q signals are inverted (LoRa only).
+    pub fn get_iq_inversion(&mut self) -> RnResult<bool, E> {
+        let iqi = self.driver.send_raw_command_str(&["radio get iqi"])?;
+        protocol::parse_on_off(iqi)
+    }
+
+    /// Set the preamble length in symbols.
+    pub fn set_preamble_length(&mut self, length: u16) -> RnResult<(), E> {
+        let mut buf = [0; 5];
+        self.driver
+            .send_raw_command_ok(&["radio set prlen ", length.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the preamble length in symbols.
+    pub fn get_preamble_length(&mut self) -> RnResult<u16, E> {
+        let length = self.driver.send_raw_command_str(&["radio get prlen"])?;
+        protocol::parse_number(length)
+    }
+
+    /// Set the sync word.
+    ///
+    /// For LoRa modulation, the sync word is a single byte. For FSK
+    /// modulation, it may contain up to 8 bytes. Otherwise,
+    /// `Error::BadParameter` is returned.
+    pub fn set_sync_word(&mut self, sync: &[u8]) -> RnResult<(), E> {
+        if sync.is_empty() || sync.len() > 8 {
+            return Err(Error::BadParameter);
+        }
+        let mut buf = [0; 16];
+        let bytes = base16::encode_config_slice(sync, base16::EncodeLower, &mut buf);
+        self.driver
+            .send_raw_command_ok(&["radio set sync ", from_utf8(&buf[..bytes])?])
+    }
+
+    /// Return the sync word as hex string.
+    pub fn get_sync_word_hex(&mut self) -> RnResult<&str, E> {
+        self.driver.send_raw_command_str(&["radio get sync"])
+    }
+
+    /// Set the watchdog timeout, after which a transmission or reception is
+    /// aborted. A zero duration disables the watchdog timer.
+    ///
+    /// The timeout must be less than 2^32 milliseconds, otherwise
+    /// `Error::BadParameter` is returned.
+    pub fn set_watchdog_timeout(&mut self, timeout: Duration) -> RnResult<(), E> {
+        let millis =
+            u32::try_from(utils::duration_to_millis(timeout)).map_err(|_| Error::BadParameter)?;
+        let mut buf = [0; 10];
+        self.driver
+            .send_raw_command_ok(&["radio set wdt ", millis.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the watchdog timeout.
+    pub fn get_watchdog_timeout(&mut self) -> RnResult<Duration, E> {
+        let wdt = self.driver.send_raw_command_str(&["radio get wdt"])?;
+        protocol::parse_number(wdt).map(Duration::from_millis)
+    }
+
+    /// Set the FSK bit rate in bit/s.
+    ///
+    /// The bit rate must be between 1 and 300000, otherwise
+    /// `Error::BadParameter` is returned.
+    pub fn set_bitrate(&mut self, bitrate: u32) -> RnResult<(), E> {
+        if !(1..=300_000).contains(&bitrate) {
+            return Err(Error::BadParameter);
+        }
+        let mut buf = [0; 10];
+        self.driver
+            .send_raw_command_ok(&["radio set bitrate ", bitrate.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the FSK bit rate in bit/s.
+    pub fn get_bitrate(&mut self) -> RnResult<u32, E> {
+        let bitrate = self.driver.send_raw_command_str(&["radio get bitrate"])?;
+        protocol::parse_number(bitrate)
+    }
+
+    /// Set the FSK frequency deviation in Hz.
+    ///
+    /// The deviation must be between 0 and 200000, otherwise
+    /// `Error::BadParameter` is returned.
+    pub fn set_frequency_deviation(&mut self, deviation: u32) -> RnResult<(), E> {
+        if deviation > 200_000 {
+            return Err(Error::BadParameter);
+        }
+        let mut buf = [0; 10];
+        self.driver
+            .send_raw_command_ok(&["radio set fdev ", deviation.numtoa_str(10, &mut buf)])
+    }
+
+    /// Return the FSK frequency deviation in Hz.
+    pub fn get_frequency_deviation(&mut self) -> RnResult<u32, E> {
+        let deviation = self.driver.send_raw_command_str(&["radio get fdev"])?;
+        protocol::parse_number(deviation)
+    }
+
+    /// Set the FSK receive bandwidth.
+    pub fn set_rx_bandwidth(&mut self, bw: RxBandwidth) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set rxbw ", bw.into()])
+    }
+
+    /// Return the FSK receive bandwidth.
+    pub fn get_rx_bandwidth(&mut self) -> RnResult<RxBandwidth, E> {
+        let bw = self.driver.send_raw_command_str(&["radio get rxbw"])?;
+        protocol::parse_variant(bw)
+    }
+
+    /// Set the FSK automatic frequency correction bandwidth.
+    pub fn set_afc_bandwidth(&mut self, bw: RxBandwidth) -> RnResult<(), E> {
+        self.driver
+            .send_raw_command_ok(&["radio set afcbw ", bw.into()])
+    }
+
+    /// Return the FSK automatic frequency correction bandwidth.
+    pub fn get_afc_bandwidth(&mut self) -> RnResult<RxBandwidth, E> {
+        let bw = self.driver.send_raw_command_str(&["radio get afcbw"])?;
+        protocol::parse_variant(bw)
+    }
+
+    /// Apply the complete radio configuration.
+    ///
+    /// The modulation is set first, followed by the common and the modulation
+    /// specific parameters. If a parameter is rejected, the remaining
+    /// parameters are not applied.
+    pub fn apply_config(&mut self, config: &RadioConfig) -> RnResult<(), E> {
+        match config.modulation {
+            ModulationConfig::Lora(lora) => {
+                self.set_modulation(Modulation::Lora)?;
+                self.apply_common_config(config)?;
+                self.set_spreading_factor(lora.spreading_factor)?;
+                self.set_bandwidth(lora.bandwidth)?;
+                self.set_coding_rate(lora.coding_rate)?;
+                self.set_iq_inversion(lora.iq_inversion)?;
+                self.set_sync_word(&[lora.sync_word])
+            }
+            ModulationConfig::Fsk(fsk) => {
+                self.set_modulation(Modulation::Fsk)?;
+                self.apply_common_config(config)?;
+                self.set_bitrate(fsk.bitrate)?;
+                self.set_frequency_deviation(fsk.frequency_deviation)?;
+                self.set_rx_bandwidth(fsk.rx_bandwidth)?;
+                self.set_afc_bandwidth(fsk.afc_bandwidth)
+            }
+        }
+    }
+
+    /// Apply the parameters that are independent of the modulation.
+    fn apply_common_config(&mut self, config: &RadioConfig) -> RnResult<(), E> {
+        self.set_frequency(config.frequency)?;
+        self.set_power(config.power)?;
+        self.set_crc(config.crc)?;
+        self.set_preamble_length(config.preamble_length)?;
+        self.set_watchdog_timeout(config.watchdog_timeout)
+    }
+
+    /// Read back the complete radio configuration.
+    pub fn read_config(&mut self) -> RnResult<RadioConfig, E> {
+        let modulation = match self.get_modulation()? {
+            Modulation::Lora => {
+                let mut sync_word = [0; 1];
+                base16::decode_slice(self.get_sync_word_hex()?, &mut sync_word)
+                    .map_err(|_| Error::ParsingError)?;
+                ModulationConfig::Lora(LoraConfig {
+                    spreading_factor: self.get_spreading_factor()?,
+                    bandwidth: self.get_bandwidth()?,
+                    coding_rate: self.get_coding_rate()?,
+                    iq_inversion: self.get_iq_inversion()?,
+                    sync_word: sync_word[0],
+                })
+            }
+            Modulation::Fsk => ModulationConfig::Fsk(FskConfig {
+                bitrate: self.get_bitrate()?,
+                frequency_deviation: self.get_frequency_deviation()?,
+                rx_bandwidth: self.get_rx_bandwidth()?,
+                afc_bandwidth: self.get_afc_bandwidth()?,
+            }),
+        };
+        Ok(RadioConfig {
+            frequency: self.get_frequency()?,
+            power: self.get_power()?,
+            crc: self.get_crc()?,
+            preamble_length: self.get_preamble_length()?,
+            watchdog_timeout: self.get_watchdog_timeout()?,
+            modulation,
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "embedded-hal-02")]
+mod tests {
+    use super::*;
+
+    use embedded_hal_mock::serial::{Mock as SerialMock, Transaction};
+
+    use crate::{rn2483_868, rn2903_915};
+
+    fn pause() -> [Transaction<u8>; 3] {
+        [
+            Transaction::write_many(b"mac pause\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"4294967245\r\n"),
+        ]
+    }
+
+    fn resume() -> [Transaction<u8>; 3] {
+        [
+            Transaction::write_many(b"mac resume\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+        ]
+    }
+
+    fn with_pause(transactions: Vec<Transaction<u8>>) -> Vec<Transaction<u8>> {
+        let mut expectations = Vec::from(pause());
+        expectations.extend(transactions);
+        expectations.extend(Vec::from(resume()));
+        expectations
+    }
+
+    #[test]
+    fn transmit_slice() {
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio tx 172a\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(b"radio_tx_ok\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(rn.radio().transmit_slice(&[23, 42]), Ok(()));
+        mock.done();
+    }
+
+    #[test]
+    fn transmit_without_auto_pause() {
+        let expectations = [
+            Transaction::write_many(b"radio tx 00\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"busy\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut radio = rn.radio();
+        radio.set_auto_pause(false);
+        assert_eq!(radio.transmit_hex("00"), Err(RadioError::Busy));
+        mock.done();
+    }
+
+    /// The MAC is resumed even if the transmission fails.
+    #[test]
+    fn transmit_radio_err() {
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio tx ff\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(b"radio_err\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(
+            rn.radio().transmit_hex("ff"),
+            Err(RadioError::TxUnsuccessful)
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn transmit_bad_parameter() {
+        let expectations = [];
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut radio = rn.radio();
+        assert_eq!(radio.transmit_hex(""), Err(RadioError::BadParameter));
+        assert_eq!(radio.transmit_hex("abc"), Err(RadioError::BadParameter));
+        assert_eq!(
+            radio.transmit_slice(&[0; 256]),
+            Err(RadioError::BadParameter)
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn mac_not_paused() {
+        let expectations = [
+            Transaction::write_many(b"mac pause\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"0\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(rn.radio().transmit_hex("00"), Err(RadioError::MacNotPaused));
+        mock.done();
+    }
+
+    mod mac_paused_guard {
+        use super::*;
+
+        #[test]
+        fn resume_on_drop() {
+            let expectations = with_pause(vec![
+                Transaction::write_many(b"radio tx 01\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"radio_tx_ok\r\n"),
+                Transaction::write_many(b"radio tx 02\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"ok\r\n"),
+                Transaction::read_many(b"radio_tx_ok\r\n"),
+            ]);
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            {
+                let mut paused = rn.pause_mac().unwrap();
+                assert_eq!(paused.duration(), Duration::from_millis(4294967245));
+                let mut radio = paused.radio();
+                assert_eq!(radio.transmit_slice(&[1]), Ok(()));
+                assert_eq!(radio.transmit_slice(&[2]), Ok(()));
+            }
+            mock.done();
+        }
+
+        #[test]
+        fn resume() {
+            let expectations = [
+                Transaction::write_many(b"mac pause\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"1000\r\n"),
+                Transaction::write_many(b"mac resume\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"invalid_param\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let paused = rn.pause_mac().unwrap();
+            assert_eq!(paused.duration(), Duration::from_secs(1));
+            assert_eq!(paused.resume(), Err(Error::CommandFailed));
+            mock.done();
+        }
+
+        #[test]
+        fn not_paused() {
+            let expectations = [
+                Transaction::write_many(b"mac pause\r\n"),
+                Transaction::flush(),
+                Transaction::read_many(b"0\r\n"),
+            ];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert!(matches!(rn.pause_mac(), Err(RadioError::MacNotPaused)));
+            mock.done();
+        }
+    }
+
+    /// Packets may be longer than the read buffer.
+    #[test]
+    fn receive_long_packet() {
+        let payload: Vec<u8> = (0..100).collect();
+        let mut line = b"radio_rx  ".to_vec();
+        let mut hex = [0; 200];
+        base16::encode_config_slice(&payload, base16::EncodeLower, &mut hex);
+        line.extend_from_slice(&hex);
+        line.extend_from_slice(b"\r\n");
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio rx 0\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(&line),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut buf = [0; 255];
+        assert_eq!(rn.radio().receive(0, &mut buf), Ok(&payload[..]));
+        mock.done();
+    }
+
+    #[test]
+    fn receive_radio_err() {
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio rx 1000\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(b"radio_err\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut buf = [0; 255];
+        assert_eq!(
+            rn.radio().receive(1000, &mut buf),
+            Err(RadioError::RxTimeout)
+        );
+        mock.done();
+    }
+
+    /// If the packet does not fit into the buffer, the rest of the line is
+    /// discarded and the MAC is still resumed.
+    #[test]
+    fn receive_buffer_too_small() {
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio rx 0\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(b"radio_rx  0102030405\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut buf = [0; 4];
+        assert_eq!(
+            rn.radio().receive(0, &mut buf),
+            Err(RadioError::BufferTooSmall)
+        );
+        mock.done();
+    }
+
+    /// A packet with an odd number of hex digits is rejected without
+    /// consuming the following response.
+    #[test]
+    fn receive_odd_length() {
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio rx 0\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(b"radio_rx  01020\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut buf = [0; 4];
+        assert_eq!(
+            rn.radio().receive(0, &mut buf),
+            Err(RadioError::Other(Error::ParsingError))
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn receive_invalid_response() {
+        let expectations = with_pause(vec![
+            Transaction::write_many(b"radio rx 0\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"invalid_param\r\n"),
+        ]);
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        let mut buf = [0; 4];
+        assert_eq!(
+            rn.radio().receive(0, &mut buf),
+            Err(RadioError::BadParameter)
+        );
+        mock.done();
+    }
+
+    #[test]
+    fn resume_failed() {
+        let expectations = [
+            Transaction::write_many(b"mac pause\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"4294967245\r\n"),
+            Transaction::write_many(b"radio tx 00\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+            Transaction::read_many(b"radio_tx_ok\r\n"),
+            Transaction::write_many(b"mac resume\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"invalid_param\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let mut rn = rn2483_868(mock.clone());
+        assert_eq!(
+            rn.radio().transmit_hex("00"),
+            Err(RadioError::Other(Error::CommandFailed))
+        );
+        mock.done();
+    }
+
+    mod config {
+        use super::*;
+
+        use crate::testing::command;
+
+        #[test]
+        fn set_frequency() {
+            let expectations = command(b"radio set freq 868100000\r\n", b"ok\r\n");
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.radio().set_frequency(868_100_000), Ok(()));
+            mock.done();
+        }
+
+        /// Frequencies outside of the band are rejected without
+        /// communicating with the module.
+        #[test]
+        fn set_frequency_out_of_band() {
+            let expectations = [];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.radio().set_frequency(915_000_000),
+                Err(Error::BadParameter)
+            );
+            let mut rn = rn2903_915(mock.clone());
+            assert_eq!(
+                rn.radio().set_frequency(868_100_000),
+                Err(Error::BadParameter)
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn set_power() {
+            let expectations = command(b"radio set pwr -3\r\n", b"ok\r\n");
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let mut radio = rn.radio();
+            assert_eq!(radio.set_power(-3), Ok(()));
+            assert_eq!(radio.set_power(16), Err(Error::BadParameter));
+            mock.done();
+        }
+
+        #[test]
+        fn set_power_rn2903() {
+            let expectations = command(b"radio set pwr 20\r\n", b"ok\r\n");
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2903_915(mock.clone());
+            let mut radio = rn.radio();
+            assert_eq!(radio.set_power(20), Ok(()));
+            assert_eq!(radio.set_power(1), Err(Error::BadParameter));
+            mock.done();
+        }
+
+        #[test]
+        fn get_spreading_factor() {
+            let expectations = command(b"radio get sf\r\n", b"sf12\r\n");
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.radio().get_spreading_factor(), Ok(SpreadingFactor::Sf12));
+            mock.done();
+        }
+
+        #[test]
+        fn get_rx_bandwidth() {
+            let expectations = command(b"radio get rxbw\r\n", b"250.0\r\n");
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.radio().get_rx_bandwidth(), Ok(RxBandwidth::Khz250));
+            mock.done();
+        }
+
+        #[test]
+        fn rx_bandwidth_roundtrip() {
+            for bw in &[
+                RxBandwidth::Khz250,
+                RxBandwidth::Khz83_3,
+                RxBandwidth::Khz25,
+                RxBandwidth::Khz2_6,
+            ] {
+                let val: &str = (*bw).into();
+                assert_eq!(RxBandwidth::try_from(val), Ok(*bw));
+            }
+        }
+
+        #[test]
+        fn set_sync_word_bad_length() {
+            let expectations = [];
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            let mut radio = rn.radio();
+            assert_eq!(radio.set_sync_word(&[]), Err(Error::BadParameter));
+            assert_eq!(radio.set_sync_word(&[0; 9]), Err(Error::BadParameter));
+            mock.done();
+        }
+
+        fn lora_config() -> RadioConfig {
+            RadioConfig {
+                frequency: 869_525_000,
+                power: 14,
+                crc: true,
+                preamble_length: 8,
+                watchdog_timeout: Duration::from_secs(15),
+                modulation: ModulationConfig::Lora(LoraConfig {
+                    spreading_factor: SpreadingFactor::Sf9,
+                    bandwidth: Bandwidth::Bw125,
+                    coding_rate: CodingRate::Cr4_5,
+                    iq_inversion: false,
+                    sync_word: 0x12,
+                }),
+            }
+        }
+
+        #[test]
+        fn apply_lora_config() {
+            let expectations: Vec<_> = vec![
+                command(b"radio set mod lora\r\n", b"ok\r\n"),
+                command(b"radio set freq 869525000\r\n", b"ok\r\n"),
+                command(b"radio set pwr 14\r\n", b"ok\r\n"),
+                command(b"radio set crc on\r\n", b"ok\r\n"),
+                command(b"radio set prlen 8\r\n", b"ok\r\n"),
+                command(b"radio set wdt 15000\r\n", b"ok\r\n"),
+                command(b"radio set sf sf9\r\n", b"ok\r\n"),
+                command(b"radio set bw 125\r\n", b"ok\r\n"),
+                command(b"radio set cr 4/5\r\n", b"ok\r\n"),
+                command(b"radio set iqi off\r\n", b"ok\r\n"),
+                command(b"radio set sync 12\r\n", b"ok\r\n"),
+            ]
+            .into_iter()
+            .flat_map(Vec::from)
+            .collect();
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(rn.radio().apply_config(&lora_config()), Ok(()));
+            mock.done();
+        }
+
+        #[test]
+        fn apply_config_rejected() {
+            let expectations: Vec<_> = vec![
+                command(b"radio set mod lora\r\n", b"ok\r\n"),
+                command(b"radio set freq 869525000\r\n", b"invalid_param\r\n"),
+            ]
+            .into_iter()
+            .flat_map(Vec::from)
+            .collect();
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.radio().apply_config(&lora_config()),
+                Err(Error::CommandFailed)
+            );
+            mock.done();
+        }
+
+        #[test]
+        fn read_fsk_config() {
+            let expectations: Vec<_> = vec![
+                command(b"radio get mod\r\n", b"fsk\r\n"),
+                command(b"radio get bitrate\r\n", b"50000\r\n"),
+                command(b"radio get fdev\r\n", b"25000\r\n"),
+                command(b"radio get rxbw\r\n", b"62.5\r\n"),
+                command(b"radio get afcbw\r\n", b"83.3\r\n"),
+                command(b"radio get freq\r\n", b"868100000\r\n"),
+                command(b"radio get pwr\r\n", b"1\r\n"),
+                command(b"radio get crc\r\n", b"off\r\n"),
+                command(b"radio get prlen\r\n", b"5\r\n"),
+                command(b"radio get wdt\r\n", b"0\r\n"),
+            ]
+            .into_iter()
+            .flat_map(Vec::from)
+            .collect();
+            let mut mock = SerialMock::new(&expectations);
+            let mut rn = rn2483_868(mock.clone());
+            assert_eq!(
+                rn.radio().read_config(),
+                Ok(RadioConfig {
+                    frequency: 868_100_000,
+                    power: 1,
+                    crc: false,
+                    preamble_length: 5,
+                    watchdog_timeout: Duration::from_secs(0),
+                    modulation: ModulationConfig::Fsk(FskConfig {
+                        bitrate: 50_000,
+                        frequency_deviation: 25_000,
+                        rx_bandwidth: RxBandwidth::Khz62_5,
+                        afc_bandwidth: RxBandwidth::Khz83_3,
+                    }),
+                })
+            );
+            mock.done();
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! The serial port interface of the blocking driver.
+//!
+//! The driver talks to the module through the [`Serial`](trait.Serial.html)
+//! trait. It is implemented for every serial port implementing the serial
+//! traits from `embedded-hal` 0.2 (if the `embedded-hal-02` feature is
+//! enabled, which it is by default), so these serial ports can be passed to
+//! the constructors in the crate root directly. Serial ports implementing the
+//! `embedded-io` traits are supported through the [`io`](../io/index.html)
+//! module.
+//!
+//! Some features need to read from the serial port without blocking:
+//! [`ensure_known_state`](../struct.Driver.html#method.ensure_known_state),
+//! [`poll_event`](../struct.Driver.html#method.poll_event) and read timeouts
+//! (see [`with_clock`](../struct.Driver.html#method.with_clock)). They are
+//! only available if the serial port implements
+//! [`TryRead`](trait.TryRead.html) as well.
+
+/// A serial port the driver can talk to the module through.
+pub trait Serial {
+    /// The error type of the serial port.
+    type Error;
+
+    /// Read at least one byte into the buffer, blocking until a byte is
+    /// available. Return the number of bytes read.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Write all bytes from the buffer, blocking until they are written.
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Block until all written bytes have been transmitted.
+    fn flush(&mut self) -> Result<(), Self::Error>;
+}
+
+/// A serial port that can be read without blocking.
+pub trait TryRead: Serial {
+    /// Read the bytes that are available right now into the buffer and return
+    /// the number of bytes read. If no bytes are available, return 0.
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+#[cfg(feature = "embedded-hal-02")]
+mod hal02 {
+    use embedded_hal::serial;
+    use nb::block;
+
+    use super::{Serial, TryRead};
+
+    impl<S, E> Serial for S
+    where
+        S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    {
+        type Error = E;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, E> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = block!(serial::Read::read(self))?;
+            Ok(1)
+        }
+
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), E> {
+            for byte in buf {
+                block!(serial::Write::write(self, *byte))?;
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), E> {
+            block!(serial::Write::flush(self))
+        }
+    }
+
+    impl<S, E> TryRead for S
+    where
+        S: serial::Read<u8, Error = E> + serial::Write<u8, Error = E>,
+    {
+        fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, E> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            match serial::Read::read(self) {
+                Ok(byte) => {
+                    buf[0] = byte;
+                    Ok(1)
+                }
+                Err(nb::Error::WouldBlock) => Ok(0),
+                Err(nb::Error::Other(e)) => Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Helpers shared by the unit tests of all modules.
+
+extern crate std;
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+#[cfg(feature = "embedded-hal-02")]
+use embedded_hal_mock::serial::Transaction;
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write};
+
+use crate::timeout::Clock;
+use crate::uart::{BaudRateControl, WakeUp};
+
+/// A scripted serial port that implements the `embedded-io` traits natively.
+///
+/// Reads return the configured chunks in order. If the read buffer is smaller
+/// than the chunk, the rest of the chunk is returned by the next read.
+///
+/// Writes, breaks (`<break>`) and baud rate changes (`<baud 9600>`) are
+/// collected and compared to the expected bytes when calling `done()`.
+///
+/// The first `ready` chunks are considered to be in the input buffer already.
+/// Writing a line makes the next chunk ready for reading.
+pub(crate) struct ScriptedSerial {
+    pub(crate) reads: VecDeque<Vec<u8>>,
+    pub(crate) written: Vec<u8>,
+    pub(crate) ready: usize,
+    pub(crate) baud_rate: u32,
+}
+
+impl ScriptedSerial {
+    pub(crate) fn new(reads: &[&[u8]]) -> Self {
+        Self {
+            reads: reads.iter().map(|chunk| chunk.to_vec()).collect(),
+            written: Vec::new(),
+            ready: 0,
+            baud_rate: 57600,
+        }
+    }
+
+    pub(crate) fn done(&self, expected_writes: &[u8]) {
+        assert!(self.reads.is_empty(), "Not all reads were consumed");
+        match (
+            std::str::from_utf8(&self.written),
+            std::str::from_utf8(expected_writes),
+        ) {
+            (Ok(written), Ok(expected)) => assert_eq!(written, expected),
+            _ => assert_eq!(self.written, expected_writes),
+        }
+    }
+
+    fn read_chunk(&mut self, buf: &mut [u8]) -> usize {
+        let mut chunk = self.reads.pop_front().expect("Unexpected read");
+        let count = buf.len().min(chunk.len());
+        buf[..count].copy_from_slice(&chunk[..count]);
+        if count < chunk.len() {
+            self.reads.push_front(chunk.split_off(count));
+        } else {
+            self.ready = self.ready.saturating_sub(1);
+        }
+        count
+    }
+
+    fn write_bytes(&mut self, buf: &[u8]) -> usize {
+        self.written.extend_from_slice(buf);
+        if buf.ends_with(b"\n") {
+            self.ready = self.ready.max(1);
+        }
+        buf.len()
+    }
+}
+
+impl ErrorType for ScriptedSerial {
+    type Error = ErrorKind;
+}
+
+impl Read for ScriptedSerial {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.read_chunk(buf))
+    }
+}
+
+impl Write for ScriptedSerial {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.write_bytes(buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl ReadReady for ScriptedSerial {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.ready > 0 && !self.reads.is_empty())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_io_async::Read for ScriptedSerial {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.read_chunk(buf))
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_io_async::Write for ScriptedSerial {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.write_bytes(buf))
+    }
+}
+
+impl WakeUp for ScriptedSerial {
+    type Error = ErrorKind;
+
+    fn send_break(&mut self) -> Result<(), Self::Error> {
+        self.written.extend_from_slice(b"<break>");
+        Ok(())
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+        self.written
+            .extend_from_slice(std::format!("<baud {}>", baud_rate).as_bytes());
+        self.baud_rate = baud_rate;
+        Ok(())
+    }
+}
+
+impl BaudRateControl for ScriptedSerial {
+    fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+}
+
+/// The transactions of a command that is answered with the specified
+/// response, for tests using `embedded-hal-mock`.
+#[cfg(feature = "embedded-hal-02")]
+pub(crate) fn command(command: &[u8], response: &[u8]) -> [Transaction<u8>; 3] {
+    [
+        Transaction::write_many(command),
+        Transaction::flush(),
+        Transaction::read_many(response),
+    ]
+}
+
+/// The transactions of a sequence of commands and their responses, see
+/// [`command`](fn.command.html).
+#[cfg(feature = "embedded-hal-02")]
+pub(crate) fn commands(commands: &[(&[u8], &[u8])]) -> Vec<Transaction<u8>> {
+    commands
+        .iter()
+        .flat_map(|(cmd, response)| command(cmd, response))
+        .collect()
+}
+
+/// The transactions of a `sys get ver` command that returns the specified
+/// version string.
+#[cfg(feature = "embedded-hal-02")]
+pub(crate) fn version(version: &[u8]) -> Vec<Transaction<u8>> {
+    vec![
+        Transaction::write_many(b"sys get ver\r\n"),
+        Transaction::flush(),
+        Transaction::read_many(version),
+        Transaction::read_many(b"\r\n"),
+    ]
+}
+
+/// A clock that advances by 500 ms every time it is queried.
+pub(crate) struct TestClock(pub(crate) u64);
+
+impl Clock for TestClock {
+    fn now_ms(&mut self) -> u64 {
+        let now = self.0;
+        self.0 += 500;
+        now
+    }
+}
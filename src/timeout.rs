@@ -0,0 +1,140 @@
+//! Read timeouts for the blocking driver.
+//!
+//! By default, the driver waits forever for a response from the module. To
+//! enforce deadlines, pass a [`Clock`](trait.Clock.html) implementation to
+//! [`Driver::with_clock`](../struct.Driver.html#method.with_clock). Every
+//! response will then have to arrive within the corresponding
+//! [`Timeouts`](struct.Timeouts.html) value, otherwise `Error::Timeout` is
+//! returned.
+//!
+//! Timeouts require a serial port that can be read without blocking (see
+//! [`TryRead`](../serial/trait.TryRead.html)).
+//!
+//! After a timeout, the state of the module is unknown. Call
+//! [`ensure_known_state`](../struct.Driver.html#method.ensure_known_state) to
+//! recover before sending the next command.
+
+use core::time::Duration;
+
+use crate::errors::{Error, RnResult};
+use crate::serial::{Serial, TryRead};
+use crate::utils;
+
+/// A monotonic time source.
+pub trait Clock {
+    /// Return the number of milliseconds elapsed since an arbitrary, fixed
+    /// point in time.
+    ///
+    /// The value must never decrease (but it may wrap around).
+    fn now_ms(&mut self) -> u64;
+}
+
+/// The default clock type parameter of the driver, used when no clock has
+/// been configured.
+///
+/// Without a clock, no timeouts are enforced.
+pub struct NoClock;
+
+/// Reads from the serial port, enforcing a read timeout.
+///
+/// This trait is implemented for [`NoClock`](struct.NoClock.html), which
+/// waits forever, and for every [`Clock`](trait.Clock.html) if the serial
+/// port implements [`TryRead`](../serial/trait.TryRead.html). There is no
+/// need to implement it yourself.
+pub trait Timer<S: Serial> {
+    /// Return the current time in milliseconds.
+    fn now_ms(&mut self) -> u64;
+
+    /// Read at least one byte into the buffer. If no byte arrives within
+    /// `timeout` after `start` (or forever, if `timeout` is `None`), return
+    /// `Error::Timeout`.
+    fn timed_read(
+        &mut self,
+        serial: &mut S,
+        buf: &mut [u8],
+        start: u64,
+        timeout: Option<Duration>,
+    ) -> RnResult<usize, S::Error>;
+}
+
+impl<S: Serial> Timer<S> for NoClock {
+    fn now_ms(&mut self) -> u64 {
+        0
+    }
+
+    fn timed_read(
+        &mut self,
+        serial: &mut S,
+        buf: &mut [u8],
+        _start: u64,
+        _timeout: Option<Duration>,
+    ) -> RnResult<usize, S::Error> {
+        serial.read(buf).map_err(Error::SerialRead)
+    }
+}
+
+impl<S: TryRead, C: Clock> Timer<S> for C {
+    fn now_ms(&mut self) -> u64 {
+        Clock::now_ms(self)
+    }
+
+    fn timed_read(
+        &mut self,
+        serial: &mut S,
+        buf: &mut [u8],
+        start: u64,
+        timeout: Option<Duration>,
+    ) -> RnResult<usize, S::Error> {
+        let timeout_ms = match timeout {
+            Some(timeout) => utils::duration_to_millis(timeout),
+            None => return serial.read(buf).map_err(Error::SerialRead),
+        };
+        loop {
+            let count = serial.try_read(buf).map_err(Error::SerialRead)?;
+            if count > 0 {
+                return Ok(count);
+            }
+            if Clock::now_ms(self).wrapping_sub(start) >= timeout_ms {
+                #[cfg(feature = "logging")]
+                log::debug!("Timeout while waiting for response");
+                return Err(Error::Timeout);
+            }
+        }
+    }
+}
+
+/// The maximum time to wait for a response from the module.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Timeouts {
+    /// Timeout for regular command responses (e.g. the response to `sys get
+    /// ver`, or the first response to `mac join` and `mac tx`).
+    pub command: Duration,
+    /// Timeout for the second response to `mac join`, indicating whether the
+    /// join procedure succeeded.
+    pub join: Duration,
+    /// Timeout for the second response to `mac tx`, which arrives after the
+    /// receive windows have been closed. For confirmed uplinks, this includes
+    /// all retransmissions.
+    pub tx: Duration,
+    /// Timeout for the second response to `radio tx` and `radio rx`. It
+    /// should be longer than the radio watchdog timeout (15 seconds by
+    /// default). Continuous reception is never subject to a timeout.
+    pub radio: Duration,
+}
+
+impl Default for Timeouts {
+    /// Return the default timeouts:
+    ///
+    /// - Command: 2 seconds
+    /// - Join: 30 seconds
+    /// - Transmit: 60 seconds
+    /// - Radio: 20 seconds
+    fn default() -> Self {
+        Self {
+            command: Duration::from_secs(2),
+            join: Duration::from_secs(30),
+            tx: Duration::from_secs(60),
+            radio: Duration::from_secs(20),
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Typestate layer over the [`Driver`](../struct.Driver.html).
+//!
+//! The handles in this module track the state of the module in the type
+//! system, so that commands which are invalid in the current state (e.g.
+//! transmitting an uplink before joining, or sending commands while the
+//! module is sleeping) are rejected at compile time:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use core::time::Duration;
+//! use rn2xx3::typestate::Idle;
+//! use rn2xx3::{ConfirmationMode, JoinMode};
+//!
+//! let mut idle = Idle::new(rn2xx3::rn2483_868(serialport));
+//! idle.driver()
+//!     .set_app_eui_hex("0011223344556677")
+//!     .expect("Could not set app EUI");
+//!
+//! // Only a joined handle can transmit uplinks
+//! let mut joined = idle.join(JoinMode::Otaa).expect("Could not join");
+//! joined
+//!     .transmit_slice(ConfirmationMode::Unconfirmed, 1, &[23, 42])
+//!     .expect("Could not transmit data");
+//!
+//! // A sleeping handle can only wait for the wakeup
+//! let sleeping = joined.sleep(Duration::from_secs(10)).expect("Could not sleep");
+//! let joined = sleeping.wait_for_wakeup(false).expect("Could not wake up");
+//! ```
+//!
+//! If the module has already joined a network (e.g. after a reset of the
+//! microcontroller), use [`Idle::into_joined`](struct.Idle.html#method.into_joined)
+//! to check the MAC status and obtain a joined handle without joining again.
+//!
+//! Failed transitions return the previous handle together with the error in
+//! a [`TransitionError`](../errors/struct.TransitionError.html), so the
+//! driver is never lost. The plain `Driver` API stays available, either
+//! directly or through the `driver()` escape hatch of the awake handles.
+
+// Failed transitions return the driver by value, just like successful ones,
+// and boxing is not possible without an allocator.
+#![allow(clippy::result_large_err)]
+
+use core::time::Duration;
+
+use crate::errors::{Error, JoinError, RadioError, TransitionError, TxError};
+use crate::radio::Radio;
+use crate::serial::Serial;
+use crate::timeout::{NoClock, Timer};
+use crate::{ConfirmationMode, Downlink, Driver, Frequency, JoinMode};
+
+mod sealed {
+    use crate::serial::Serial;
+    use crate::timeout::Timer;
+    use crate::{Driver, Frequency};
+
+    /// A state in which the module is awake and the MAC is running.
+    pub trait Awake {
+        type Frequency: Frequency;
+        type Serial: Serial;
+        type Clock: Timer<Self::Serial>;
+
+        fn into_driver(self) -> Driver<Self::Frequency, Self::Serial, Self::Clock>;
+        fn driver(&mut self) -> &mut Driver<Self::Frequency, Self::Serial, Self::Clock>;
+    }
+}
+
+/// The module is awake and has not joined a network.
+pub struct Idle<F: Frequency, S, C = NoClock> {
+    driver: Driver<F, S, C>,
+}
+
+/// The module is awake and has joined a network.
+pub struct Joined<F: Frequency, S, C = NoClock> {
+    driver: Driver<F, S, C>,
+}
+
+/// The module is sleeping. `P` is the state the module returns to after
+/// waking up.
+pub struct Sleeping<P> {
+    state: P,
+}
+
+/// The LoRaWAN MAC is paused and the radio can be used. `P` is the state the
+/// module returns to after resuming the MAC.
+pub struct MacPaused<P> {
+    state: P,
+    duration: Duration,
+}
+
+/// Macro to implement the transitions that are valid in all awake states.
+macro_rules! awake_state {
+    ($state:ident) => {
+        impl<F: Frequency, S: Serial, C: Timer<S>> sealed::Awake for $state<F, S, C> {
+            type Frequency = F;
+            type Serial = S;
+            type Clock = C;
+
+            fn into_driver(self) -> Driver<F, S, C> {
+                self.driver
+            }
+
+            fn driver(&mut self) -> &mut Driver<F, S, C> {
+                &mut self.driver
+            }
+        }
+
+        impl<F: Frequency, S, C> $state<F, S, C> {
+            /// Return the wrapped driver.
+            pub fn into_driver(self) -> Driver<F, S, C> {
+                self.driver
+            }
+
+            /// Return the wrapped driver, e.g. to change the configuration.
+            ///
+            /// Changing the state through the driver (e.g. by calling
+            /// `sleep` or `join`) bypasses the checks of this module.
+            pub fn driver(&mut self) -> &mut Driver<F, S, C> {
+                &mut self.driver
+            }
+        }
+
+        impl<F, S, C, E> $state<F, S, C>
+        where
+            S: Serial<Error = E>,
+            F: Frequency,
+            C: Timer<S>,
+        {
+            /// Put the module to sleep, see
+            /// [`Driver::sleep`](../struct.Driver.html#method.sleep).
+            pub fn sleep(
+                mut self,
+                duration: Duration,
+            ) -> Result<Sleeping<Self>, TransitionError<Self, Error<E>>> {
+                match self.driver.sleep(duration) {
+                    Ok(()) => Ok(Sleeping { state: self }),
+                    Err(error) => Err(TransitionError { state: self, error }),
+                }
+            }
+
+            /// Pause the LoRaWAN MAC to use the radio.
+            ///
+            /// If the MAC cannot be paused, `RadioError::MacNotPaused` is
+            /// returned.
+            pub fn pause_mac(
+                mut self,
+            ) -> Result<MacPaused<Self>, TransitionError<Self, RadioError<E>>> {
+                match self.driver.send_mac_pause() {
+                    Ok(duration) => Ok(MacPaused {
+                        state: self,
+                        duration,
+                    }),
+                    Err(error) => Err(TransitionError { state: self, error }),
+                }
+            }
+        }
+    };
+}
+
+awake_state!(Idle);
+awake_state!(Joined);
+
+impl<F: Frequency, S, C> Idle<F, S, C> {
+    /// Wrap a driver for a module that is awake and has not joined a network.
+    pub fn new(driver: Driver<F, S, C>) -> Self {
+        Self { driver }
+    }
+}
+
+impl<F, S, C, E> Idle<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Join the network.
+    pub fn join(
+        mut self,
+        mode: JoinMode,
+    ) -> Result<Joined<F, S, C>, TransitionError<Self, JoinError<E>>> {
+        match self.driver.join(mode) {
+            Ok(()) => Ok(Joined {
+                driver: self.driver,
+            }),
+            Err(error) => Err(TransitionError { state: self, error }),
+        }
+    }
+
+    /// Return a joined handle if the module has already joined a network,
+    /// e.g. after the microcontroller has been reset while the module kept
+    /// its session.
+    ///
+    /// The joined flag of the [MAC status](../struct.Driver.html#method.mac_status)
+    /// is checked. If the module has not joined a network,
+    /// `Error::InvalidState` is returned.
+    pub fn into_joined(mut self) -> Result<Joined<F, S, C>, TransitionError<Self, Error<E>>> {
+        match self.driver.mac_status() {
+            Ok(status) if status.joined => Ok(Joined {
+                driver: self.driver,
+            }),
+            Ok(_) => Err(TransitionError {
+                state: self,
+                error: Error::InvalidState,
+            }),
+            Err(error) => Err(TransitionError { state: self, error }),
+        }
+    }
+}
+
+impl<F, S, C, E> Joined<F, S, C>
+where
+    S: Serial<Error = E>,
+    F: Frequency,
+    C: Timer<S>,
+{
+    /// Send a hex uplink on the specified port, see
+    /// [`Driver::transmit_hex`](../struct.Driver.html#method.transmit_hex).
+    pub fn transmit_hex(
+        &mut self,
+        mode: ConfirmationMode,
+        port: u8,
+        data: &str,
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        self.driver.transmit_hex(mode, port, data)
+    }
+
+    /// Send an uplink on the specified port, see
+    /// [`Driver::transmit_slice`](../struct.Driver.html#method.transmit_slice).
+    pub fn transmit_slice(
+        &mut self,
+        mode: ConfirmationMode,
+        port: u8,
+        data: &[u8],
+    ) -> Result<Option<Downlink<'_>>, TxError<E>> {
+        self.driver.transmit_slice(mode, port, data)
+    }
+}
+
+impl<P, E> Sleeping<P>
+where
+    P: sealed::Awake,
+    P::Serial: Serial<Error = E>,
+{
+    /// Wait for the module to wake up, see
+    /// [`Driver::wait_for_wakeup`](../struct.Driver.html#method.wait_for_wakeup).
+    ///
+    /// If the module responds with something else than "ok", the error is
+    /// returned, but the module is awake anyway. Calling this method again
+    /// will then return immediately.
+    pub fn wait_for_wakeup(mut self, force: bool) -> Result<P, TransitionError<Self, Error<E>>> {
+        match self.state.driver().wait_for_wakeup(force) {
+            Ok(()) => Ok(self.state),
+            Err(error) => Err(TransitionError { state: self, error }),
+        }
+    }
+}
+
+impl<P, E> MacPaused<P>
+where
+    P: sealed::Awake,
+    P::Serial: Serial<Error = E>,
+{
+    /// Return how long the MAC is paused for, as reported by the module.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Return a handle for direct access to the radio.
+    pub fn radio(&mut self) -> Radio<'_, P::Frequency, P::Serial, P::Clock> {
+        let mut radio = self.state.driver().radio();
+        radio.set_auto_pause(false);
+        radio
+    }
+
+    /// Resume the LoRaWAN MAC.
+    pub fn resume(mut self) -> Result<P, TransitionError<Self, Error<E>>> {
+        match self.state.driver().send_raw_command_ok(&["mac resume"]) {
+            Ok(()) => Ok(self.state),
+            Err(error) => Err(TransitionError { state: self, error }),
+        }
+    }
+
+    /// Return the wrapped driver, without resuming the MAC.
+    pub fn into_driver(self) -> Driver<P::Frequency, P::Serial, P::Clock> {
+        self.state.into_driver()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "embedded-hal-02")]
+mod tests {
+    use super::*;
+
+    use embedded_hal_mock::serial::{Mock as SerialMock, Transaction};
+
+    use crate::rn2483_868;
+
+    #[test]
+    fn join_transmit_sleep() {
+        let expectations = [
+            Transaction::write_many(b"mac join otaa\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\naccepted\r\n"),
+            Transaction::write_many(b"mac tx uncnf 1 172a\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\nmac_tx_ok\r\n"),
+            Transaction::write_many(b"sys sleep 1000\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let idle = Idle::new(rn2483_868(mock.clone()));
+        let mut joined = idle.join(JoinMode::Otaa).unwrap();
+        assert_eq!(
+            joined.transmit_slice(ConfirmationMode::Unconfirmed, 1, &[23, 42]),
+            Ok(None)
+        );
+        let sleeping = joined.sleep(Duration::from_secs(1)).unwrap();
+        let _joined: Joined<_, _> = sleeping.wait_for_wakeup(false).unwrap();
+        mock.done();
+    }
+
+    /// A failed join returns the idle handle.
+    #[test]
+    fn join_denied() {
+        let expectations = [
+            Transaction::write_many(b"mac join otaa\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\ndenied\r\n"),
+            Transaction::write_many(b"mac join otaa\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\naccepted\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let idle = Idle::new(rn2483_868(mock.clone()));
+        let failed = idle.join(JoinMode::Otaa).err().unwrap();
+        assert_eq!(failed.error, JoinError::JoinUnsuccessful);
+        assert!(failed.state.join(JoinMode::Otaa).is_ok());
+        mock.done();
+    }
+
+    #[test]
+    fn into_joined() {
+        let expectations = [
+            Transaction::write_many(b"mac get status\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"00000000\r\n"),
+            Transaction::write_many(b"mac get status\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"00000001\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let idle = Idle::new(rn2483_868(mock.clone()));
+        let failed = idle.into_joined().err().unwrap();
+        assert_eq!(failed.error, Error::InvalidState);
+        assert!(failed.state.into_joined().is_ok());
+        mock.done();
+    }
+
+    #[test]
+    fn sleep_invalid_duration() {
+        let mut mock = SerialMock::new(&[]);
+        let idle = Idle::new(rn2483_868(mock.clone()));
+        let failed = idle.sleep(Duration::from_millis(10)).err().unwrap();
+        assert_eq!(failed.error, Error::BadParameter);
+        mock.done();
+    }
+
+    #[test]
+    fn pause_mac() {
+        let expectations = [
+            Transaction::write_many(b"mac pause\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"1000\r\n"),
+            Transaction::write_many(b"radio tx 01\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\nradio_tx_ok\r\n"),
+            Transaction::write_many(b"mac resume\r\n"),
+            Transaction::flush(),
+            Transaction::read_many(b"ok\r\n"),
+        ];
+        let mut mock = SerialMock::new(&expectations);
+        let idle = Idle::new(rn2483_868(mock.clone()));
+        let mut paused = idle.pause_mac().unwrap();
+        assert_eq!(paused.duration(), Duration::from_secs(1));
+        assert_eq!(paused.radio().transmit_hex("01"), Ok(()));
+        let _idle: Idle<_, _> = paused.resume().unwrap();
+        mock.done();
+    }
+}
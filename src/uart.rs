@@ -0,0 +1,189 @@
+//! Control of the UART line beyond reading and writing bytes.
+//!
+//! The RN modules detect the baud rate of the UART automatically when they
+//! receive a break condition followed by the character `0x55`. This is also
+//! the only way to wake up a module early from
+//! [`sleep`](../struct.Driver.html#method.sleep).
+//!
+//! The serial traits cannot send a break condition, so the serial port
+//! (or a wrapper around it) has to implement the [`WakeUp`](trait.WakeUp.html)
+//! trait to use these features:
+//!
+//! ```no_run
+//! # use linux_embedded_hal::Serial;
+//! # let port = serial::open("/dev/ttyACM0").expect("Could not open serial port");
+//! # let serialport = Serial(port);
+//! use core::time::Duration;
+//! use rn2xx3::uart::WakeUp;
+//!
+//! struct BreakingSerial(Serial);
+//!
+//! impl WakeUp for BreakingSerial {
+//!     // ...
+//! #   type Error = std::io::ErrorKind;
+//! #   fn send_break(&mut self) -> Result<(), Self::Error> { unimplemented!() }
+//! #   fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> { unimplemented!() }
+//! }
+//! # impl embedded_hal::serial::Read<u8> for BreakingSerial {
+//! #     type Error = std::io::ErrorKind;
+//! #     fn read(&mut self) -> nb::Result<u8, Self::Error> { unimplemented!() }
+//! # }
+//! # impl embedded_hal::serial::Write<u8> for BreakingSerial {
+//! #     type Error = std::io::ErrorKind;
+//! #     fn write(&mut self, _: u8) -> nb::Result<(), Self::Error> { unimplemented!() }
+//! #     fn flush(&mut self) -> nb::Result<(), Self::Error> { unimplemented!() }
+//! # }
+//!
+//! let mut rn = rn2xx3::rn2483_868(BreakingSerial(serialport));
+//! rn.sleep(Duration::from_secs(3600)).expect("Could not sleep");
+//! // ...
+//! rn.wake_now().expect("Could not wake up module");
+//! ```
+//!
+//! To change the baud rate with
+//! [`change_baud_rate`](../struct.Driver.html#method.change_baud_rate), the
+//! serial port has to implement the
+//! [`BaudRateControl`](trait.BaudRateControl.html) trait as well, so that the
+//! previous baud rate can be restored if the module does not respond.
+//!
+//! The [`FromIo`](../io/struct.FromIo.html) adapter implements `WakeUp` and
+//! `BaudRateControl` if the wrapped serial port does.
+
+/// A serial port that can send a break condition and change its baud rate.
+pub trait WakeUp {
+    /// The error type of the serial port.
+    type Error;
+
+    /// Send a break condition, i.e. hold the TX line low for longer than a
+    /// full character.
+    fn send_break(&mut self) -> Result<(), Self::Error>;
+
+    /// Change the baud rate of the serial port.
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error>;
+}
+
+/// A serial port that can also report its current baud rate, see
+/// [`change_baud_rate`](../struct.Driver.html#method.change_baud_rate).
+pub trait BaudRateControl: WakeUp {
+    /// Return the current baud rate of the serial port.
+    fn baud_rate(&self) -> u32;
+}
+
+impl<T: WakeUp + ?Sized> WakeUp for &mut T {
+    type Error = T::Error;
+
+    fn send_break(&mut self) -> Result<(), Self::Error> {
+        T::send_break(self)
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+        T::set_baud_rate(self, baud_rate)
+    }
+}
+
+impl<T: BaudRateControl + ?Sized> BaudRateControl for &mut T {
+    fn baud_rate(&self) -> u32 {
+        T::baud_rate(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{ScriptedSerial, TestClock};
+
+    #[test]
+    fn wake_now() {
+        let serial = ScriptedSerial::new(&[b"ok\r\n", b"invalid_param\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        rn.sleep(core::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(rn.version(), Err(crate::errors::Error::SleepMode));
+        rn.wake_now().unwrap();
+        rn.free()
+            .into_inner()
+            .done(b"sys sleep 60000\r\n<break>\x55z\r\n");
+    }
+
+    #[test]
+    fn wake_now_not_sleeping() {
+        let serial = ScriptedSerial::new(&[b"invalid_param\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        rn.wake_now().unwrap();
+        rn.free().into_inner().done(b"<break>\x55z\r\n");
+    }
+
+    #[test]
+    fn wake_now_unexpected_response() {
+        let serial = ScriptedSerial::new(&[b"invalid_param\r\n", b"invalid_param\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        rn.sleep(core::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(rn.wake_now(), Err(crate::errors::Error::ParsingError));
+        // The module is awake anyway
+        assert_eq!(rn.wait_for_wakeup(false), Ok(()));
+        rn.free()
+            .into_inner()
+            .done(b"sys sleep 60000\r\n<break>\x55z\r\n");
+    }
+
+    #[test]
+    fn wake_now_timeout() {
+        // The module does not respond to the break condition
+        let serial = ScriptedSerial::new(&[b"", b"invalid_param\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial).with_clock(TestClock(0));
+        rn.sleep(core::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(rn.wake_now(), Err(crate::errors::Error::Timeout));
+        // The sleep flag is cleared and the module has been recovered anyway
+        assert_eq!(rn.wait_for_wakeup(false), Ok(()));
+        rn.free()
+            .into_inner()
+            .done(b"sys sleep 60000\r\n<break>\x55z\r\n");
+    }
+
+    #[test]
+    fn change_baud_rate() {
+        let serial = ScriptedSerial::new(&[b"RN2483 1.0.5 Oct 31 2018 15:06:52\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        rn.change_baud_rate(115200).unwrap();
+        let serial = rn.free().into_inner();
+        assert_eq!(serial.baud_rate, 115200);
+        serial.done(b"<break><baud 115200>\x55sys get ver\r\n");
+    }
+
+    #[test]
+    fn change_baud_rate_rollback() {
+        let serial = ScriptedSerial::new(&[b"~\x00~\r\n", b"invalid_param\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        assert_eq!(
+            rn.change_baud_rate(115200),
+            Err(crate::errors::Error::ParsingError)
+        );
+        let serial = rn.free().into_inner();
+        assert_eq!(serial.baud_rate, 57600);
+        serial.done(b"<break><baud 115200>\x55sys get ver\r\n<break><baud 57600>\x55z\r\n");
+    }
+
+    #[test]
+    fn change_baud_rate_rollback_failed() {
+        let serial = ScriptedSerial::new(&[b"~\x00~\r\n", b"?\r\n", b"?\r\n", b"?\r\n"]);
+        let mut rn = crate::io::rn2483_868(serial);
+        // The original error is returned, not the one of the recovery
+        assert_eq!(
+            rn.change_baud_rate(115200),
+            Err(crate::errors::Error::ParsingError)
+        );
+        rn.free()
+            .into_inner()
+            .done(b"<break><baud 115200>\x55sys get ver\r\n<break><baud 57600>\x55z\r\nz\r\nz\r\n");
+    }
+
+    #[test]
+    fn change_baud_rate_sleeping() {
+        let serial = ScriptedSerial::new(&[]);
+        let mut rn = crate::io::rn2483_868(serial);
+        rn.sleep(core::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(
+            rn.change_baud_rate(115200),
+            Err(crate::errors::Error::SleepMode)
+        );
+        rn.free().into_inner().done(b"sys sleep 60000\r\n");
+    }
+}
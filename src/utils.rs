@@ -1,4 +1,5 @@
 use core::str::from_utf8;
+use core::time::Duration;
 
 use crate::errors::{Error, RnResult};
 
@@ -54,8 +55,16 @@ pub(crate) fn ltrim_hex(val: &str) -> &str {
     }
 }
 
+/// Convert a duration to milliseconds, saturating at `u64::MAX`.
+pub(crate) fn duration_to_millis(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(duration.subsec_millis()))
+}
+
 pub(crate) fn validate_port<T>(port: u8, err: T) -> Result<(), T> {
-    if port >= 1 && port <= 223 {
+    if (1..=223).contains(&port) {
         Ok(())
     } else {
         Err(err)